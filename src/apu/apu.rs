@@ -0,0 +1,370 @@
+use crate::{
+    apu::{
+        dmc::Dmc,
+        frame_counter::{FrameCounter, FrameEvent},
+        mixer::{Channel, ChannelLevels, Mixer},
+        pulse::Pulse,
+        sunsoft_5b::Sunsoft5bAudio,
+        vrc6::Vrc6Audio,
+        waveform::WaveformHistory,
+    },
+    console::region::Region,
+};
+use bitflags::bitflags;
+
+/* APU registers exposed to the CPU, relative to $4000 */
+pub const PULSE_1_ADDRESS_LO: u16 = 0x0000;
+pub const PULSE_1_ADDRESS_HI: u16 = 0x0003;
+pub const PULSE_2_ADDRESS_LO: u16 = 0x0004;
+pub const PULSE_2_ADDRESS_HI: u16 = 0x0007;
+pub const DMC_ADDRESS_LO: u16 = 0x0010;
+pub const DMC_ADDRESS_HI: u16 = 0x0013;
+pub const STATUS_ADDRESS: u16 = 0x0015;
+pub const FRAME_COUNTER_ADDRESS: u16 = 0x0017;
+
+/* Number of sound channels of the APU */
+pub const CHANNEL_COUNT: usize = Channel::ALL.len();
+
+/*
+ * Each bit indicates a sound chip a cartridge adds to the
+ * console, in the order used by NSF files
+ */
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ExpansionChips: u8 {
+        const VRC6    = 0b0000_0001;
+        const VRC7    = 0b0000_0010;
+        const FDS     = 0b0000_0100;
+        const MMC5    = 0b0000_1000;
+        const N163    = 0b0001_0000;
+        const SUNSOFT = 0b0010_0000;
+    }
+}
+
+/*
+ * The Audio Processing Unit, generating the sound of the
+ * console alongside the CPU.
+ *
+ * It is clocked once per CPU cycle, most of its units only
+ * moving every other cycle. The slower envelope, sweep and
+ * length counter updates happen on quarter and half frames.
+ */
+pub struct APU {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+
+    /* Sound chips of the cartridge, mixed with the console channels */
+    vrc6: Option<Vrc6Audio>,
+    sunsoft_5b: Option<Sunsoft5bAudio>,
+
+    mixer: Mixer,
+    /* Mixed output of the channels after the last cycle */
+    sample: f32,
+    /* Left and right outputs, only mixed while the mixer is in stereo */
+    stereo_sample: (f32, f32),
+    waveforms: WaveformHistory,
+
+    /* Whether the current CPU cycle is the second half of an APU cycle */
+    odd_cycle: bool,
+}
+
+impl APU {
+    /* Initializes a new APU */
+    pub fn new() -> Self {
+        Self {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            dmc: Dmc::new(),
+            frame_counter: FrameCounter::new(),
+            vrc6: None,
+            sunsoft_5b: None,
+            mixer: Mixer::new(),
+            sample: 0.0,
+            stereo_sample: (0.0, 0.0),
+            waveforms: WaveformHistory::new(),
+            odd_cycle: false,
+        }
+    }
+
+    /* Writes to one of the APU registers */
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            PULSE_1_ADDRESS_LO..=PULSE_1_ADDRESS_HI => {
+                self.pulse_1.write(address - PULSE_1_ADDRESS_LO, value)
+            }
+            PULSE_2_ADDRESS_LO..=PULSE_2_ADDRESS_HI => {
+                self.pulse_2.write(address - PULSE_2_ADDRESS_LO, value)
+            }
+            DMC_ADDRESS_LO..=DMC_ADDRESS_HI => self.dmc.write(address - DMC_ADDRESS_LO, value),
+            STATUS_ADDRESS => self.write_status(value),
+            FRAME_COUNTER_ADDRESS => self.frame_counter.write(value, self.odd_cycle),
+            _ => {}
+        }
+    }
+
+    /*
+     * Adds the sound chips of the cartridge to the console.
+     * Only the VRC6 and 5B are emulated, the other chips stay
+     * silent.
+     */
+    pub fn set_expansion_chips(&mut self, chips: ExpansionChips) {
+        self.vrc6 = chips.contains(ExpansionChips::VRC6).then(Vrc6Audio::new);
+        self.sunsoft_5b = chips
+            .contains(ExpansionChips::SUNSOFT)
+            .then(Sunsoft5bAudio::new);
+    }
+
+    /* Writes to the sound registers of the cartridge, given as CPU addresses */
+    pub fn write_expansion(&mut self, address: u16, value: u8) {
+        if let Some(vrc6) = self.vrc6.as_mut() {
+            vrc6.write(address, value);
+        }
+
+        if let Some(sunsoft_5b) = self.sunsoft_5b.as_mut() {
+            sunsoft_5b.write(address, value);
+        }
+    }
+
+    /*
+     * Reads $4015, holding the length counter status of each
+     * channel along with the frame and DMC interrupt flags.
+     * Reading it acknowledges the frame interrupt. The triangle
+     * and noise channels are not emulated, so their bits (2 and
+     * 3) always read 0, as if their length counters were over.
+     */
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+
+        if self.pulse_1.is_active() {
+            status |= 0x01;
+        }
+        if self.pulse_2.is_active() {
+            status |= 0x02;
+        }
+        if self.dmc.is_active() {
+            status |= 0x10;
+        }
+        if self.frame_counter.irq_flag() {
+            status |= 0x40;
+        }
+        if self.dmc.irq_flag() {
+            status |= 0x80;
+        }
+
+        self.frame_counter.clear_irq_flag();
+
+        status
+    }
+
+    /*
+     * Writes $4015, enabling or disabling each channel. Disabled
+     * channels are silenced right away, while enabling the DMC
+     * restarts its sample if it was over.
+     */
+    fn write_status(&mut self, value: u8) {
+        self.pulse_1.set_enabled(value & 0x01 != 0);
+        self.pulse_2.set_enabled(value & 0x02 != 0);
+        self.dmc.set_enabled(value & 0x10 != 0);
+    }
+
+    /*
+     * Presses the reset button, silencing every channel as if
+     * $4015 was cleared and restarting the frame sequence in
+     * its current mode
+     */
+    pub fn reset(&mut self) {
+        self.write_status(0x00);
+        self.frame_counter.reset(self.odd_cycle);
+    }
+
+    /* Switches the APU to the timing of another console region */
+    pub fn set_region(&mut self, region: Region) {
+        self.dmc.set_region(region);
+        self.frame_counter.set_region(region);
+    }
+
+    /* Advances the APU by a single CPU cycle */
+    pub fn clock(&mut self) {
+        match self.frame_counter.clock() {
+            FrameEvent::QuarterFrame => self.clock_quarter_frame(),
+            FrameEvent::HalfFrame => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            FrameEvent::None => {}
+        }
+
+        self.dmc.clock_timer();
+
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+
+        self.odd_cycle = !self.odd_cycle;
+
+        if let Some(vrc6) = self.vrc6.as_mut() {
+            vrc6.clock();
+        }
+
+        if let Some(sunsoft_5b) = self.sunsoft_5b.as_mut() {
+            sunsoft_5b.clock();
+        }
+
+        let levels = self.get_channel_levels();
+
+        self.sample = self.mixer.mix(&levels);
+
+        if self.mixer.is_stereo() {
+            self.stereo_sample = self.mixer.mix_stereo(&levels);
+        }
+
+        self.waveforms.push(&levels);
+    }
+
+    /* Mixed output of all channels, between 0.0 and 1.0 */
+    pub fn sample(&self) -> f32 {
+        self.sample
+    }
+
+    /* Left and right outputs of all channels, when the mixer is in stereo */
+    pub fn stereo_sample(&self) -> (f32, f32) {
+        self.stereo_sample
+    }
+
+    /*
+     * Output of each channel alone through the mixer, in the
+     * order of Channel::ALL
+     */
+    pub fn channel_samples(&self) -> [f32; CHANNEL_COUNT] {
+        let levels = self.get_channel_levels();
+
+        Channel::ALL.map(|channel| {
+            let mut channel_levels = [0.0; CHANNEL_COUNT];
+            channel_levels[channel as usize] = levels[channel as usize];
+
+            self.mixer.mix(&channel_levels)
+        })
+    }
+
+    /*
+     * Output level of every channel, in the order of Channel::ALL.
+     * The triangle and noise channels are not emulated, they stay
+     * silent.
+     */
+    fn get_channel_levels(&self) -> ChannelLevels {
+        let mut levels = [0.0; CHANNEL_COUNT];
+
+        levels[Channel::Pulse1 as usize] = self.pulse_1.output() as f32;
+        levels[Channel::Pulse2 as usize] = self.pulse_2.output() as f32;
+        levels[Channel::Dmc as usize] = self.dmc.output() as f32;
+
+        if let Some(vrc6) = self.vrc6.as_ref() {
+            levels[Channel::Vrc6Pulse1 as usize] = vrc6.pulse_1().output() as f32;
+            levels[Channel::Vrc6Pulse2 as usize] = vrc6.pulse_2().output() as f32;
+            levels[Channel::Vrc6Sawtooth as usize] = vrc6.sawtooth().output() as f32;
+        }
+
+        if let Some(sunsoft_5b) = self.sunsoft_5b.as_ref() {
+            levels[Channel::Sunsoft5bA as usize] = sunsoft_5b.channel(0).output();
+            levels[Channel::Sunsoft5bB as usize] = sunsoft_5b.channel(1).output();
+            levels[Channel::Sunsoft5bC as usize] = sunsoft_5b.channel(2).output();
+        }
+
+        levels
+    }
+
+    /*
+     * Specifies whether the frame counter or the DMC is asking
+     * for an interrupt. The former is acknowledged by reading
+     * $4015 and the latter by writing it.
+     */
+    pub fn is_irq_pending(&self) -> bool {
+        self.frame_counter.irq_flag() || self.dmc.irq_flag()
+    }
+
+    /* Clocks the envelopes, four times per frame */
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+    }
+
+    /* Clocks the length counters and sweeps, twice per frame */
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+    }
+
+    /* Gives access to the mixer, holding the volume of each channel */
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    /* Gives mutable access to the mixer */
+    pub fn mixer_mut(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+
+    /* Gives access to the VRC6 sound chip, if the cartridge has one */
+    pub fn vrc6(&self) -> Option<&Vrc6Audio> {
+        self.vrc6.as_ref()
+    }
+
+    /* Gives access to the 5B sound chip, if the cartridge has one */
+    pub fn sunsoft_5b(&self) -> Option<&Sunsoft5bAudio> {
+        self.sunsoft_5b.as_ref()
+    }
+
+    /* Gives access to the recent output of each channel */
+    pub fn waveforms(&self) -> &WaveformHistory {
+        &self.waveforms
+    }
+
+    /* Gives mutable access to the recent output of each channel */
+    pub fn waveforms_mut(&mut self) -> &mut WaveformHistory {
+        &mut self.waveforms
+    }
+
+    /* Gives access to the frame counter */
+    pub fn frame_counter(&self) -> &FrameCounter {
+        &self.frame_counter
+    }
+
+    /* Gives access to the delta modulation channel */
+    pub fn dmc(&self) -> &Dmc {
+        &self.dmc
+    }
+
+    /* Gives mutable access to the delta modulation channel */
+    pub fn dmc_mut(&mut self) -> &mut Dmc {
+        &mut self.dmc
+    }
+
+    /* Gives access to the first pulse channel */
+    pub fn pulse_1(&self) -> &Pulse {
+        &self.pulse_1
+    }
+
+    /* Gives mutable access to the first pulse channel */
+    pub fn pulse_1_mut(&mut self) -> &mut Pulse {
+        &mut self.pulse_1
+    }
+
+    /* Gives access to the second pulse channel */
+    pub fn pulse_2(&self) -> &Pulse {
+        &self.pulse_2
+    }
+
+    /* Gives mutable access to the second pulse channel */
+    pub fn pulse_2_mut(&mut self) -> &mut Pulse {
+        &mut self.pulse_2
+    }
+}
+
+impl Default for APU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
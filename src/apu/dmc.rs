@@ -0,0 +1,213 @@
+use crate::console::region::Region;
+
+/* Timer periods of the DMC, in CPU cycles, indexed by its rate setting */
+pub const NTSC_DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+pub const PAL_DMC_RATES: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+/* Samples are read from the upper part of the cartridge space */
+pub const DMC_SAMPLE_ADDRESS_BASE: u16 = 0xC000;
+
+/*
+ * The delta modulation channel, playing 1-bit delta encoded
+ * samples read straight from memory.
+ *
+ * Its memory reader fetches a byte whenever the sample buffer
+ * is empty, which the CPU bus services by halting the CPU.
+ * Each bit of the output shift register then moves a 7-bit
+ * level up or down by 2.
+ */
+#[derive(Debug, Clone)]
+pub struct Dmc {
+    rates: &'static [u16; 16],
+
+    irq_enabled: bool,
+    looping: bool,
+    irq_flag: bool,
+
+    timer_period: u16,
+    timer: u16,
+
+    /* Registers written by the CPU */
+    sample_address: u16,
+    sample_length: u16,
+
+    /* Memory reader */
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    /* Output unit */
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    output_level: u8,
+}
+
+impl Dmc {
+    /* Initializes a new silent Dmc */
+    pub fn new() -> Self {
+        Self {
+            rates: &NTSC_DMC_RATES,
+            irq_enabled: false,
+            looping: false,
+            irq_flag: false,
+            timer_period: NTSC_DMC_RATES[0],
+            timer: NTSC_DMC_RATES[0],
+            sample_address: DMC_SAMPLE_ADDRESS_BASE,
+            sample_length: 1,
+            current_address: DMC_SAMPLE_ADDRESS_BASE,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            output_level: 0,
+        }
+    }
+
+    /* Switches to the rate table of another console region */
+    pub fn set_region(&mut self, region: Region) {
+        self.rates = match region {
+            Region::Ntsc | Region::Dendy => &NTSC_DMC_RATES,
+            Region::Pal => &PAL_DMC_RATES,
+        };
+    }
+
+    /* Writes one of the 4 registers of the channel */
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = value & 0x80 != 0;
+                self.looping = value & 0x40 != 0;
+                self.timer_period = self.rates[(value & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq_flag = false;
+                }
+            }
+            1 => self.output_level = value & 0x7F,
+            2 => self.sample_address = DMC_SAMPLE_ADDRESS_BASE | ((value as u16) << 6),
+            3 => self.sample_length = ((value as u16) << 4) + 1,
+            _ => {}
+        }
+    }
+
+    /*
+     * Enables or disables the channel, as done through $4015.
+     * Enabling it restarts the sample only once it is over.
+     */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq_flag = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /* Specifies whether sample bytes remain to be read */
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    /* Specifies whether the end of a sample raised an interrupt */
+    pub fn irq_flag(&self) -> bool {
+        self.irq_flag
+    }
+
+    /*
+     * Address the memory reader wants a byte from, if its
+     * buffer is empty and the sample is not over
+     */
+    pub fn get_pending_read(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /*
+     * Fills the sample buffer with the byte read by the CPU bus
+     * and moves to the next one, wrapping from $FFFF to $8000
+     */
+    pub fn load_sample(&mut self, value: u8) {
+        self.sample_buffer = Some(value);
+
+        self.current_address = if self.current_address == 0xFFFF {
+            0x8000
+        } else {
+            self.current_address + 1
+        };
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_flag = true;
+            }
+        }
+    }
+
+    /* Clocked on every CPU cycle */
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+        self.clock_output();
+    }
+
+    /* Current output level, between 0 and 127 */
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+
+    /* Plays the next bit of the shift register */
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift_register & 0x01 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+
+            match self.sample_buffer.take() {
+                Some(value) => {
+                    self.silence = false;
+                    self.shift_register = value;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /* Starts reading the sample over from its first byte */
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ * Volume envelope shared by the pulse and noise channels. It
+ * either outputs a constant volume or a decaying one, going
+ * from 15 down to 0 and optionally looping.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Envelope {
+    start: bool,
+    looping: bool,
+    constant_volume: bool,
+    volume: u8,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl Envelope {
+    /* Initializes a new silent Envelope */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Sets up the envelope from the lower bits of the channel control register */
+    pub fn write_control(&mut self, value: u8) {
+        self.looping = value & 0x20 != 0;
+        self.constant_volume = value & 0x10 != 0;
+        self.volume = value & 0x0F;
+    }
+
+    /* Restarts the decay on the next quarter frame */
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    /* Clocked by the frame counter on every quarter frame */
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay_level = 15;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+
+        self.divider = self.volume;
+
+        if self.decay_level > 0 {
+            self.decay_level -= 1;
+        } else if self.looping {
+            self.decay_level = 15;
+        }
+    }
+
+    /* Current volume, between 0 and 15 */
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay_level
+        }
+    }
+}
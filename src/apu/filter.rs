@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+
+/*
+ * Cutoff frequencies of the filters found between the APU and
+ * the audio output of the console, in Hz
+ */
+pub const FIRST_HIGH_PASS_CUTOFF: f32 = 90.0;
+pub const SECOND_HIGH_PASS_CUTOFF: f32 = 440.0;
+pub const LOW_PASS_CUTOFF: f32 = 14000.0;
+
+/* Which frequencies a filter lets through */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    HighPass,
+    LowPass,
+}
+
+/* A first-order RC filter, as built with a resistor and a capacitor */
+#[derive(Debug, Clone)]
+pub struct Filter {
+    kind: FilterKind,
+    cutoff: f32,
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl Filter {
+    /* Initializes a new Filter for samples coming at the given rate */
+    pub fn new(kind: FilterKind, cutoff: f32, sample_rate: u32) -> Self {
+        let mut filter = Self {
+            kind,
+            cutoff,
+            alpha: 0.0,
+            previous_input: 0.0,
+            previous_output: 0.0,
+        };
+        filter.set_sample_rate(sample_rate);
+
+        filter
+    }
+
+    /* Recomputes the coefficient of the filter for another sample rate */
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let rc = 1.0 / (2.0 * PI * self.cutoff);
+        let dt = 1.0 / sample_rate as f32;
+
+        self.alpha = match self.kind {
+            FilterKind::HighPass => rc / (rc + dt),
+            FilterKind::LowPass => dt / (rc + dt),
+        };
+    }
+
+    /* Filters a single sample */
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            FilterKind::HighPass => {
+                self.alpha * (self.previous_output + input - self.previous_input)
+            }
+            FilterKind::LowPass => {
+                self.previous_output + self.alpha * (input - self.previous_output)
+            }
+        };
+
+        self.previous_input = input;
+        self.previous_output = output;
+
+        output
+    }
+}
+
+/*
+ * The filters the audio of the console goes through before
+ * leaving it, two high-pass ones removing the DC offset and
+ * some bass, and a low-pass one taming the harshest highs
+ */
+#[derive(Debug, Clone)]
+pub struct OutputFilters {
+    filters: [Filter; 3],
+}
+
+impl OutputFilters {
+    /* Initializes the filters for samples coming at the given rate */
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            filters: [
+                Filter::new(FilterKind::HighPass, FIRST_HIGH_PASS_CUTOFF, sample_rate),
+                Filter::new(FilterKind::HighPass, SECOND_HIGH_PASS_CUTOFF, sample_rate),
+                Filter::new(FilterKind::LowPass, LOW_PASS_CUTOFF, sample_rate),
+            ],
+        }
+    }
+
+    /* Recomputes the coefficients of the filters for another sample rate */
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        for filter in self.filters.iter_mut() {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    /* Runs a single sample through every filter */
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.process(sample))
+    }
+}
@@ -0,0 +1,202 @@
+use crate::console::region::Region;
+
+/*
+ * CPU cycles at which the steps of the frame sequence happen,
+ * the last entry of each mode being the cycle it starts over on
+ */
+pub const NTSC_FOUR_STEP_CYCLES: [u32; 5] = [7457, 14913, 22371, 29829, 29830];
+pub const NTSC_FIVE_STEP_CYCLES: [u32; 5] = [7457, 14913, 22371, 37281, 37282];
+pub const PAL_FOUR_STEP_CYCLES: [u32; 5] = [8313, 16627, 24939, 33253, 33254];
+pub const PAL_FIVE_STEP_CYCLES: [u32; 5] = [8313, 16627, 24939, 41565, 41566];
+
+/* Units of the APU to be clocked after a frame counter step */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEvent {
+    None,
+    /* Envelopes only */
+    QuarterFrame,
+    /* Envelopes, along with length counters and sweeps */
+    HalfFrame,
+}
+
+/*
+ * The frame counter of the APU, written through $4017. It
+ * divides the CPU clock into quarter and half frames driving
+ * the slower units of the channels. In 4-step mode it also
+ * raises an interrupt at the end of every sequence, unless
+ * inhibited.
+ */
+#[derive(Debug, Clone)]
+pub struct FrameCounter {
+    region: Region,
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    irq_flag: bool,
+
+    cycle: u32,
+    /* CPU cycles left before a $4017 write restarts the sequence */
+    reset_delay: u8,
+}
+
+impl FrameCounter {
+    /* Initializes a new FrameCounter in 4-step mode */
+    pub fn new() -> Self {
+        Self {
+            region: Region::Ntsc,
+            five_step_mode: false,
+            irq_inhibit: false,
+            irq_flag: false,
+            cycle: 0,
+            reset_delay: 0,
+        }
+    }
+
+    /* Switches to the sequence timing of another console region */
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    /*
+     * Selects the mode and the IRQ inhibit flag. The sequence
+     * restarts 3 or 4 cycles later depending on whether the
+     * write happens in the middle of an APU cycle.
+     */
+    pub fn write(&mut self, value: u8, odd_cycle: bool) {
+        self.five_step_mode = value & 0x80 != 0;
+        self.irq_inhibit = value & 0x40 != 0;
+
+        if self.irq_inhibit {
+            self.irq_flag = false;
+        }
+
+        self.reset_delay = if odd_cycle { 4 } else { 3 };
+    }
+
+    /*
+     * Restarts the sequence as the reset button does, which acts
+     * like writing $4017 again with the mode left unchanged
+     */
+    pub fn reset(&mut self, odd_cycle: bool) {
+        self.irq_flag = false;
+        self.reset_delay = if odd_cycle { 4 } else { 3 };
+    }
+
+    /* Specifies whether the frame interrupt is raised */
+    pub fn irq_flag(&self) -> bool {
+        self.irq_flag
+    }
+
+    /* Acknowledges the frame interrupt */
+    pub fn clear_irq_flag(&mut self) {
+        self.irq_flag = false;
+    }
+
+    /* Advances the sequence by a single CPU cycle */
+    pub fn clock(&mut self) -> FrameEvent {
+        let mut is_restarted = false;
+
+        if self.reset_delay > 0 {
+            self.reset_delay -= 1;
+
+            if self.reset_delay == 0 {
+                self.cycle = 0;
+                is_restarted = true;
+            }
+        }
+
+        self.cycle += 1;
+
+        let steps = self.get_steps();
+        /* The interrupt flag is raised on the last 3 cycles of the sequence */
+        let is_sequence_end = self.cycle + 1 >= steps[3] && !self.five_step_mode;
+
+        if is_sequence_end && !self.irq_inhibit {
+            self.irq_flag = true;
+        }
+
+        /*
+         * Restarting in 5-step mode clocks all units right away,
+         * while the sequence keeps counting from this cycle
+         */
+        if is_restarted && self.five_step_mode {
+            return FrameEvent::HalfFrame;
+        }
+
+        match self.cycle {
+            cycle if cycle == steps[0] || cycle == steps[2] => FrameEvent::QuarterFrame,
+            cycle if cycle == steps[1] || cycle == steps[3] => FrameEvent::HalfFrame,
+            cycle if cycle >= steps[4] => {
+                self.cycle = 0;
+                FrameEvent::None
+            }
+            _ => FrameEvent::None,
+        }
+    }
+
+    fn get_steps(&self) -> &'static [u32; 5] {
+        match (self.region, self.five_step_mode) {
+            (Region::Pal, false) => &PAL_FOUR_STEP_CYCLES,
+            (Region::Pal, true) => &PAL_FIVE_STEP_CYCLES,
+            (_, false) => &NTSC_FOUR_STEP_CYCLES,
+            (_, true) => &NTSC_FIVE_STEP_CYCLES,
+        }
+    }
+}
+
+impl Default for FrameCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Number of clocks until the frame counter gives the event, the last one included */
+    fn get_clocks_until(counter: &mut FrameCounter, event: FrameEvent) -> u32 {
+        (1..).find(|_| counter.clock() == event).unwrap()
+    }
+
+    #[test]
+    fn write_restarts_the_sequence_3_or_4_cycles_later() {
+        for (odd_cycle, delay) in [(false, 3), (true, 4)] {
+            let mut counter = FrameCounter::new();
+            for _ in 0..100 {
+                counter.clock();
+            }
+
+            counter.write(0x00, odd_cycle);
+
+            assert_eq!(
+                get_clocks_until(&mut counter, FrameEvent::QuarterFrame),
+                delay - 1 + NTSC_FOUR_STEP_CYCLES[0]
+            );
+        }
+    }
+
+    #[test]
+    fn five_step_write_clocks_every_unit_once_restarted() {
+        let mut counter = FrameCounter::new();
+        counter.write(0x80, false);
+
+        assert_eq!(get_clocks_until(&mut counter, FrameEvent::HalfFrame), 3);
+        assert_eq!(
+            get_clocks_until(&mut counter, FrameEvent::QuarterFrame),
+            NTSC_FIVE_STEP_CYCLES[0] - 1
+        );
+    }
+
+    #[test]
+    fn reset_restarts_the_sequence_with_the_mode_kept() {
+        let mut counter = FrameCounter::new();
+        counter.write(0x80, false);
+        get_clocks_until(&mut counter, FrameEvent::HalfFrame);
+        for _ in 0..100 {
+            counter.clock();
+        }
+
+        counter.reset(false);
+        assert_eq!(get_clocks_until(&mut counter, FrameEvent::HalfFrame), 3);
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ * Durations loaded into the length counter, indexed by the
+ * 5 upper bits written to the last register of a channel
+ */
+pub const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/*
+ * Silences a channel once a given duration has elapsed. It is
+ * clocked on every half frame unless halted, and is only
+ * loaded while the channel is enabled.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct LengthCounter {
+    enabled: bool,
+    halted: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    /* Initializes a new disabled LengthCounter */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Enables or disables the channel, disabling it clears the counter */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /* Freezes the counter at its current value */
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    /* Loads a new duration from the length table */
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    /* Clocked by the frame counter on every half frame */
+    pub fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    /* Specifies whether the channel is still allowed to play */
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+
+    /* Remaining duration, in half frames */
+    pub fn counter(&self) -> u8 {
+        self.counter
+    }
+}
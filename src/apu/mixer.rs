@@ -0,0 +1,284 @@
+/* Number of possible sums of the pulse outputs, and of the triangle, noise and DMC ones */
+pub const PULSE_TABLE_SIZE: usize = 31;
+pub const TND_TABLE_SIZE: usize = 203;
+
+/*
+ * Output of a single step of the VRC6 channels, which are
+ * mixed linearly. A pulse at full volume is about as loud as
+ * one of the console.
+ */
+pub const VRC6_LEVEL_SCALE: f32 = 0.00996;
+
+/* Output of a 5B channel at full volume, mixed linearly as well */
+pub const SUNSOFT_5B_LEVEL_SCALE: f32 = 0.15;
+
+/* The sound channels going through the mixer */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    Vrc6Pulse1,
+    Vrc6Pulse2,
+    Vrc6Sawtooth,
+    Sunsoft5bA,
+    Sunsoft5bB,
+    Sunsoft5bC,
+}
+
+/* Output level of every channel, in the order of Channel::ALL */
+pub type ChannelLevels = [f32; Channel::ALL.len()];
+
+/*
+ * Positions of the channels in the classic fake stereo spread,
+ * from -1.0 (left) to 1.0 (right), the others staying centered
+ */
+pub const FAKE_STEREO_PANS: [(Channel, f32); 8] = [
+    (Channel::Pulse1, -0.5),
+    (Channel::Pulse2, 0.5),
+    (Channel::Noise, 0.3),
+    (Channel::Dmc, -0.3),
+    (Channel::Vrc6Pulse1, -0.5),
+    (Channel::Vrc6Pulse2, 0.5),
+    (Channel::Sunsoft5bA, -0.5),
+    (Channel::Sunsoft5bC, 0.5),
+];
+
+impl Channel {
+    /* Every channel, in the order used for per-channel outputs */
+    pub const ALL: [Channel; 11] = [
+        Channel::Pulse1,
+        Channel::Pulse2,
+        Channel::Triangle,
+        Channel::Noise,
+        Channel::Dmc,
+        Channel::Vrc6Pulse1,
+        Channel::Vrc6Pulse2,
+        Channel::Vrc6Sawtooth,
+        Channel::Sunsoft5bA,
+        Channel::Sunsoft5bB,
+        Channel::Sunsoft5bC,
+    ];
+
+    /* Highest output level of the channel */
+    pub fn max_level(&self) -> f32 {
+        match self {
+            Channel::Dmc => 127.0,
+            Channel::Vrc6Sawtooth => 31.0,
+            Channel::Sunsoft5bA | Channel::Sunsoft5bB | Channel::Sunsoft5bC => 1.0,
+            _ => 15.0,
+        }
+    }
+}
+
+/*
+ * Combines the channel outputs the way the resistor network
+ * of the console does. The pulses share one nonlinear DAC and
+ * the triangle, noise and DMC another, both approximated with
+ * the lookup tables documented on NESdev. The triangle and
+ * noise channels are not emulated, so their levels are 0 and
+ * the second DAC only ever sees the DMC.
+ *
+ * Each channel can also be muted, soloed, attenuated or
+ * panned before reaching the DACs, in which case the tables
+ * are bypassed for the formulas they were computed from. The
+ * stereo output runs both sides through the formulas.
+ */
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    pulse_table: [f32; PULSE_TABLE_SIZE],
+    tnd_table: [f32; TND_TABLE_SIZE],
+
+    volumes: [f32; Channel::ALL.len()],
+    muted: [bool; Channel::ALL.len()],
+    solo: Option<Channel>,
+    pans: [f32; Channel::ALL.len()],
+    is_stereo: bool,
+}
+
+impl Mixer {
+    /* Initializes a new Mixer, computing its lookup tables */
+    pub fn new() -> Self {
+        let mut pulse_table = [0.0; PULSE_TABLE_SIZE];
+        let mut tnd_table = [0.0; TND_TABLE_SIZE];
+
+        for (n, value) in pulse_table.iter_mut().enumerate().skip(1) {
+            *value = Self::get_dac_output(n as f32, 95.52, 8128.0);
+        }
+
+        for (n, value) in tnd_table.iter_mut().enumerate().skip(1) {
+            *value = Self::get_dac_output(n as f32, 163.67, 24329.0);
+        }
+
+        Self {
+            pulse_table,
+            tnd_table,
+            volumes: [1.0; Channel::ALL.len()],
+            muted: [false; Channel::ALL.len()],
+            solo: None,
+            pans: [0.0; Channel::ALL.len()],
+            is_stereo: false,
+        }
+    }
+
+    /* Sets the volume of a channel, 1.0 being its original level */
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volumes[channel as usize] = volume.max(0.0);
+    }
+
+    /* Volume of a channel, 1.0 being its original level */
+    pub fn volume(&self, channel: Channel) -> f32 {
+        self.volumes[channel as usize]
+    }
+
+    /* Mutes or unmutes a channel */
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.muted[channel as usize] = muted;
+    }
+
+    /* Specifies whether a channel is muted */
+    pub fn is_muted(&self, channel: Channel) -> bool {
+        self.muted[channel as usize]
+    }
+
+    /* Only lets the given channel through, or all of them again with None */
+    pub fn set_solo(&mut self, channel: Option<Channel>) {
+        self.solo = channel;
+    }
+
+    /* The channel being soloed, if any */
+    pub fn solo(&self) -> Option<Channel> {
+        self.solo
+    }
+
+    /* Switches between a single output and separate left and right ones */
+    pub fn set_stereo(&mut self, enabled: bool) {
+        self.is_stereo = enabled;
+    }
+
+    /* Specifies whether left and right outputs are mixed */
+    pub fn is_stereo(&self) -> bool {
+        self.is_stereo
+    }
+
+    /* Sets the position of a channel between the left and right outputs */
+    pub fn set_pan(&mut self, channel: Channel, pan: f32) {
+        self.pans[channel as usize] = pan.clamp(-1.0, 1.0);
+    }
+
+    /* Position of a channel, from -1.0 (left) to 1.0 (right) */
+    pub fn pan(&self, channel: Channel) -> f32 {
+        self.pans[channel as usize]
+    }
+
+    /*
+     * Pans the channels apart in the classic fake stereo way,
+     * each pair of similar channels going to opposite sides
+     */
+    pub fn set_fake_stereo_pans(&mut self) {
+        self.pans = [0.0; Channel::ALL.len()];
+
+        for (channel, pan) in FAKE_STEREO_PANS {
+            self.set_pan(channel, pan);
+        }
+    }
+
+    /*
+     * Mixes the output levels of all channels, in the order of
+     * Channel::ALL, into a single sample between 0.0 and 1.0
+     * for the console channels alone
+     */
+    pub fn mix(&self, levels: &ChannelLevels) -> f32 {
+        if self.is_neutral() {
+            let pulse =
+                (levels[Channel::Pulse1 as usize] + levels[Channel::Pulse2 as usize]) as usize;
+            /* The triangle and noise are not emulated, their levels are always 0 */
+            let tnd = (3.0 * levels[Channel::Triangle as usize]
+                + 2.0 * levels[Channel::Noise as usize]
+                + levels[Channel::Dmc as usize]) as usize;
+
+            return self.pulse_table[pulse.min(PULSE_TABLE_SIZE - 1)]
+                + self.tnd_table[tnd.min(TND_TABLE_SIZE - 1)]
+                + self.get_expansion_output(levels, |_| 1.0);
+        }
+
+        self.get_output(levels, |_| 1.0)
+    }
+
+    /* Mixes the output levels of all channels into a left and a right sample */
+    pub fn mix_stereo(&self, levels: &ChannelLevels) -> (f32, f32) {
+        let left = |channel: Channel| (1.0 - self.pans[channel as usize]).min(1.0);
+        let right = |channel: Channel| (1.0 + self.pans[channel as usize]).min(1.0);
+
+        (
+            self.get_output(levels, left),
+            self.get_output(levels, right),
+        )
+    }
+
+    /*
+     * Output of every channel through the DAC formulas, each
+     * level being scaled by the given gain on top of its volume
+     */
+    fn get_output<F: Fn(Channel) -> f32>(&self, levels: &ChannelLevels, gain: F) -> f32 {
+        let level =
+            |channel: Channel| self.get_level(channel, levels[channel as usize]) * gain(channel);
+
+        let pulse = level(Channel::Pulse1) + level(Channel::Pulse2);
+        let tnd =
+            3.0 * level(Channel::Triangle) + 2.0 * level(Channel::Noise) + level(Channel::Dmc);
+
+        Self::get_dac_output(pulse, 95.52, 8128.0)
+            + Self::get_dac_output(tnd, 163.67, 24329.0)
+            + self.get_expansion_output(levels, gain)
+    }
+
+    /* Output of the cartridge sound chips, which are mixed linearly */
+    fn get_expansion_output<F: Fn(Channel) -> f32>(&self, levels: &ChannelLevels, gain: F) -> f32 {
+        let level =
+            |channel: Channel| self.get_level(channel, levels[channel as usize]) * gain(channel);
+
+        let vrc6 =
+            level(Channel::Vrc6Pulse1) + level(Channel::Vrc6Pulse2) + level(Channel::Vrc6Sawtooth);
+        let sunsoft_5b =
+            level(Channel::Sunsoft5bA) + level(Channel::Sunsoft5bB) + level(Channel::Sunsoft5bC);
+
+        vrc6 * VRC6_LEVEL_SCALE + sunsoft_5b * SUNSOFT_5B_LEVEL_SCALE
+    }
+
+    /* Specifies whether every channel goes through untouched */
+    fn is_neutral(&self) -> bool {
+        self.solo.is_none()
+            && self.volumes.iter().all(|volume| *volume == 1.0)
+            && self.muted.iter().all(|muted| !muted)
+    }
+
+    /* Output level of a channel once muted, soloed or attenuated */
+    fn get_level(&self, channel: Channel, level: f32) -> f32 {
+        let is_silenced =
+            self.muted[channel as usize] || self.solo.is_some_and(|solo| solo != channel);
+
+        if is_silenced {
+            0.0
+        } else {
+            level * self.volumes[channel as usize]
+        }
+    }
+
+    /* Formula the DAC lookup tables are computed from */
+    fn get_dac_output(level: f32, numerator: f32, divisor: f32) -> f32 {
+        if level <= 0.0 {
+            0.0
+        } else {
+            numerator / (divisor / level + 100.0)
+        }
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
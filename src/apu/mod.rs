@@ -0,0 +1,14 @@
+pub mod apu;
+pub mod dmc;
+pub mod envelope;
+pub mod filter;
+pub mod frame_counter;
+pub mod length_counter;
+pub mod mixer;
+pub mod pulse;
+pub mod recorder;
+pub mod sample_buffer;
+pub mod sunsoft_5b;
+pub mod sweep;
+pub mod vrc6;
+pub mod waveform;
@@ -0,0 +1,107 @@
+use crate::apu::{envelope::Envelope, length_counter::LengthCounter, sweep::Sweep};
+
+/* Waveforms of the 4 duty cycles: 12.5%, 25%, 50% and 25% negated */
+pub const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/*
+ * A square wave channel. Its timer walks over an 8 steps
+ * duty sequence, the resulting wave being shaped by the
+ * envelope and cut by the length counter and the sweep.
+ */
+#[derive(Debug, Clone)]
+pub struct Pulse {
+    duty: u8,
+    sequence_step: u8,
+    timer_period: u16,
+    timer: u16,
+
+    envelope: Envelope,
+    length_counter: LengthCounter,
+    sweep: Sweep,
+}
+
+impl Pulse {
+    /*
+     * Initializes a new Pulse channel. Only the first of the two
+     * channels negates its sweep with a ones' complement.
+     */
+    pub fn new(is_first_channel: bool) -> Self {
+        Self {
+            duty: 0,
+            sequence_step: 0,
+            timer_period: 0,
+            timer: 0,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+            sweep: Sweep::new(is_first_channel),
+        }
+    }
+
+    /* Writes one of the 4 registers of the channel */
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> 6;
+                self.length_counter.set_halted(value & 0x20 != 0);
+                self.envelope.write_control(value);
+            }
+            1 => self.sweep.write(value),
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length_counter.load(value >> 3);
+                self.sequence_step = 0;
+                self.envelope.restart();
+            }
+            _ => {}
+        }
+    }
+
+    /* Enables or disables the channel, as done through $4015 */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    /* Specifies whether the length counter is still running */
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    /* Clocked on every APU cycle, moving through the duty sequence */
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /* Clocked by the frame counter on every quarter frame */
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    /* Clocked by the frame counter on every half frame */
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+        self.timer_period = self.sweep.clock(self.timer_period);
+    }
+
+    /* Current output level, between 0 and 15 */
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active()
+            || self.sweep.is_muting(self.timer_period)
+            || DUTY_SEQUENCES[self.duty as usize][self.sequence_step as usize] == 0
+        {
+            return 0;
+        }
+
+        self.envelope.output()
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    apu::{
+        apu::{APU, CHANNEL_COUNT},
+        sample_buffer::SampleBuffer,
+    },
+    errors::AppResult,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{fs::File, io::BufWriter, path::Path};
+
+/* Selects what ends up in a recording */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordingMode {
+    /* A single channel holding the mixed output, as heard */
+    #[default]
+    Mixed,
+    /* One channel per sound channel, in the order of Channel::ALL */
+    Channels,
+}
+
+/*
+ * Writes the APU output to a 16-bit WAV file. Each recorded
+ * channel is resampled on its own to the rate of the file,
+ * and the samples are written interleaved on every flush.
+ */
+pub struct WavRecorder {
+    writer: WavWriter<BufWriter<File>>,
+    mode: RecordingMode,
+    buffers: Vec<SampleBuffer>,
+    channels: Vec<Vec<i16>>,
+}
+
+impl WavRecorder {
+    /*
+     * Creates the WAV file at the given path, recording samples
+     * coming at the input rate, usually the CPU clock rate
+     */
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        mode: RecordingMode,
+        input_rate: f64,
+        output_rate: u32,
+    ) -> AppResult<Self> {
+        let channel_count = match mode {
+            RecordingMode::Mixed => 1,
+            RecordingMode::Channels => CHANNEL_COUNT,
+        };
+
+        let spec = WavSpec {
+            channels: channel_count as u16,
+            sample_rate: output_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let buffers = (0..channel_count)
+            .map(|_| {
+                let mut buffer = SampleBuffer::new(input_rate);
+                buffer.set_output_rate(output_rate);
+                buffer
+            })
+            .collect();
+
+        Ok(Self {
+            writer: WavWriter::create(path, spec)?,
+            mode,
+            buffers,
+            channels: vec![Vec::new(); channel_count],
+        })
+    }
+
+    /* Changes the rate of the incoming samples, such as the CPU clock of another region */
+    pub fn set_input_rate(&mut self, rate: f64) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.set_input_rate(rate);
+        }
+    }
+
+    /* Records the output of the APU after its last cycle */
+    pub fn push(&mut self, apu: &APU) {
+        match self.mode {
+            RecordingMode::Mixed => self.buffers[0].push(apu.sample()),
+            RecordingMode::Channels => {
+                for (buffer, sample) in self.buffers.iter_mut().zip(apu.channel_samples()) {
+                    buffer.push(sample);
+                }
+            }
+        }
+    }
+
+    /* Writes the samples produced so far to the file */
+    pub fn flush(&mut self) -> AppResult<()> {
+        for (buffer, channel) in self.buffers.iter_mut().zip(self.channels.iter_mut()) {
+            buffer.take_samples(channel);
+        }
+
+        /* All buffers are fed together, so they hold as many samples */
+        let length = self.channels.iter().map(Vec::len).min().unwrap_or(0);
+
+        for index in 0..length {
+            for channel in self.channels.iter() {
+                self.writer.write_sample(channel[index])?;
+            }
+        }
+
+        for channel in self.channels.iter_mut() {
+            channel.drain(..length);
+        }
+
+        Ok(())
+    }
+
+    /* Writes the remaining samples and completes the WAV header */
+    pub fn finish(mut self) -> AppResult<()> {
+        self.flush()?;
+        self.writer.finalize()?;
+
+        Ok(())
+    }
+}
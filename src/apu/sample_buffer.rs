@@ -0,0 +1,265 @@
+use crate::apu::filter::OutputFilters;
+use std::{collections::VecDeque, f64::consts::PI};
+
+/* Rate of the produced samples unless told otherwise */
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/*
+ * Number of output samples a single step is spread over, and
+ * number of fractional positions the step kernel is computed
+ * for between two output samples
+ */
+const STEP_WIDTH: usize = 16;
+const STEP_PHASES: usize = 32;
+
+/* Cutoff of the step kernel, as a fraction of the output Nyquist frequency */
+const STEP_CUTOFF: f64 = 0.9;
+
+/* Function receiving batches of produced samples */
+pub type SamplesCallback = Box<dyn FnMut(&[i16])>;
+
+/*
+ * State of a single output channel of the buffer, holding the
+ * steps not yet turned into samples and its own filters
+ */
+struct BufferChannel {
+    /*
+     * Amplitude changes spread over the upcoming output samples,
+     * the first one being the next to be produced
+     */
+    deltas: VecDeque<f32>,
+    /* Amplitude of the last input sample */
+    last: f32,
+    /* Amplitude of the last output sample */
+    amplitude: f32,
+
+    filters: OutputFilters,
+}
+
+impl BufferChannel {
+    /* Initializes a new silent channel */
+    fn new(output_rate: u32) -> Self {
+        Self {
+            deltas: VecDeque::from(vec![0.0; STEP_WIDTH + 1]),
+            last: 0.0,
+            amplitude: 0.0,
+            filters: OutputFilters::new(output_rate),
+        }
+    }
+
+    /* Spreads an amplitude change over the output samples, with the kernel of its phase */
+    fn add_step(&mut self, kernel: &[f32; STEP_WIDTH], delta: f32) {
+        for (index, tap) in kernel.iter().enumerate() {
+            self.deltas[index] += delta * tap;
+        }
+    }
+
+    /* Produces the next output sample */
+    fn get_next_sample(&mut self, are_filters_enabled: bool) -> i16 {
+        self.amplitude += self.deltas.pop_front().unwrap_or(0.0);
+        self.deltas.push_back(0.0);
+
+        let amplitude = if are_filters_enabled {
+            self.filters.process(self.amplitude)
+        } else {
+            self.amplitude
+        };
+
+        let sample = (amplitude * i16::MAX as f32).round();
+        sample.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+}
+
+/*
+ * Collects the APU output, produced once per CPU cycle, and
+ * resamples it to the rate of the audio device.
+ *
+ * The output is a sum of steps, so instead of sampling it
+ * every cycle, each change of amplitude is added as a band
+ * limited step at its exact position between output samples,
+ * the way a blip buffer does. This avoids aliasing and costs
+ * nothing while the output stays flat.
+ *
+ * The console filters are then applied to the output, unless
+ * they are turned off.
+ *
+ * The buffer is either mono or stereo, the left and right
+ * samples being interleaved in the latter case. Samples are
+ * either taken by polling or handed to a callback whenever
+ * the buffer is flushed.
+ */
+pub struct SampleBuffer {
+    input_rate: f64,
+    output_rate: u32,
+    /* Multiplier applied to the output rate, see set_rate_adjustment */
+    rate_adjustment: f64,
+
+    /* Derivative of the band limited step, for each phase */
+    kernel: Vec<[f32; STEP_WIDTH]>,
+
+    channels: Vec<BufferChannel>,
+    /* Output samples elapsed since the first pending delta */
+    position: f64,
+    are_filters_enabled: bool,
+
+    samples: Vec<i16>,
+    callback: Option<SamplesCallback>,
+}
+
+impl SampleBuffer {
+    /* Initializes a new mono SampleBuffer converting from the given input rate */
+    pub fn new(input_rate: f64) -> Self {
+        Self {
+            input_rate,
+            output_rate: DEFAULT_SAMPLE_RATE,
+            rate_adjustment: 1.0,
+            kernel: Self::get_step_kernel(),
+            channels: vec![BufferChannel::new(DEFAULT_SAMPLE_RATE)],
+            position: 0.0,
+            are_filters_enabled: true,
+            samples: Vec::new(),
+            callback: None,
+        }
+    }
+
+    /* Changes the rate of the incoming samples, such as the CPU clock of another region */
+    pub fn set_input_rate(&mut self, rate: f64) {
+        self.input_rate = rate;
+    }
+
+    /* Changes the rate samples are produced at */
+    pub fn set_output_rate(&mut self, rate: u32) {
+        self.output_rate = rate;
+
+        for channel in self.channels.iter_mut() {
+            channel.filters.set_sample_rate(rate);
+        }
+    }
+
+    /* Rate samples are produced at */
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /* Switches between mono and interleaved stereo output, dropping pending samples */
+    pub fn set_stereo(&mut self, enabled: bool) {
+        let count = if enabled { 2 } else { 1 };
+
+        self.channels = (0..count)
+            .map(|_| BufferChannel::new(self.output_rate))
+            .collect();
+        self.samples.clear();
+    }
+
+    /* Number of interleaved channels in the produced samples */
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /* Enables or disables the console filters, they are applied by default */
+    pub fn set_filters_enabled(&mut self, enabled: bool) {
+        self.are_filters_enabled = enabled;
+    }
+
+    /*
+     * Slightly speeds up or slows down the production of samples,
+     * 1.0 being the nominal rate. Frontends use it to keep the
+     * buffer of the audio device from running dry or overflowing.
+     */
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        self.rate_adjustment = adjustment;
+    }
+
+    /* Adds a single APU sample, between 0.0 and 1.0, to every channel */
+    pub fn push(&mut self, sample: f32) {
+        self.push_frame(&[sample, sample]);
+    }
+
+    /* Adds a left and a right APU sample, mixed together when the buffer is mono */
+    pub fn push_stereo(&mut self, left: f32, right: f32) {
+        if self.channels.len() == 1 {
+            self.push_frame(&[(left + right) / 2.0]);
+        } else {
+            self.push_frame(&[left, right]);
+        }
+    }
+
+    /* Adds a sample to each channel, then produces the output samples that are due */
+    fn push_frame(&mut self, frame: &[f32]) {
+        let phase = ((self.position * STEP_PHASES as f64) as usize).min(STEP_PHASES - 1);
+
+        for (channel, sample) in self.channels.iter_mut().zip(frame) {
+            if *sample != channel.last {
+                channel.add_step(&self.kernel[phase], sample - channel.last);
+                channel.last = *sample;
+            }
+        }
+
+        self.position += self.output_rate as f64 * self.rate_adjustment / self.input_rate;
+
+        while self.position >= 1.0 {
+            self.position -= 1.0;
+
+            for channel in self.channels.iter_mut() {
+                self.samples
+                    .push(channel.get_next_sample(self.are_filters_enabled));
+            }
+        }
+    }
+
+    /* Moves all produced samples to the end of the given vector */
+    pub fn take_samples(&mut self, samples: &mut Vec<i16>) {
+        samples.append(&mut self.samples);
+    }
+
+    /* Registers a callback receiving the produced samples on every flush */
+    pub fn on_samples<F: FnMut(&[i16]) + 'static>(&mut self, callback: F) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /* Removes the samples callback, going back to polling */
+    pub fn clear_samples_callback(&mut self) {
+        self.callback = None;
+    }
+
+    /* Hands the produced samples to the callback, if there is one */
+    pub fn flush(&mut self) {
+        if let Some(callback) = self.callback.as_mut() {
+            callback(&self.samples);
+            self.samples.clear();
+        }
+    }
+
+    /*
+     * Computes a Blackman windowed sinc for every phase, which
+     * is what a step turns into once band limited. The taps of
+     * each phase add up to 1 so the step keeps its height.
+     */
+    fn get_step_kernel() -> Vec<[f32; STEP_WIDTH]> {
+        let half = (STEP_WIDTH / 2) as f64;
+
+        (0..STEP_PHASES)
+            .map(|phase| {
+                let mut taps = [0.0f64; STEP_WIDTH];
+
+                for (index, tap) in taps.iter_mut().enumerate() {
+                    let x = index as f64 - (half - 1.0) - phase as f64 / STEP_PHASES as f64;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (PI * x * STEP_CUTOFF).sin() / (PI * x * STEP_CUTOFF)
+                    };
+
+                    let window_position = (x + half) / STEP_WIDTH as f64;
+                    let window = 0.42 - 0.5 * (2.0 * PI * window_position).cos()
+                        + 0.08 * (4.0 * PI * window_position).cos();
+
+                    *tap = sinc * window;
+                }
+
+                let sum: f64 = taps.iter().sum();
+                taps.map(|tap| (tap / sum) as f32)
+            })
+            .collect()
+    }
+}
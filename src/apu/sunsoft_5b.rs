@@ -0,0 +1,136 @@
+/* CPU addresses of the 5B registers, as wired on FME-7 boards */
+pub const REGISTER_SELECT_ADDRESS_LO: u16 = 0xC000;
+pub const REGISTER_SELECT_ADDRESS_HI: u16 = 0xDFFF;
+pub const REGISTER_WRITE_ADDRESS_LO: u16 = 0xE000;
+pub const REGISTER_WRITE_ADDRESS_HI: u16 = 0xFFFF;
+
+/* Number of CPU cycles between two clocks of the tone timers */
+pub const TONE_PRESCALER: u8 = 16;
+
+/*
+ * Output level of each of the 16 volumes. The chip has a
+ * logarithmic DAC, every step being 3dB apart.
+ */
+const VOLUME_LEVELS: [f32; 16] = {
+    let mut levels = [0.0; 16];
+    let mut level = 1.0;
+    let mut volume = 15;
+
+    while volume > 0 {
+        levels[volume] = level;
+        level *= 0.707_945_8;
+        volume -= 1;
+    }
+
+    levels
+};
+
+/*
+ * A square wave channel of the 5B. Its output flips every
+ * time the timer runs through the period.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Sunsoft5bChannel {
+    period: u16,
+    timer: u16,
+    volume: u8,
+    is_tone_enabled: bool,
+    is_high: bool,
+}
+
+impl Sunsoft5bChannel {
+    /* Clocked every 16 CPU cycles */
+    fn clock_timer(&mut self) {
+        self.timer += 1;
+
+        if self.timer >= self.period {
+            self.timer = 0;
+            self.is_high = !self.is_high;
+        }
+    }
+
+    /* Current output level, between 0.0 and 1.0 */
+    pub fn output(&self) -> f32 {
+        /* A channel with its tone disabled outputs a constant level */
+        if self.is_high || !self.is_tone_enabled {
+            VOLUME_LEVELS[self.volume as usize]
+        } else {
+            0.0
+        }
+    }
+}
+
+/*
+ * The sound chip of Sunsoft's 5B, a variant of the AY-3-8910
+ * with three square wave channels. Its 16 internal registers
+ * are written by selecting one first, then sending its value.
+ *
+ * Only the square waves are emulated, the noise and envelope
+ * generators are left out as no game is known to use them.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Sunsoft5bAudio {
+    channels: [Sunsoft5bChannel; 3],
+    selected_register: u8,
+    prescaler: u8,
+}
+
+impl Sunsoft5bAudio {
+    /* Initializes a new silent 5B sound chip */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Writes the register select or the register value port */
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            REGISTER_SELECT_ADDRESS_LO..=REGISTER_SELECT_ADDRESS_HI => {
+                self.selected_register = value & 0x0F
+            }
+            REGISTER_WRITE_ADDRESS_LO..=REGISTER_WRITE_ADDRESS_HI => {
+                self.write_register(self.selected_register, value)
+            }
+            _ => {}
+        }
+    }
+
+    /* Writes one of the 16 internal registers */
+    fn write_register(&mut self, register: u8, value: u8) {
+        match register {
+            0x00..=0x05 => {
+                let channel = &mut self.channels[register as usize / 2];
+
+                channel.period = if register & 0x01 == 0 {
+                    (channel.period & 0x0F00) | value as u16
+                } else {
+                    (channel.period & 0x00FF) | ((value as u16 & 0x0F) << 8)
+                };
+            }
+            0x07 => {
+                for (index, channel) in self.channels.iter_mut().enumerate() {
+                    channel.is_tone_enabled = value & (1 << index) == 0;
+                }
+            }
+            0x08..=0x0A => self.channels[register as usize - 0x08].volume = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    /* Advances the channels by a single CPU cycle */
+    pub fn clock(&mut self) {
+        self.prescaler += 1;
+
+        if self.prescaler == TONE_PRESCALER {
+            self.prescaler = 0;
+
+            for channel in self.channels.iter_mut() {
+                channel.clock_timer();
+            }
+        }
+    }
+
+    /* Gives access to one of the three channels */
+    pub fn channel(&self, index: usize) -> &Sunsoft5bChannel {
+        &self.channels[index]
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * Periodically bends the pitch of a pulse channel up or down
+ * by adding a shifted copy of the timer period to itself.
+ *
+ * The first pulse channel negates the change with a ones'
+ * complement and the second one with a twos' complement,
+ * so they do not sweep down by the same amount.
+ */
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    ones_complement: bool,
+    enabled: bool,
+    negate: bool,
+    period: u8,
+    shift: u8,
+    divider: u8,
+    reload: bool,
+}
+
+impl Sweep {
+    /* Initializes a new Sweep, negating with a ones' complement for pulse 1 */
+    pub fn new(ones_complement: bool) -> Self {
+        Self {
+            ones_complement,
+            enabled: false,
+            negate: false,
+            period: 0,
+            shift: 0,
+            divider: 0,
+            reload: false,
+        }
+    }
+
+    /* Sets up the sweep from its EPPP NSSS register */
+    pub fn write(&mut self, value: u8) {
+        self.enabled = value & 0x80 != 0;
+        self.period = (value >> 4) & 0x07;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 0x07;
+        self.reload = true;
+    }
+
+    /*
+     * Timer period the sweep is heading to. It is computed all
+     * the time, even when the sweep is disabled, since it can
+     * mute the channel by going past $7FF.
+     */
+    pub fn get_target_period(&self, period: u16) -> u16 {
+        let change = period >> self.shift;
+
+        if !self.negate {
+            return period + change;
+        }
+
+        let change = if self.ones_complement {
+            change + 1
+        } else {
+            change
+        };
+
+        period.saturating_sub(change)
+    }
+
+    /* Specifies whether the channel is silenced by its period */
+    pub fn is_muting(&self, period: u16) -> bool {
+        period < 8 || self.get_target_period(period) > 0x07FF
+    }
+
+    /*
+     * Clocked by the frame counter on every half frame, returns
+     * the new timer period of the channel
+     */
+    pub fn clock(&mut self, period: u16) -> u16 {
+        let mut period = period;
+
+        if self.divider == 0 && self.enabled && self.shift > 0 && !self.is_muting(period) {
+            period = self.get_target_period(period);
+        }
+
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+
+        period
+    }
+}
@@ -0,0 +1,211 @@
+/* CPU addresses of the VRC6 sound registers, as wired on mapper 24 boards */
+pub const PULSE_1_ADDRESS_LO: u16 = 0x9000;
+pub const PULSE_1_ADDRESS_HI: u16 = 0x9002;
+pub const FREQUENCY_CONTROL_ADDRESS: u16 = 0x9003;
+pub const PULSE_2_ADDRESS_LO: u16 = 0xA000;
+pub const PULSE_2_ADDRESS_HI: u16 = 0xA002;
+pub const SAWTOOTH_ADDRESS_LO: u16 = 0xB000;
+pub const SAWTOOTH_ADDRESS_HI: u16 = 0xB002;
+
+/*
+ * A VRC6 square wave channel. It walks over 16 steps and
+ * outputs its volume while the step is within the duty
+ * cycle, or all the time in digitized mode.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    is_digitized: bool,
+    is_enabled: bool,
+    step: u8,
+    timer_period: u16,
+    timer: u16,
+}
+
+impl Vrc6Pulse {
+    /* Writes one of the 3 registers of the channel */
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.volume = value & 0x0F;
+                self.duty = (value >> 4) & 0x07;
+                self.is_digitized = value & 0x80 != 0;
+            }
+            1 => self.timer_period = (self.timer_period & 0x0F00) | value as u16,
+            2 => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.is_enabled = value & 0x80 != 0;
+
+                if !self.is_enabled {
+                    self.step = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /* Clocked on every CPU cycle, the period being shifted by the frequency control */
+    pub fn clock_timer(&mut self, shift: u8) {
+        if !self.is_enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.timer_period >> shift;
+            self.step = (self.step + 1) & 0x0F;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /* Current output level, between 0 and 15 */
+    pub fn output(&self) -> u8 {
+        if self.is_enabled && (self.is_digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+/*
+ * The VRC6 sawtooth channel. An accumulator is increased by
+ * the rate on every other step and cleared on the 14th one,
+ * its upper 5 bits giving the output.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Vrc6Sawtooth {
+    rate: u8,
+    accumulator: u8,
+    is_enabled: bool,
+    step: u8,
+    timer_period: u16,
+    timer: u16,
+}
+
+impl Vrc6Sawtooth {
+    /* Writes one of the 3 registers of the channel */
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.rate = value & 0x3F,
+            1 => self.timer_period = (self.timer_period & 0x0F00) | value as u16,
+            2 => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.is_enabled = value & 0x80 != 0;
+
+                if !self.is_enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /* Clocked on every CPU cycle, the period being shifted by the frequency control */
+    pub fn clock_timer(&mut self, shift: u8) {
+        if !self.is_enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.timer_period >> shift;
+            self.step += 1;
+
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if self.step & 0x01 == 0 {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /* Current output level, between 0 and 31 */
+    pub fn output(&self) -> u8 {
+        if self.is_enabled {
+            self.accumulator >> 3
+        } else {
+            0
+        }
+    }
+}
+
+/*
+ * The sound chip of Konami's VRC6, adding two pulse channels
+ * and a sawtooth one to the console. They share a frequency
+ * control register able to halt them all or to speed them
+ * up by 16 or 256 times.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Vrc6Audio {
+    pulse_1: Vrc6Pulse,
+    pulse_2: Vrc6Pulse,
+    sawtooth: Vrc6Sawtooth,
+
+    is_halted: bool,
+    /* Number of bits the periods are shifted right by */
+    frequency_shift: u8,
+}
+
+impl Vrc6Audio {
+    /* Initializes a new silent VRC6 sound chip */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Writes a sound register, anything else being ignored */
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            PULSE_1_ADDRESS_LO..=PULSE_1_ADDRESS_HI => {
+                self.pulse_1.write(address - PULSE_1_ADDRESS_LO, value)
+            }
+            FREQUENCY_CONTROL_ADDRESS => {
+                self.is_halted = value & 0x01 != 0;
+                self.frequency_shift = if value & 0x04 != 0 {
+                    8
+                } else if value & 0x02 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            PULSE_2_ADDRESS_LO..=PULSE_2_ADDRESS_HI => {
+                self.pulse_2.write(address - PULSE_2_ADDRESS_LO, value)
+            }
+            SAWTOOTH_ADDRESS_LO..=SAWTOOTH_ADDRESS_HI => {
+                self.sawtooth.write(address - SAWTOOTH_ADDRESS_LO, value)
+            }
+            _ => {}
+        }
+    }
+
+    /* Advances the channels by a single CPU cycle */
+    pub fn clock(&mut self) {
+        if self.is_halted {
+            return;
+        }
+
+        self.pulse_1.clock_timer(self.frequency_shift);
+        self.pulse_2.clock_timer(self.frequency_shift);
+        self.sawtooth.clock_timer(self.frequency_shift);
+    }
+
+    /* Gives access to the first pulse channel */
+    pub fn pulse_1(&self) -> &Vrc6Pulse {
+        &self.pulse_1
+    }
+
+    /* Gives access to the second pulse channel */
+    pub fn pulse_2(&self) -> &Vrc6Pulse {
+        &self.pulse_2
+    }
+
+    /* Gives access to the sawtooth channel */
+    pub fn sawtooth(&self) -> &Vrc6Sawtooth {
+        &self.sawtooth
+    }
+}
@@ -0,0 +1,85 @@
+use crate::apu::mixer::{Channel, ChannelLevels};
+use std::collections::VecDeque;
+
+/* Number of samples kept for each channel */
+pub const WAVEFORM_CAPACITY: usize = 1024;
+
+/*
+ * Number of CPU cycles between two recorded samples, giving
+ * about 2 frames of history at a rate close to 44.1 kHz
+ */
+pub const WAVEFORM_DECIMATION: u32 = 40;
+
+/*
+ * Keeps the recent output of each channel in small ring
+ * buffers, for frontends drawing oscilloscope views of them.
+ * Levels are normalized between 0.0 and 1.0 and capturing is
+ * disabled by default.
+ */
+#[derive(Debug, Clone)]
+pub struct WaveformHistory {
+    is_enabled: bool,
+    cycles: u32,
+    channels: Vec<VecDeque<f32>>,
+}
+
+impl WaveformHistory {
+    /* Initializes a new empty history */
+    pub fn new() -> Self {
+        Self {
+            is_enabled: false,
+            cycles: 0,
+            channels: vec![VecDeque::with_capacity(WAVEFORM_CAPACITY); Channel::ALL.len()],
+        }
+    }
+
+    /* Starts or stops capturing, clearing the history when stopped */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.is_enabled = enabled;
+
+        if !enabled {
+            for channel in self.channels.iter_mut() {
+                channel.clear();
+            }
+        }
+    }
+
+    /* Specifies whether channel outputs are being captured */
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    /* Records the channel levels of a CPU cycle, only keeping one every few cycles */
+    pub fn push(&mut self, levels: &ChannelLevels) {
+        if !self.is_enabled {
+            return;
+        }
+
+        self.cycles += 1;
+
+        if self.cycles < WAVEFORM_DECIMATION {
+            return;
+        }
+
+        self.cycles = 0;
+
+        for (channel, history) in Channel::ALL.iter().zip(self.channels.iter_mut()) {
+            if history.len() == WAVEFORM_CAPACITY {
+                history.pop_front();
+            }
+
+            history.push_back(levels[*channel as usize] / channel.max_level());
+        }
+    }
+
+    /* The recent output of a channel, oldest sample first */
+    pub fn channel(&self, channel: Channel) -> &VecDeque<f32> {
+        &self.channels[channel as usize]
+    }
+}
+
+impl Default for WaveformHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,13 @@
 use std::rc::Rc;
 
-use crate::{cartridge::cartridge::Cartridge, memory::memory::Memory, ppu::ppu::PPU};
+use crate::{
+    apu::apu::APU,
+    cartridge::cartridge::Cartridge,
+    cheats::cheats::CheatList,
+    input::ports::InputPorts,
+    memory::memory::Memory,
+    ppu::ppu::{OAMDATA, PPU},
+};
 
 /* Hard-wired memory address boundaries for all physical
  * devices accessible by the CPU.
@@ -9,6 +16,15 @@ pub const RAM_ADDRESS_LO: u16 = 0x0000;
 pub const RAM_ADDRESS_HI: u16 = 0x1FFF;
 pub const PPU_REGISTERS_ADDRESS_LO: u16 = 0x2000;
 pub const PPU_REGISTERS_ADDRESS_HI: u16 = 0x3FFF;
+pub const APU_REGISTERS_ADDRESS_LO: u16 = 0x4000;
+pub const APU_REGISTERS_ADDRESS_HI: u16 = 0x4013;
+pub const OAM_DMA_ADDRESS: u16 = 0x4014;
+pub const APU_STATUS_ADDRESS: u16 = 0x4015;
+pub const CONTROLLER_1_ADDRESS: u16 = 0x4016;
+pub const CONTROLLER_2_ADDRESS: u16 = 0x4017;
+pub const APU_FRAME_COUNTER_ADDRESS: u16 = 0x4017;
+pub const CARTRIDGE_EXPANSION_ADDRESS_LO: u16 = 0x4020;
+pub const CARTRIDGE_EXPANSION_ADDRESS_HI: u16 = 0x7FFF;
 pub const CARTRIDGE_PRG_ADDRESS_LO: u16 = 0x8000;
 pub const CARTRIDGE_PRG_ADDRESS_HI: u16 = 0xFFFF;
 
@@ -23,6 +39,22 @@ pub const NMI_VECTOR_ADDRESS_HI: u16 = 0xFFFB;
 pub const RESET_VECTOR_ADDRESS_LO: u16 = 0xFFFC;
 pub const RESET_VECTOR_ADDRESS_HI: u16 = 0xFFFD;
 
+/*
+ * Number of cycles the CPU is halted for while the OAM DMA
+ * copies a whole page to the PPU, one read and one write
+ * per byte plus a dummy cycle
+ */
+pub const OAM_DMA_CYCLES: u16 = 513;
+
+/*
+ * Data lines driven by the devices of the controller ports,
+ * the others keeping the last value seen on the bus
+ */
+pub const CONTROLLER_DATA_MASK: u8 = 0x1F;
+
+/* Number of cycles the CPU is halted for while the DMC fetches a sample byte */
+pub const DMC_DMA_CYCLES: u16 = 4;
+
 /*
  * Represents the main communication component that allows
  * the CPU to interact with other hardware devices such as
@@ -35,50 +67,189 @@ pub const RESET_VECTOR_ADDRESS_HI: u16 = 0xFFFD;
 pub struct CpuBus {
     ram: Memory,
     ppu: PPU,
+    apu: APU,
     cartridge: Rc<Cartridge>,
+    cheats: CheatList,
+    /* Devices plugged into the controller ports */
+    input: InputPorts,
+
+    /* CPU cycles to be stolen by devices accessing the bus */
+    stall_cycles: u16,
+    /* Last value seen on the data bus, read back from lines nothing drives */
+    open_bus: u8,
 }
 
 impl CpuBus {
     /* Initializing a new CPU BUS */
-    pub fn new(ram: Memory, ppu: PPU, cartridge: Rc<Cartridge>) -> Self {
+    pub fn new(ram: Memory, ppu: PPU, apu: APU, cartridge: Rc<Cartridge>) -> Self {
         Self {
             ram,
             ppu,
+            apu,
             cartridge,
+            cheats: CheatList::new(),
+            input: InputPorts::new(),
+            stall_cycles: 0,
+            open_bus: 0,
         }
     }
 
-    /* Reading from specific address */
-    pub fn read(&self, address: u16) -> u8 {
-        match address {
-            RAM_ADDRESS_LO..=RAM_ADDRESS_HI => {
-                self.ram.read(self.get_mirrored_ram_address(address))
-            }
+    /* Reading from specific address, as altered by the enabled cheats */
+    pub fn read(&mut self, address: u16) -> u8 {
+        let value = match address {
+            RAM_ADDRESS_LO..=RAM_ADDRESS_HI => self
+                .ram
+                .read(self.get_mirrored_ram_address(address) as usize),
             PPU_REGISTERS_ADDRESS_LO..=PPU_REGISTERS_ADDRESS_HI => {
-                self.ppu.read(self.get_mirrored_ppu_address(address))
+                let address = self.get_mirrored_ppu_address(address);
+                self.ppu.read(address)
+            }
+            APU_STATUS_ADDRESS => self.apu.read_status(),
+            CONTROLLER_1_ADDRESS | CONTROLLER_2_ADDRESS => {
+                let data = self
+                    .input
+                    .read((address - CONTROLLER_1_ADDRESS) as usize, &self.ppu);
+
+                (self.open_bus & !CONTROLLER_DATA_MASK) | (data & CONTROLLER_DATA_MASK)
+            }
+            CARTRIDGE_EXPANSION_ADDRESS_LO..=CARTRIDGE_EXPANSION_ADDRESS_HI
+            | CARTRIDGE_PRG_ADDRESS_LO..=CARTRIDGE_PRG_ADDRESS_HI => {
+                self.cartridge.prg_read(address)
             }
-            CARTRIDGE_PRG_ADDRESS_LO..=CARTRIDGE_PRG_ADDRESS_HI => self.cartridge.prg_read(address),
             _ => 0,
-        }
+        };
+
+        self.open_bus = self.cheats.apply(address, value);
+        self.open_bus
     }
 
     /* Writing to a specific address */
-    pub fn write(&self, address: u16, value: u8) {
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.open_bus = value;
+
         match address {
             RAM_ADDRESS_LO..=RAM_ADDRESS_HI => self
                 .ram
-                .write(self.get_mirrored_ram_address(address), value),
+                .write(self.get_mirrored_ram_address(address) as usize, value),
             PPU_REGISTERS_ADDRESS_LO..=PPU_REGISTERS_ADDRESS_HI => {
-                self.ppu
-                    .write(self.get_mirrored_ppu_address(address), value);
+                let address = self.get_mirrored_ppu_address(address);
+                self.ppu.write(address, value);
+                self.cartridge.notify_ppu_register_write(address, value);
+            }
+            APU_REGISTERS_ADDRESS_LO..=APU_REGISTERS_ADDRESS_HI => {
+                self.apu.write(address - APU_REGISTERS_ADDRESS_LO, value)
+            }
+            OAM_DMA_ADDRESS => self.start_oam_dma(value),
+            CONTROLLER_1_ADDRESS => self.input.write(value),
+            APU_STATUS_ADDRESS | APU_FRAME_COUNTER_ADDRESS => {
+                self.apu.write(address - APU_REGISTERS_ADDRESS_LO, value)
             }
-            CARTRIDGE_PRG_ADDRESS_LO..=CARTRIDGE_PRG_ADDRESS_HI => {
+            CARTRIDGE_EXPANSION_ADDRESS_LO..=CARTRIDGE_EXPANSION_ADDRESS_HI
+            | CARTRIDGE_PRG_ADDRESS_LO..=CARTRIDGE_PRG_ADDRESS_HI => {
+                self.apu
+                    .write_expansion(self.cartridge.audio_address(address), value);
                 self.cartridge.prg_write(address, value)
             }
             _ => {}
         }
     }
 
+    /* Gives access to the inserted cartridge */
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    /*
+     * State of the shared IRQ line of the CPU. Every device able
+     * to interrupt pulls it low for as long as its request has
+     * not been acknowledged.
+     */
+    pub fn is_irq_pending(&self) -> bool {
+        self.apu.is_irq_pending() || self.cartridge.irq_pending()
+    }
+
+    /*
+     * Returns the number of cycles the CPU has to be halted for
+     * since the last call, acknowledging them at the same time
+     */
+    pub fn take_stall_cycles(&mut self) -> u16 {
+        let cycles = self.stall_cycles;
+        self.stall_cycles = 0;
+
+        cycles
+    }
+
+    /* Gives access to the cheats applied to the reads */
+    pub fn cheats(&self) -> &CheatList {
+        &self.cheats
+    }
+
+    /* Gives mutable access to the cheats applied to the reads */
+    pub fn cheats_mut(&mut self) -> &mut CheatList {
+        &mut self.cheats
+    }
+
+    /* Gives access to the devices plugged into the controller ports */
+    pub fn input(&self) -> &InputPorts {
+        &self.input
+    }
+
+    /* Gives mutable access to the devices plugged into the controller ports */
+    pub fn input_mut(&mut self) -> &mut InputPorts {
+        &mut self.input
+    }
+
+    /* Gives access to the PPU connected to the bus */
+    pub fn ppu(&self) -> &PPU {
+        &self.ppu
+    }
+
+    /* Gives mutable access to the PPU connected to the bus */
+    pub fn ppu_mut(&mut self) -> &mut PPU {
+        &mut self.ppu
+    }
+
+    /*
+     * Advances the APU by a single CPU cycle, then services the
+     * memory reader of the DMC which steals cycles from the CPU
+     * to fetch its sample bytes
+     */
+    pub fn clock_apu(&mut self) {
+        self.apu.clock();
+
+        if let Some(address) = self.apu.dmc().get_pending_read() {
+            let value = self.read(address);
+
+            self.apu.dmc_mut().load_sample(value);
+            self.stall_cycles += DMC_DMA_CYCLES;
+        }
+    }
+
+    /* Gives access to the APU connected to the bus */
+    pub fn apu(&self) -> &APU {
+        &self.apu
+    }
+
+    /* Gives mutable access to the APU connected to the bus */
+    pub fn apu_mut(&mut self) -> &mut APU {
+        &mut self.apu
+    }
+
+    /*
+     * Copies a whole 256 bytes page, starting at $XX00, into
+     * the PPU OAM while the CPU is suspended
+     */
+    fn start_oam_dma(&mut self, page: u8) {
+        let base = (page as u16) << 8;
+
+        for offset in 0..256 {
+            let value = self.read(base + offset);
+            self.ppu.write(OAMDATA, value);
+        }
+
+        self.stall_cycles += OAM_DMA_CYCLES;
+    }
+
     /*
      * The NES uses only 2KB of its total 8KB RAM, so all memory locations
      * must be mirrored within first 2KB
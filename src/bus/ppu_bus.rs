@@ -1,24 +1,216 @@
-use crate::cartridge::cartridge::Cartridge;
-use std::rc::Rc;
+use crate::{
+    cartridge::cartridge::{Cartridge, Mirroring},
+    errors::AppResult,
+    memory::memory::Memory,
+    state::state::{StateReader, StateWriter},
+};
+use std::{cell::Cell, rc::Rc};
 
+/*
+ * Hard-wired memory address boundaries for all physical
+ * devices accessible by the PPU.
+ */
 pub const CARTRIDGE_CHR_ADDRESS_LO: u16 = 0x0000;
 pub const CARTRIDGE_CHR_ADDRESS_HI: u16 = 0x1FFF;
+pub const NAMETABLES_ADDRESS_LO: u16 = 0x2000;
+pub const NAMETABLES_ADDRESS_HI: u16 = 0x3EFF;
+pub const PALETTE_ADDRESS_LO: u16 = 0x3F00;
+pub const PALETTE_ADDRESS_HI: u16 = 0x3FFF;
 
+/* Size of a single nametable inside the PPU VRAM */
+pub const NAMETABLE_SIZE: u16 = 0x0400;
+
+/*
+ * Minimum number of dots the A12 address line has to stay low
+ * before a rise is reported. Mappers filter out the short
+ * pulses caused by consecutive pattern fetches this way.
+ */
+pub const A12_FILTER_DOTS: u64 = 10;
+
+/*
+ * Represents the bus used by the PPU to reach the pattern
+ * tables inside the cartridge, its own 2KB of nametable
+ * VRAM and the 32 bytes of palette memory.
+ */
 pub struct PpuBus {
     cartridge: Rc<Cartridge>,
+    vram: Memory,
+    palette: Memory,
+
+    /* Number of dots elapsed, used to time the A12 line */
+    dots: u64,
+    /* Last observed state of the A12 address line */
+    a12: Cell<bool>,
+    /* Dot at which the A12 line last went low */
+    a12_low_since: Cell<u64>,
 }
 
 impl PpuBus {
+    /* Initializing a new PPU BUS */
     pub fn new(cartridge: Rc<Cartridge>) -> Self {
-        Self { cartridge }
+        /* Four-screen boards add their own 2KB of nametable RAM */
+        let vram_size = match cartridge.mirroring() {
+            Mirroring::FourScreen => 4 * NAMETABLE_SIZE as usize,
+            _ => 2 * NAMETABLE_SIZE as usize,
+        };
+
+        Self {
+            cartridge,
+            vram: Memory::new(vram_size),
+            palette: Memory::new(32),
+            dots: 0,
+            a12: Cell::new(false),
+            a12_low_since: Cell::new(0),
+        }
     }
 
-    fn read(&self, address: u16) -> u8 {
+    /* Advances the bus clock by a single dot */
+    pub fn clock(&mut self) {
+        self.dots += 1;
+    }
+
+    /* Number of dots elapsed since power on */
+    pub fn dots(&self) -> u64 {
+        self.dots
+    }
+
+    /* Reading from specific address */
+    pub fn read(&self, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+        self.track_a12(address);
+        self.cartridge.notify_ppu_read(address);
+
+        self.peek(address)
+    }
+
+    /*
+     * Reading from specific address without any of the side
+     * effects a real access has on the cartridge
+     */
+    pub fn peek(&self, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+
         match address {
             CARTRIDGE_CHR_ADDRESS_LO..=CARTRIDGE_CHR_ADDRESS_HI => self.cartridge.chr_read(address),
+            NAMETABLES_ADDRESS_LO..=NAMETABLES_ADDRESS_HI => {
+                self.cartridge.nametable_read(address).unwrap_or_else(|| {
+                    self.vram
+                        .read(self.get_mirrored_nametable_address(address) as usize)
+                })
+            }
+            PALETTE_ADDRESS_LO..=PALETTE_ADDRESS_HI => self
+                .palette
+                .read(self.get_mirrored_palette_address(address) as usize),
             _ => 0,
         }
     }
 
-    fn write(&self, address: u16, value: u8) {}
+    /* Writing to a specific address */
+    pub fn write(&self, address: u16, value: u8) {
+        let address = address & 0x3FFF;
+        self.track_a12(address);
+
+        self.poke(address, value);
+    }
+
+    /*
+     * Writing to a specific address without any of the side
+     * effects a real access has on the cartridge
+     */
+    pub fn poke(&self, address: u16, value: u8) {
+        let address = address & 0x3FFF;
+
+        match address {
+            CARTRIDGE_CHR_ADDRESS_LO..=CARTRIDGE_CHR_ADDRESS_HI => {
+                self.cartridge.chr_write(address, value)
+            }
+            NAMETABLES_ADDRESS_LO..=NAMETABLES_ADDRESS_HI
+                if !self.cartridge.nametable_write(address, value) =>
+            {
+                self.vram
+                    .write(self.get_mirrored_nametable_address(address) as usize, value)
+            }
+            PALETTE_ADDRESS_LO..=PALETTE_ADDRESS_HI => self
+                .palette
+                .write(self.get_mirrored_palette_address(address) as usize, value),
+            _ => {}
+        }
+    }
+
+    /* Saves the nametable and palette memory along with the A12 line state */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.vram.save_state(state);
+        self.palette.save_state(state);
+        state.write_u64(self.dots);
+        state.write_bool(self.a12.get());
+        state.write_u64(self.a12_low_since.get());
+    }
+
+    /* Restores a state produced by save_state */
+    pub fn load_state(&mut self, state: &mut StateReader) -> AppResult<()> {
+        self.vram.load_state(state)?;
+        self.palette.load_state(state)?;
+        self.dots = state.read_u64()?;
+        self.a12.set(state.read_bool()?);
+        self.a12_low_since.set(state.read_u64()?);
+
+        Ok(())
+    }
+
+    /*
+     * Watches the A12 address line, which goes high whenever the
+     * upper pattern table is accessed. Rises happening after the
+     * line was low long enough are reported to the cartridge,
+     * this is what scanline counting mappers are clocked by.
+     */
+    fn track_a12(&self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+
+        if a12 && !self.a12.get() {
+            if self.dots - self.a12_low_since.get() >= A12_FILTER_DOTS {
+                self.cartridge.notify_a12_rise();
+            }
+        } else if !a12 && self.a12.get() {
+            self.a12_low_since.set(self.dots);
+        }
+
+        self.a12.set(a12);
+    }
+
+    /*
+     * The PPU addresses four logical nametables but only has
+     * room for two of them, so the cartridge decides how the
+     * logical ones are folded onto the physical VRAM.
+     */
+    fn get_mirrored_nametable_address(&self, address: u16) -> u16 {
+        let address = (address - NAMETABLES_ADDRESS_LO) & 0x0FFF;
+        let table = address / NAMETABLE_SIZE;
+        let offset = address % NAMETABLE_SIZE;
+
+        match self.cartridge.mirroring() {
+            Mirroring::Horizontal => (table >> 1) * NAMETABLE_SIZE + offset,
+            Mirroring::Vertical => (table & 0x01) * NAMETABLE_SIZE + offset,
+            Mirroring::SingleScreenLower => offset,
+            Mirroring::SingleScreenUpper => NAMETABLE_SIZE + offset,
+            Mirroring::Custom(pages) => {
+                (pages[table as usize] & 0x01) as u16 * NAMETABLE_SIZE + offset
+            }
+            Mirroring::FourScreen => table * NAMETABLE_SIZE + offset,
+        }
+    }
+
+    /*
+     * Palette memory is only 32 bytes long and the background
+     * color entries of the sprite palettes ($3F10, $3F14,
+     * $3F18, $3F1C) are mirrors of the background ones.
+     */
+    fn get_mirrored_palette_address(&self, address: u16) -> u16 {
+        let address = address & 0x001F;
+
+        if address & 0x0013 == 0x0010 {
+            address & !0x0010
+        } else {
+            address
+        }
+    }
 }
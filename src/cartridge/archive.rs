@@ -0,0 +1,38 @@
+use crate::errors::{AppError, AppResult};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/* First bytes of a zip file, those of its first local file header */
+pub const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/* Extensions of the ROM files looked for inside an archive */
+const ROM_EXTENSIONS: [&str; 3] = [".nes", ".unf", ".unif"];
+
+/*
+ * Decompresses the ROM held by a zip archive. Collections
+ * sometimes ship extra files along with the ROM, so the
+ * largest entry with a ROM extension is picked.
+ */
+pub fn extract_rom(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut rom = None;
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index)?;
+        let name = entry.name().to_lowercase();
+
+        let is_rom = ROM_EXTENSIONS
+            .iter()
+            .any(|extension| name.ends_with(extension));
+
+        if is_rom && rom.is_none_or(|(_, size)| entry.size() > size) {
+            rom = Some((index, entry.size()));
+        }
+    }
+
+    let (index, size) = rom.ok_or(AppError::NoRomInArchive)?;
+    let mut bytes = Vec::with_capacity(size as usize);
+    archive.by_index(index)?.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,57 @@
+use crate::{cartridge::cartridge::Cartridge, errors::AppResult};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/* Number of frames between two writes of the save file, about 10 seconds */
+pub const SAVE_INTERVAL_FRAMES: u64 = 600;
+
+/*
+ * The .sav file keeping the battery backed PRG RAM of a
+ * cartridge. It is loaded once the game is inserted, then
+ * written again whenever the RAM content has changed.
+ */
+pub struct BatterySave {
+    path: PathBuf,
+    /* PRG RAM content as last read from or written to the file */
+    saved: Vec<u8>,
+}
+
+impl BatterySave {
+    /*
+     * Opens the save file at the given path, restoring its content
+     * into the PRG RAM. A missing file is created on the next write.
+     */
+    pub fn open<P: AsRef<Path>>(path: P, cartridge: &Cartridge) -> AppResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        match fs::read(&path) {
+            Ok(bytes) => cartridge.load_prg_ram(&bytes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(Self {
+            path,
+            saved: cartridge.prg_ram(),
+        })
+    }
+
+    /* Save file path of a ROM, sitting next to it with the .sav extension */
+    pub fn path_for_rom<P: AsRef<Path>>(rom_path: P) -> PathBuf {
+        rom_path.as_ref().with_extension("sav")
+    }
+
+    /* Writes the PRG RAM to the save file, unless it did not change */
+    pub fn flush(&mut self, cartridge: &Cartridge) -> AppResult<()> {
+        let prg_ram = cartridge.prg_ram();
+
+        if prg_ram != self.saved {
+            fs::write(&self.path, &prg_ram)?;
+            self.saved = prg_ram;
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,33 @@
 use crate::{
-    cartridge::mapper::Mapper,
+    apu::apu::ExpansionChips,
+    cartridge::{
+        archive::{self, ZIP_MAGIC},
+        crc32::Crc32,
+        game_database::GameDatabase,
+        mapper::{
+            self, CHR_BANK_SIZE, CartridgeMemory, Mapper, PRG_BANK_SIZE, PRG_RAM_ADDRESS_LO,
+            PRG_RAM_SIZE,
+        },
+        mappers::nrom::Nrom,
+        rom_info::{ConsoleType, RomFormat, RomHash, RomInfo},
+        unif::{UNIF_MAGIC, Unif},
+    },
+    console::region::Region,
     errors::{AppError, AppResult},
+    input::ports::InputDevice,
     memory::memory::Memory,
+    nsf::{board::NsfBoard, nsf::Nsf},
+    patch::patch,
+    state::state::{StateReader, StateWriter},
 };
 use bitflags::bitflags;
+use std::{fs, path::Path};
+
+pub const HEADER_SIZE: usize = 16;
+
+/* Optional block of code between the header and PRG ROM, loaded at $7000 */
+pub const TRAINER_SIZE: usize = 512;
+pub const TRAINER_ADDRESS: u16 = 0x7000;
 
 /*
  * Each bit indicates a specific cartridge feature or configuration
@@ -32,12 +56,69 @@ bitflags! {
     }
 }
 
-/* First 16 bytes of iNES file header */
+/* How the nametables are mirrored inside the PPU VRAM */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mirroring {
+    #[default]
+    Horizontal,
+    Vertical,
+    /* All four nametables show the first or the second 1KB of VRAM */
+    SingleScreenLower,
+    SingleScreenUpper,
+    /* Each of the four nametables picks one of the two 1KB pages of VRAM */
+    Custom([u8; 4]),
+    /* The cartridge brings 2KB of extra VRAM, so nothing is mirrored */
+    FourScreen,
+}
+
+impl Mirroring {
+    /* Saves the kind of mirroring along with the pages of a custom one */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        let (kind, pages) = match self {
+            Mirroring::Horizontal => (0, [0; 4]),
+            Mirroring::Vertical => (1, [0; 4]),
+            Mirroring::SingleScreenLower => (2, [0; 4]),
+            Mirroring::SingleScreenUpper => (3, [0; 4]),
+            Mirroring::Custom(pages) => (4, *pages),
+            Mirroring::FourScreen => (5, [0; 4]),
+        };
+
+        state.write_u8(kind);
+        state.write_bytes(&pages);
+    }
+
+    /* Reads back a mirroring saved by save_state */
+    pub fn load_state(state: &mut StateReader) -> AppResult<Self> {
+        let kind = state.read_u8()?;
+        let pages = state.read_array()?;
+
+        match kind {
+            0 => Ok(Mirroring::Horizontal),
+            1 => Ok(Mirroring::Vertical),
+            2 => Ok(Mirroring::SingleScreenLower),
+            3 => Ok(Mirroring::SingleScreenUpper),
+            4 => Ok(Mirroring::Custom(pages)),
+            5 => Ok(Mirroring::FourScreen),
+            _ => Err(AppError::InvalidSaveState),
+        }
+    }
+}
+
+/* First 16 bytes of an iNES or NES 2.0 file */
 struct Header {
     pub prg_banks: u8,
     pub chr_banks: u8,
     pub first_mapper_flags: MapperFirstFlags,
     pub second_mapper_flags: MapperSecondFlags,
+
+    /* Bytes 8 to 12 and 15, only meaningful in NES 2.0 headers */
+    pub mapper_extension: u8,
+    pub rom_size_extension: u8,
+    pub prg_ram_shifts: u8,
+    pub chr_ram_shifts: u8,
+    pub timing: u8,
+    /* Device the game expects in the controller or expansion ports */
+    pub expansion_device: u8,
 }
 
 impl Header {
@@ -62,93 +143,499 @@ impl Header {
             chr_banks,
             first_mapper_flags,
             second_mapper_flags,
+            mapper_extension: bytes[8],
+            rom_size_extension: bytes[9],
+            prg_ram_shifts: bytes[10],
+            chr_ram_shifts: bytes[11],
+            timing: bytes[12],
+            expansion_device: bytes[15],
         })
     }
 
+    /* NES 2.0 headers are told apart by bits 2 and 3 of byte 7 being 10 */
+    fn is_nes20(&self) -> bool {
+        self.second_mapper_flags.bits() & MapperSecondFlags::NES2_0_INDICATOR.bits() == 0b0000_1000
+    }
+
     /*
      * Calculates cartridge mapper ID from lower and higher
-     * mapper bits mask
+     * mapper bits mask, NES 2.0 adding 4 more bits on top
      */
-    fn get_mapper_id(&self) -> u8 {
+    fn get_mapper_id(&self) -> u16 {
         let lower =
             (self.first_mapper_flags.bits() & MapperFirstFlags::LOWER_MAPPER_BITS_MASK.bits()) >> 4;
         let upper =
             self.second_mapper_flags.bits() & MapperSecondFlags::UPPER_MAPPER_BITS_MASK.bits();
-        upper | lower
+        let id = (upper | lower) as u16;
+
+        if self.is_nes20() {
+            id | ((self.mapper_extension as u16 & 0x0F) << 8)
+        } else {
+            id
+        }
+    }
+
+    /* Nametable mirroring hard-wired on the cartridge board */
+    fn get_mirroring(&self) -> Mirroring {
+        if self
+            .first_mapper_flags
+            .contains(MapperFirstFlags::FOUR_SCREEN_VRAM)
+        {
+            Mirroring::FourScreen
+        } else if self
+            .first_mapper_flags
+            .contains(MapperFirstFlags::MIRRORING_VERTICAL)
+        {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    /*
+     * Size of a ROM in bytes. NES 2.0 headers extend the bank
+     * count with 4 more bits, and switch to an exponent and
+     * multiplier notation when those are all set.
+     */
+    fn get_rom_size(&self, banks: u8, extension: u8, bank_size: usize) -> usize {
+        if !self.is_nes20() {
+            return banks as usize * bank_size;
+        }
+
+        if extension == 0x0F {
+            let exponent = banks >> 2;
+            let multiplier = (banks & 0x03) as usize * 2 + 1;
+
+            /* Saturates so absurd sizes are reported as truncated files */
+            (1usize << exponent).saturating_mul(multiplier)
+        } else {
+            (((extension as usize) << 8) | banks as usize) * bank_size
+        }
+    }
+
+    /* Size of a RAM in bytes, given as a shift count of 64 */
+    fn get_ram_size(shift: u8) -> usize {
+        match shift {
+            0 => 0,
+            shift => 64 << shift,
+        }
+    }
+
+    /* Gathers everything the header tells about the ROM */
+    fn get_rom_info(&self) -> RomInfo {
+        let chr_rom_size =
+            self.get_rom_size(self.chr_banks, self.rom_size_extension >> 4, CHR_BANK_SIZE);
+        let info = RomInfo {
+            format: RomFormat::INes,
+            mapper_id: self.get_mapper_id(),
+            submapper: 0,
+            prg_rom_size: self.get_rom_size(
+                self.prg_banks,
+                self.rom_size_extension & 0x0F,
+                PRG_BANK_SIZE,
+            ),
+            chr_rom_size,
+            /* iNES boards are assumed to have work RAM, and CHR RAM when there is no CHR ROM */
+            prg_ram_size: PRG_RAM_SIZE,
+            prg_nvram_size: 0,
+            chr_ram_size: if chr_rom_size == 0 { CHR_BANK_SIZE } else { 0 },
+            chr_nvram_size: 0,
+            mirroring: self.get_mirroring(),
+            is_battery_backed: self
+                .first_mapper_flags
+                .contains(MapperFirstFlags::BATTERY_BACKED_RAM),
+            has_trainer: self
+                .first_mapper_flags
+                .contains(MapperFirstFlags::TRAINER_PRESENT),
+            console_type: match self.second_mapper_flags.bits() & 0x03 {
+                0x01 => ConsoleType::VsSystem,
+                0x02 => ConsoleType::PlayChoice10,
+                _ => ConsoleType::Nes,
+            },
+            region: Region::Ntsc,
+            ..RomInfo::default()
+        };
+
+        if !self.is_nes20() {
+            return info;
+        }
+
+        RomInfo {
+            format: RomFormat::Nes20,
+            submapper: self.mapper_extension >> 4,
+            prg_ram_size: Self::get_ram_size(self.prg_ram_shifts & 0x0F),
+            prg_nvram_size: Self::get_ram_size(self.prg_ram_shifts >> 4),
+            chr_ram_size: Self::get_ram_size(self.chr_ram_shifts & 0x0F),
+            chr_nvram_size: Self::get_ram_size(self.chr_ram_shifts >> 4),
+            console_type: match self.second_mapper_flags.bits() & 0x03 {
+                0x00 => ConsoleType::Nes,
+                0x01 => ConsoleType::VsSystem,
+                0x02 => ConsoleType::PlayChoice10,
+                _ => ConsoleType::Extended,
+            },
+            region: match self.timing & 0x03 {
+                0x01 => Region::Pal,
+                0x03 => Region::Dendy,
+                _ => Region::Ntsc,
+            },
+            input_device: match self.expansion_device {
+                0x02 => InputDevice::FourScore,
+                0x08 => InputDevice::Zapper,
+                0x23 => InputDevice::FamilyKeyboard,
+                _ => InputDevice::Controllers,
+            },
+            ..info
+        }
     }
 }
 
 /*
- * Represents a cartridge containing iNES game data.
+ * Represents a cartridge containing iNES, NES 2.0 or UNIF game data.
  *
- * Currently, the implementation only supports iNES 1.0 format
- * and games using Mapper 000
+ * The program and character data are owned by the mapper of
+ * the board, which every access goes through.
  */
 pub struct Cartridge {
-    header: Header,
+    mapper: Box<dyn Mapper>,
+    /* What the header told about the ROM */
+    info: RomInfo,
+
+    expansion_chips: ExpansionChips,
 
+    /* Board standing in for the cartridge while an NSF file is played */
+    nsf_board: Option<NsfBoard>,
+}
+
+impl Cartridge {
     /*
-     * Disassembled program data
-     * The CPU can read from and write to this memory region
+     * Initializes a new Cartridge out of an iNES, NES 2.0 or UNIF
+     * file, possibly zipped, its header being corrected by the
+     * embedded database
      */
-    prg_ram: Memory,
+    pub fn new(bytes: &[u8]) -> AppResult<Self> {
+        Self::with_database(bytes, GameDatabase::embedded())
+    }
+
     /*
-     * Character data or graphics stored in read-only memory (ROM)
-     * The PPU can only read data from this
+     * Loads an iNES, NES 2.0 or UNIF file from disk, possibly
+     * zipped. A patch sitting next to it with the same name is
+     * applied to it.
      */
-    chr_rom: Memory,
-    mapper: Mapper,
-}
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        match patch::find_patch(&path) {
+            Some(patch_path) => Self::from_file_with_patch(path, patch_path),
+            None => Self::new(&Self::read_rom(path)?),
+        }
+    }
 
-impl Cartridge {
-    /* Initializes a new Cartridge */
-    pub fn new(bytes: &[u8]) -> AppResult<Self> {
-        let header = Header::new(bytes)?;
+    /* Loads a ROM file from disk and applies the given patch file to it */
+    pub fn from_file_with_patch<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        patch_path: Q,
+    ) -> AppResult<Self> {
+        let rom = Self::read_rom(path)?;
+        let patch = fs::read(patch_path)?;
 
-        if header.get_mapper_id() != 0 {
-            return Err(AppError::InvalidCartridgeMapper);
+        Self::new(&patch::apply_patch(&rom, &patch)?)
+    }
+
+    /* Reads a ROM file, decompressing it if it is zipped */
+    fn read_rom<P: AsRef<Path>>(path: P) -> AppResult<Vec<u8>> {
+        let bytes = fs::read(path)?;
+
+        if bytes.starts_with(ZIP_MAGIC) {
+            archive::extract_rom(&bytes)
+        } else {
+            Ok(bytes)
+        }
+    }
+
+    /* Initializes a new Cartridge, its header being corrected by the given database */
+    pub fn with_database(bytes: &[u8], database: &GameDatabase) -> AppResult<Self> {
+        if bytes.starts_with(ZIP_MAGIC) {
+            return Self::with_database(&archive::extract_rom(bytes)?, database);
         }
 
-        let mut offset = 528;
+        if bytes.starts_with(UNIF_MAGIC) {
+            let unif = Unif::new(bytes)?;
+            let info = unif.get_rom_info()?;
 
-        let prg_memory_size = header.prg_banks as usize * 16384;
-        let chr_memory_size = header.chr_banks as usize * 8192;
+            return Self::from_roms(info, bytes, unif.prg_rom(), unif.chr_rom(), None, database);
+        }
 
-        let prg_ram = Memory::new(prg_memory_size);
-        let chr_rom = Memory::new(chr_memory_size);
+        let info = Header::new(bytes)?.get_rom_info();
 
-        prg_ram.write_chunk(0, &bytes[offset..offset + prg_memory_size]);
-        offset += prg_memory_size;
-        chr_rom.write_chunk(0, &bytes[offset..offset + chr_memory_size]);
+        if info.prg_rom_size == 0 {
+            return Err(AppError::MissingPrgRom);
+        }
 
-        let mapper = Mapper::new(header.prg_banks, header.chr_banks);
+        let mut offset = HEADER_SIZE;
 
-        Ok(Self {
-            header,
+        let trainer = if info.has_trainer {
+            Some(Self::get_section(
+                bytes,
+                &mut offset,
+                TRAINER_SIZE,
+                "trainer",
+            )?)
+        } else {
+            None
+        };
+
+        let prg_rom = Self::get_section(bytes, &mut offset, info.prg_rom_size, "PRG ROM")?;
+        let chr_rom = Self::get_section(bytes, &mut offset, info.chr_rom_size, "CHR ROM")?;
+
+        /* Many dumps carry garbage after the ROMs, it is left out */
+        Self::from_roms(info, bytes, prg_rom, chr_rom, trainer, database)
+    }
+
+    /* Takes the next section of the file, failing when it is cut short */
+    fn get_section<'a>(
+        bytes: &'a [u8],
+        offset: &mut usize,
+        size: usize,
+        section: &'static str,
+    ) -> AppResult<&'a [u8]> {
+        let available = bytes.len() - *offset;
+
+        if size > available {
+            return Err(AppError::TruncatedRom {
+                section,
+                expected: size,
+                actual: available,
+            });
+        }
+
+        let data = &bytes[*offset..*offset + size];
+        *offset += size;
+
+        Ok(data)
+    }
+
+    /*
+     * Builds the board described by the ROM info around its ROMs.
+     * NES 2.0 headers are trusted, the others being looked up in
+     * the database.
+     */
+    fn from_roms(
+        mut info: RomInfo,
+        file: &[u8],
+        prg_rom: &[u8],
+        chr_rom: &[u8],
+        trainer: Option<&[u8]>,
+        database: &GameDatabase,
+    ) -> AppResult<Self> {
+        let mut crc = Crc32::new();
+        crc.update(prg_rom);
+        crc.update(chr_rom);
+        info.crc = crc.finish();
+        info.file_hash = RomHash::new(file);
+        info.prg_hash = RomHash::new(prg_rom);
+        info.chr_hash = RomHash::new(chr_rom);
+
+        if info.format != RomFormat::Nes20 {
+            database.correct(info.crc, &mut info);
+        }
+
+        let prg_rom = {
+            let memory = Memory::new(prg_rom.len());
+            memory.write_chunk(0, prg_rom);
+            memory
+        };
+
+        /* Boards without CHR ROM come with CHR RAM instead */
+        let is_chr_ram = chr_rom.is_empty();
+        let chr = if is_chr_ram {
+            Memory::new((info.chr_ram_size + info.chr_nvram_size).max(CHR_BANK_SIZE))
+        } else {
+            let chr = Memory::new(chr_rom.len());
+            chr.write_chunk(0, chr_rom);
+            chr
+        };
+
+        let prg_ram = Memory::new(info.prg_ram_size + info.prg_nvram_size);
+        let trainer_offset = (TRAINER_ADDRESS - PRG_RAM_ADDRESS_LO) as usize;
+
+        /* The trainer is dropped on boards without enough PRG RAM to hold it */
+        if let Some(trainer) = trainer
+            && prg_ram.len() >= trainer_offset + TRAINER_SIZE
+        {
+            prg_ram.write_chunk(trainer_offset, trainer);
+        }
+
+        let memory = CartridgeMemory {
+            prg_rom,
             prg_ram,
-            chr_rom,
+            chr,
+            is_chr_ram,
+            mirroring: info.mirroring,
+            has_bus_conflicts: mapper::has_bus_conflicts(&info),
+        };
+        let mapper = mapper::new_mapper(&info, memory)?;
+
+        Ok(Self {
+            expansion_chips: mapper.expansion_chips(),
             mapper,
+            info,
+            nsf_board: None,
         })
     }
 
-    /* Reads a specific address from PRG RAM  */
+    /*
+     * Initializes a cartridge out of an NSF file, so the music
+     * can be played by the CPU and APU like any game would
+     */
+    pub fn from_nsf(nsf: &Nsf) -> Self {
+        let memory = CartridgeMemory {
+            prg_rom: Memory::new(0),
+            prg_ram: Memory::new(0),
+            chr: Memory::new(CHR_BANK_SIZE),
+            is_chr_ram: true,
+            mirroring: Mirroring::Horizontal,
+            has_bus_conflicts: false,
+        };
+
+        Self {
+            mapper: Box::new(Nrom::new(memory)),
+            info: RomInfo::default(),
+            expansion_chips: nsf.expansion_chips(),
+            nsf_board: Some(NsfBoard::new(nsf)),
+        }
+    }
+
+    /* Initializes a new Cartridge around any mapper, for tests to watch the buses */
+    #[cfg(test)]
+    pub(crate) fn from_mapper(mapper: Box<dyn Mapper>) -> Self {
+        Self {
+            mapper,
+            info: RomInfo::default(),
+            expansion_chips: ExpansionChips::empty(),
+            nsf_board: None,
+        }
+    }
+
+    /* Reads a CPU address between $4020 and $FFFF */
     pub fn prg_read(&self, address: u16) -> u8 {
-        let mapped_address = self.mapper.get_prg_address(address);
+        if let Some(board) = self.nsf_board.as_ref() {
+            return board.read(address);
+        }
 
-        self.prg_ram.read(mapped_address)
+        self.mapper.cpu_read(address)
     }
 
-    /* Writes a specific value to an address from PRG RAM  */
+    /* Writes a CPU address between $4020 and $FFFF */
     pub fn prg_write(&self, address: u16, value: u8) {
-        let mapped_address = self.mapper.get_prg_address(address);
+        if let Some(board) = self.nsf_board.as_ref() {
+            return board.write(address, value);
+        }
 
-        self.prg_ram.write(mapped_address, value);
+        self.mapper.cpu_write(address, value);
     }
 
-    /* Reads a specific address from CHR ROM  */
+    /* Reads a PPU address between $0000 and $1FFF */
     pub fn chr_read(&self, address: u16) -> u8 {
-        let mapped_address = self.mapper.get_chr_address(address);
+        self.mapper.ppu_read(address)
+    }
+
+    /* Writes a PPU address between $0000 and $1FFF */
+    pub fn chr_write(&self, address: u16, value: u8) {
+        self.mapper.ppu_write(address, value);
+    }
+
+    /* Advances the mapper by a single CPU cycle */
+    pub fn clock(&self) {
+        self.mapper.clock();
+    }
+
+    /* Lets the mapper watch an actual PPU read of the given address */
+    pub fn notify_ppu_read(&self, address: u16) {
+        self.mapper.on_ppu_read(address);
+    }
+
+    /* Lets the mapper watch a CPU write to one of the 8 PPU registers */
+    pub fn notify_ppu_register_write(&self, register: u16, value: u8) {
+        self.mapper.on_ppu_register_write(register, value);
+    }
+
+    /* Reads a nametable address the mapper answers itself, instead of the PPU VRAM */
+    pub fn nametable_read(&self, address: u16) -> Option<u8> {
+        self.mapper.read_nametable(address)
+    }
+
+    /* Writes a nametable address, returning whether the mapper took it over */
+    pub fn nametable_write(&self, address: u16, value: u8) -> bool {
+        self.mapper.write_nametable(address, value)
+    }
+
+    /* Specifies whether the mapper pulls the IRQ line of the CPU */
+    pub fn irq_pending(&self) -> bool {
+        self.mapper.irq_pending()
+    }
+
+    /* Specifies whether the mapper only works with the dot renderer of the PPU */
+    pub fn needs_dot_renderer(&self) -> bool {
+        self.mapper.needs_dot_renderer()
+    }
+
+    /* Forwards a filtered rise of the PPU A12 address line to the mapper */
+    pub fn notify_a12_rise(&self) {
+        self.mapper.on_a12_rise();
+    }
+
+    /* Address a CPU write reaches the sound chips of the cartridge at */
+    pub fn audio_address(&self, address: u16) -> u16 {
+        if self.nsf_board.is_some() {
+            return address;
+        }
+
+        self.mapper.audio_address(address)
+    }
+
+    /* Sound chips the cartridge adds to the console */
+    pub fn expansion_chips(&self) -> ExpansionChips {
+        self.expansion_chips
+    }
+
+    /* The board playing an NSF file, if the cartridge was made from one */
+    pub fn nsf_board(&self) -> Option<&NsfBoard> {
+        self.nsf_board.as_ref()
+    }
+
+    /* Specifies whether the PRG RAM is kept by a battery, and worth saving */
+    pub fn is_battery_backed(&self) -> bool {
+        self.info.is_battery_backed
+    }
+
+    /* Copies the content of the PRG RAM */
+    pub fn prg_ram(&self) -> Vec<u8> {
+        self.mapper.memory().prg_ram.to_vec()
+    }
+
+    /* Restores the content of the PRG RAM, extra or missing bytes being ignored */
+    pub fn load_prg_ram(&self, bytes: &[u8]) {
+        let prg_ram = &self.mapper.memory().prg_ram;
+        let length = bytes.len().min(prg_ram.len());
+
+        prg_ram.write_chunk(0, &bytes[..length]);
+    }
+
+    /* Saves the state of the board, its mapper registers and RAM */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.mapper.save_state(state);
+    }
+
+    /* Restores a state produced by save_state, which must come from the same ROM */
+    pub fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.mapper.load_state(state)
+    }
+
+    /* What the header told about the ROM */
+    pub fn info(&self) -> &RomInfo {
+        &self.info
+    }
 
-        self.chr_rom.read(mapped_address)
+    /* Nametable mirroring used by the PPU */
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.mirroring()
     }
 }
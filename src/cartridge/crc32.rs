@@ -0,0 +1,66 @@
+/* Reversed polynomial of the CRC32 used by zip files and ROM databases */
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/* Remainder of every byte value, computed at compile time */
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut value = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            value = if value & 0x01 != 0 {
+                (value >> 1) ^ POLYNOMIAL
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = value;
+        index += 1;
+    }
+
+    table
+};
+
+/* A CRC32 computed over several pieces of data in a row */
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    /* Initializes a new CRC32 over no data */
+    pub fn new() -> Self {
+        Self { value: 0xFFFF_FFFF }
+    }
+
+    /* Adds the given data to the checksum */
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.value = TABLE[((self.value ^ *byte as u32) & 0xFF) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    /* Checksum of all the data added so far */
+    pub fn finish(&self) -> u32 {
+        !self.value
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/* Computes the CRC32 of the given data */
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+
+    crc.finish()
+}
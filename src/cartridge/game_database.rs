@@ -0,0 +1,170 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        rom_info::{HeaderCorrection, RomInfo},
+    },
+    console::region::Region,
+    errors::{AppError, AppResult},
+    input::ports::InputDevice,
+};
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+/* Database shipped with the emulator, see the file for its format */
+const EMBEDDED_DATABASE: &str = include_str!("game_database.txt");
+
+/* Fields of a header a database entry overrides, the others being kept */
+#[derive(Debug, Clone, Default)]
+pub struct GameEntry {
+    pub mapper_id: Option<u16>,
+    pub submapper: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub prg_ram_size: Option<usize>,
+    pub is_battery_backed: Option<bool>,
+    pub region: Option<Region>,
+    pub input_device: Option<InputDevice>,
+}
+
+/*
+ * Known games keyed by the CRC32 of their PRG and CHR ROMs,
+ * correcting the iNES headers many dumps were made with.
+ *
+ * Entries are read from a text file holding one game per
+ * line, the CRC32 in hexadecimal followed by key=value pairs.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct GameDatabase {
+    entries: HashMap<u32, GameEntry>,
+}
+
+impl GameDatabase {
+    /* Parses the content of a database file */
+    pub fn new(text: &str) -> AppResult<Self> {
+        let mut entries = HashMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (crc, entry) =
+                Self::get_entry(line).ok_or(AppError::InvalidGameDatabase(index + 1))?;
+            entries.insert(crc, entry);
+        }
+
+        Ok(Self { entries })
+    }
+
+    /* Loads a database file from disk */
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let text = fs::read_to_string(path)?;
+
+        Self::new(&text)
+    }
+
+    /* The database shipped with the emulator, parsed once */
+    pub fn embedded() -> &'static Self {
+        static DATABASE: OnceLock<GameDatabase> = OnceLock::new();
+
+        DATABASE.get_or_init(|| Self::new(EMBEDDED_DATABASE).unwrap_or_default())
+    }
+
+    /* Entry of the game whose PRG and CHR ROMs have the given CRC32 */
+    pub fn get(&self, crc: u32) -> Option<&GameEntry> {
+        self.entries.get(&crc)
+    }
+
+    /*
+     * Overrides the fields of the ROM info the database knows
+     * better, recording every field that actually changed
+     */
+    pub fn correct(&self, crc: u32, info: &mut RomInfo) {
+        let Some(entry) = self.get(crc) else {
+            return;
+        };
+
+        if let Some(mapper_id) = entry.mapper_id.filter(|id| *id != info.mapper_id) {
+            info.mapper_id = mapper_id;
+            info.corrections.push(HeaderCorrection::Mapper);
+        }
+        if let Some(submapper) = entry.submapper.filter(|id| *id != info.submapper) {
+            info.submapper = submapper;
+            info.corrections.push(HeaderCorrection::Submapper);
+        }
+        if let Some(mirroring) = entry.mirroring.filter(|mode| *mode != info.mirroring) {
+            info.mirroring = mirroring;
+            info.corrections.push(HeaderCorrection::Mirroring);
+        }
+        if let Some(size) = entry.prg_ram_size.filter(|size| *size != info.prg_ram_size) {
+            info.prg_ram_size = size;
+            info.corrections.push(HeaderCorrection::PrgRamSize);
+        }
+        if let Some(battery) = entry
+            .is_battery_backed
+            .filter(|battery| *battery != info.is_battery_backed)
+        {
+            info.is_battery_backed = battery;
+            info.corrections.push(HeaderCorrection::Battery);
+        }
+        if let Some(region) = entry.region.filter(|region| *region != info.region) {
+            info.region = region;
+            info.corrections.push(HeaderCorrection::Region);
+        }
+        if let Some(device) = entry
+            .input_device
+            .filter(|device| *device != info.input_device)
+        {
+            info.input_device = device;
+            info.corrections.push(HeaderCorrection::InputDevice);
+        }
+    }
+
+    /* Parses a line of the database, made of a CRC32 and key=value pairs */
+    fn get_entry(line: &str) -> Option<(u32, GameEntry)> {
+        let mut fields = line.split_whitespace();
+        let crc = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let mut entry = GameEntry::default();
+
+        for field in fields {
+            let (key, value) = field.split_once('=')?;
+
+            match key {
+                "mapper" => entry.mapper_id = Some(value.parse().ok()?),
+                "submapper" => entry.submapper = Some(value.parse().ok()?),
+                "mirroring" => {
+                    entry.mirroring = Some(match value {
+                        "horizontal" => Mirroring::Horizontal,
+                        "vertical" => Mirroring::Vertical,
+                        "four_screen" => Mirroring::FourScreen,
+                        "single_screen_lower" => Mirroring::SingleScreenLower,
+                        "single_screen_upper" => Mirroring::SingleScreenUpper,
+                        _ => return None,
+                    })
+                }
+                "prg_ram" => entry.prg_ram_size = Some(value.parse().ok()?),
+                "battery" => entry.is_battery_backed = Some(value == "1"),
+                "region" => {
+                    entry.region = Some(match value {
+                        "ntsc" => Region::Ntsc,
+                        "pal" => Region::Pal,
+                        "dendy" => Region::Dendy,
+                        _ => return None,
+                    })
+                }
+                "input" => {
+                    entry.input_device = Some(match value {
+                        "controllers" => InputDevice::Controllers,
+                        "four_score" => InputDevice::FourScore,
+                        "zapper" => InputDevice::Zapper,
+                        "family_keyboard" => InputDevice::FamilyKeyboard,
+                        _ => return None,
+                    })
+                }
+                _ => return None,
+            }
+        }
+
+        Some((crc, entry))
+    }
+}
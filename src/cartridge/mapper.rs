@@ -1,28 +1,289 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    cartridge::{
+        cartridge::Mirroring,
+        mappers::{
+            axrom::Axrom,
+            camerica::Camerica,
+            cnrom::Cnrom,
+            color_dreams::ColorDreams,
+            fme7::Fme7,
+            mmc1::Mmc1,
+            mmc2::Mmc2,
+            mmc5::Mmc5,
+            namco108::Namco108,
+            namco163::Namco163,
+            nrom::Nrom,
+            rambo1::Rambo1,
+            uxrom::Uxrom,
+            vrc4::{Vrc4, Vrc4Variant},
+            vrc6::Vrc6,
+            vrc7::Vrc7,
+        },
+        rom_info::RomInfo,
+    },
+    errors::{AppError, AppResult},
+    memory::memory::Memory,
+    state::state::{StateReader, StateWriter},
+};
+
+/* Size of the PRG and CHR banks of the iNES format */
+pub const PRG_BANK_SIZE: usize = 0x4000;
+pub const CHR_BANK_SIZE: usize = 0x2000;
+
+/* Size of the work RAM mapped at $6000-$7FFF when the header does not tell */
+pub const PRG_RAM_SIZE: usize = 0x2000;
+pub const PRG_RAM_ADDRESS_LO: u16 = 0x6000;
+pub const PRG_RAM_ADDRESS_HI: u16 = 0x7FFF;
+
+/*
+ * Memory chips found on a cartridge board, handed to its
+ * mapper which decides how they are reached by the CPU and
+ * the PPU.
+ */
+pub struct CartridgeMemory {
+    pub prg_rom: Memory,
+    pub prg_ram: Memory,
+    pub chr: Memory,
+    /* Whether the CHR memory is RAM the PPU can write to */
+    pub is_chr_ram: bool,
+    /* Nametable mirroring hard-wired on the board */
+    pub mirroring: Mirroring,
+    /* Whether the PRG ROM keeps driving the data bus during register writes */
+    pub has_bus_conflicts: bool,
+}
+
+impl CartridgeMemory {
+    /* Reads from a PRG ROM bank of the given size */
+    pub fn read_prg(&self, bank_size: usize, bank: usize, address: u16) -> u8 {
+        read_bank(&self.prg_rom, bank_size, bank, address)
+    }
+
+    /* Number of the last PRG ROM bank of the given size */
+    pub fn last_prg_bank(&self, bank_size: usize) -> usize {
+        (self.prg_rom.len() / bank_size).saturating_sub(1)
+    }
+
+    /* Reads from a CHR bank of the given size */
+    pub fn read_chr(&self, bank_size: usize, bank: usize, address: u16) -> u8 {
+        read_bank(&self.chr, bank_size, bank, address)
+    }
+
+    /* Writes to a CHR bank of the given size, unless the board has CHR ROM */
+    pub fn write_chr(&self, bank_size: usize, bank: usize, address: u16, value: u8) {
+        if self.is_chr_ram && !self.chr.is_empty() {
+            let address = get_bank_address(&self.chr, bank_size, bank, address);
+            self.chr.write(address, value);
+        }
+    }
+
+    /*
+     * Value a register write ends up with. On boards with bus
+     * conflicts the ROM outputs its byte at the written address
+     * while the CPU drives the value, the low bits winning.
+     */
+    pub fn get_bus_value(&self, rom_value: u8, value: u8) -> u8 {
+        if self.has_bus_conflicts {
+            rom_value & value
+        } else {
+            value
+        }
+    }
+
+    /* Reads from the work RAM, given a CPU address between $6000 and $7FFF */
+    pub fn read_prg_ram(&self, address: u16) -> u8 {
+        if self.prg_ram.is_empty() {
+            return 0;
+        }
+
+        self.prg_ram
+            .read((address - PRG_RAM_ADDRESS_LO) as usize % self.prg_ram.len())
+    }
+
+    /* Writes to the work RAM, given a CPU address between $6000 and $7FFF */
+    pub fn write_prg_ram(&self, address: u16, value: u8) {
+        if !self.prg_ram.is_empty() {
+            self.prg_ram.write(
+                (address - PRG_RAM_ADDRESS_LO) as usize % self.prg_ram.len(),
+                value,
+            );
+        }
+    }
+
+    /* Saves the RAM of the board, the ROMs never changing */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.prg_ram.save_state(state);
+
+        if self.is_chr_ram {
+            self.chr.save_state(state);
+        }
+    }
+
+    /* Restores the RAM saved by save_state */
+    pub fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.prg_ram.load_state(state)?;
+
+        if self.is_chr_ram {
+            self.chr.load_state(state)?;
+        }
+
+        Ok(())
+    }
+}
+
+/* Reads inside a memory made of banks of the given size */
+fn read_bank(memory: &Memory, bank_size: usize, bank: usize, address: u16) -> u8 {
+    if memory.is_empty() {
+        return 0;
+    }
+
+    memory.read(get_bank_address(memory, bank_size, bank, address))
+}
+
+/*
+ * Address inside a memory made of banks of the given size, the
+ * bank number wrapping around the available banks
+ */
+fn get_bank_address(memory: &Memory, bank_size: usize, bank: usize, address: u16) -> usize {
+    let bank_count = (memory.len() / bank_size).max(1);
+    let address = (bank % bank_count) * bank_size + (address as usize & (bank_size - 1));
+
+    address % memory.len()
+}
+
 /*
  * A separate physical device for mapping memory locations
  * inside the cartridge. This enables games to support
  * additional memory for both PRG and CHR data.
  *
- * This is Mapper 000 implementation
+ * Mappers are shared between the CPU and the PPU buses, so
+ * their registers live in cells.
  */
-pub struct Mapper {
-    prg_banks: u8,
-    chr_banks: u8,
-}
+pub trait Mapper {
+    /* Reads a CPU address between $4020 and $FFFF */
+    fn cpu_read(&self, address: u16) -> u8;
 
-impl Mapper {
-    pub fn new(prg_banks: u8, chr_banks: u8) -> Self {
-        Self {
-            prg_banks,
-            chr_banks,
-        }
+    /* Writes a CPU address between $4020 and $FFFF */
+    fn cpu_write(&self, address: u16, value: u8);
+
+    /* Reads a PPU address between $0000 and $1FFF */
+    fn ppu_read(&self, address: u16) -> u8;
+
+    /* Writes a PPU address between $0000 and $1FFF */
+    fn ppu_write(&self, address: u16, value: u8);
+
+    /* Memory chips of the board the mapper is wired to */
+    fn memory(&self) -> &CartridgeMemory;
+
+    /* Nametable mirroring currently selected */
+    fn mirroring(&self) -> Mirroring;
+
+    /*
+     * Saves the bank registers, counters and latches of the
+     * mapper, along with the RAM of the board
+     */
+    fn save_state(&self, state: &mut StateWriter);
+
+    /* Restores a state produced by save_state */
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()>;
+
+    /* Advances the mapper by a single CPU cycle */
+    fn clock(&self) {}
+
+    /* Specifies whether the mapper pulls the IRQ line of the CPU */
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    /*
+     * Called on every filtered rise of the PPU A12 address line,
+     * which clocks the scanline counter of some mappers
+     */
+    fn on_a12_rise(&self) {}
+
+    /*
+     * Called before every actual PPU read of $0000-$3FFF, for
+     * mappers following the fetches of the PPU
+     */
+    fn on_ppu_read(&self, _address: u16) {}
+
+    /*
+     * Specifies whether the mapper relies on the PPU fetches
+     * happening on their exact dots, which only the dot renderer
+     * reproduces
+     */
+    fn needs_dot_renderer(&self) -> bool {
+        false
+    }
+
+    /* Called on CPU writes to the PPU registers, given relative to $2000 */
+    fn on_ppu_register_write(&self, _register: u16, _value: u8) {}
+
+    /*
+     * Reads a nametable address between $2000 and $2FFF, for
+     * mappers replacing the PPU VRAM. None lets the VRAM answer.
+     */
+    fn read_nametable(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    /* Writes a nametable address, returning whether the mapper took it over */
+    fn write_nametable(&self, _address: u16, _value: u8) -> bool {
+        false
     }
 
-    pub fn get_prg_address(&self, address: u16) -> u16 {
-        address & if self.prg_banks > 1 { 0x7FFF } else { 0x3FFF }
+    /* Sound chips found on the board */
+    fn expansion_chips(&self) -> ExpansionChips {
+        ExpansionChips::empty()
     }
 
-    pub fn get_chr_address(&self, address: u16) -> u16 {
+    /*
+     * Address a CPU write reaches the sound chips at, for boards
+     * wiring them differently than the NSF format expects
+     */
+    fn audio_address(&self, address: u16) -> u16 {
         address
     }
 }
+
+/*
+ * Whether the board of a ROM has bus conflicts. NES 2.0 tells
+ * it with the submapper of the discrete boards coming in both
+ * kinds, otherwise only the ones always wired with conflicts
+ * are assumed to have them.
+ */
+pub fn has_bus_conflicts(info: &RomInfo) -> bool {
+    match (info.mapper_id, info.submapper) {
+        (2 | 3 | 7, 1) => false,
+        (2 | 3 | 7, 2) => true,
+        (id, _) => matches!(id, 11 | 185),
+    }
+}
+
+/* Builds the mapper of a ROM around the memory of its board */
+pub fn new_mapper(info: &RomInfo, memory: CartridgeMemory) -> AppResult<Box<dyn Mapper>> {
+    match info.mapper_id {
+        0 => Ok(Box::new(Nrom::new(memory))),
+        1 => Ok(Box::new(Mmc1::new(memory))),
+        2 => Ok(Box::new(Uxrom::new(memory))),
+        3 => Ok(Box::new(Cnrom::new(memory, false, info.submapper))),
+        5 => Ok(Box::new(Mmc5::new(memory))),
+        7 => Ok(Box::new(Axrom::new(memory))),
+        9 => Ok(Box::new(Mmc2::new(memory))),
+        11 => Ok(Box::new(ColorDreams::new(memory))),
+        19 => Ok(Box::new(Namco163::new(memory))),
+        21 => Ok(Box::new(Vrc4::new(memory, Vrc4Variant::Vrc4ac))),
+        22 => Ok(Box::new(Vrc4::new(memory, Vrc4Variant::Vrc2a))),
+        23 => Ok(Box::new(Vrc4::new(memory, Vrc4Variant::Vrc2bVrc4e))),
+        24 => Ok(Box::new(Vrc6::new(memory, false))),
+        25 => Ok(Box::new(Vrc4::new(memory, Vrc4Variant::Vrc4bd))),
+        26 => Ok(Box::new(Vrc6::new(memory, true))),
+        64 => Ok(Box::new(Rambo1::new(memory))),
+        69 => Ok(Box::new(Fme7::new(memory))),
+        71 => Ok(Box::new(Camerica::new(memory))),
+        85 => Ok(Box::new(Vrc7::new(memory))),
+        185 => Ok(Box::new(Cnrom::new(memory, true, info.submapper))),
+        206 => Ok(Box::new(Namco108::new(memory))),
+        id => Err(AppError::InvalidCartridgeMapper(id)),
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, CartridgeMemory, Mapper},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the PRG banks AxROM switches */
+pub const PRG_BANK_SIZE: usize = 0x8000;
+
+/*
+ * Mapper 007, the AxROM boards. Writes to $8000-$FFFF select
+ * the 32KB PRG bank and which 1KB of VRAM all nametables
+ * show. Graphics come from 8KB of CHR RAM.
+ */
+pub struct Axrom {
+    memory: CartridgeMemory,
+    /* Last value written to $8000-$FFFF */
+    latch: Cell<u8>,
+}
+
+impl Axrom {
+    /* Initializes a new AxROM board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            latch: Cell::new(0),
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, (self.latch.get() & 0x07) as usize, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = self.memory.get_bus_value(self.cpu_read(address), value);
+            self.latch.set(value);
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE, 0, address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE, 0, address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.latch.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.latch.set(state.read_u8()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.latch.get() & 0x10 == 0 {
+            Mirroring::SingleScreenLower
+        } else {
+            Mirroring::SingleScreenUpper
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, CartridgeMemory, Mapper, PRG_BANK_SIZE},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/*
+ * Mapper 071, Camerica's BF9093 and BF9097 boards used by
+ * Codemasters. Like UxROM, writes to $C000-$FFFF select the
+ * 16KB PRG bank mapped at $8000, the last bank being fixed at
+ * $C000, and graphics come from 8KB of CHR RAM.
+ *
+ * The BF9097 of Fire Hawk also selects a single screen with
+ * bit 4 of writes to $9000-$9FFF. The header mirroring is
+ * kept until such a write happens.
+ */
+pub struct Camerica {
+    memory: CartridgeMemory,
+    prg_bank: Cell<u8>,
+    /* Single screen selected by the BF9097, if any */
+    single_screen: Cell<Option<Mirroring>>,
+}
+
+impl Camerica {
+    /* Initializes a new Camerica board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            prg_bank: Cell::new(0),
+            single_screen: Cell::new(None),
+        }
+    }
+}
+
+impl Mapper for Camerica {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xBFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.prg_bank.get() as usize, address)
+            }
+            0xC000..=0xFFFF => {
+                let last_bank = self.memory.last_prg_bank(PRG_BANK_SIZE);
+                self.memory.read_prg(PRG_BANK_SIZE, last_bank, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        match address {
+            0x9000..=0x9FFF => self.single_screen.set(Some(if value & 0x10 == 0 {
+                Mirroring::SingleScreenLower
+            } else {
+                Mirroring::SingleScreenUpper
+            })),
+            0xC000..=0xFFFF => self.prg_bank.set(value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE, 0, address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE, 0, address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.prg_bank.get());
+        state.write_bool(self.single_screen.get().is_some());
+        self.single_screen
+            .get()
+            .unwrap_or_default()
+            .save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_bank.set(state.read_u8()?);
+        let is_single_screen = state.read_bool()?;
+        let single_screen = Mirroring::load_state(state)?;
+        self.single_screen
+            .set(is_single_screen.then_some(single_screen));
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.single_screen.get().unwrap_or(self.memory.mirroring)
+    }
+}
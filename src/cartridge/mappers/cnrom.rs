@@ -0,0 +1,122 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, CartridgeMemory, Mapper, PRG_BANK_SIZE},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Value read from the CHR ROM while it is disabled, its data lines floating high */
+const DISABLED_CHR_VALUE: u8 = 0xFF;
+
+/*
+ * Mapper 003, the CNROM boards. PRG ROM is mapped like on
+ * NROM, while writes to $8000-$FFFF select the 8KB CHR bank.
+ *
+ * Mapper 185 boards use the same latch as a copy protection
+ * instead, the CHR ROM only being enabled by the values the
+ * game expects and reading garbage otherwise. Games check
+ * this at boot and refuse to run on a plain CNROM. NES 2.0
+ * submappers 4 to 7 tell the exact value enabling it.
+ */
+pub struct Cnrom {
+    memory: CartridgeMemory,
+    /* Last value written to $8000-$FFFF */
+    latch: Cell<u8>,
+    is_copy_protected: bool,
+    submapper: u8,
+}
+
+impl Cnrom {
+    /* Initializes a new CNROM board, with the mapper 185 protection if asked */
+    pub fn new(memory: CartridgeMemory, is_copy_protected: bool, submapper: u8) -> Self {
+        Self {
+            memory,
+            latch: Cell::new(0),
+            is_copy_protected,
+            submapper,
+        }
+    }
+
+    /*
+     * Without a submapper telling which diodes a protected board
+     * has, the CHR ROM is considered enabled by any value with one
+     * of its lower bits set, except $13 which Seicross uses to
+     * disable it
+     */
+    fn is_chr_enabled(&self) -> bool {
+        let latch = self.latch.get();
+
+        if !self.is_copy_protected {
+            true
+        } else if self.submapper & 0x04 != 0 {
+            latch & 0x03 == self.submapper & 0x03
+        } else {
+            latch & 0x0F != 0 && latch != 0x13
+        }
+    }
+
+    /* 8KB CHR bank mapped at $0000-$1FFF */
+    fn get_chr_bank(&self) -> usize {
+        if self.is_copy_protected {
+            0
+        } else {
+            self.latch.get() as usize
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&self, address: u16) -> u8 {
+        /* Nothing is mapped below $8000 */
+        if address < 0x8000 {
+            return 0;
+        }
+
+        let bank = (address as usize - 0x8000) / PRG_BANK_SIZE;
+        self.memory.read_prg(PRG_BANK_SIZE, bank, address)
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = self.memory.get_bus_value(self.cpu_read(address), value);
+            self.latch.set(value);
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        if !self.is_chr_enabled() {
+            return DISABLED_CHR_VALUE;
+        }
+
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.latch.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.latch.set(state.read_u8()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.memory.mirroring
+    }
+}
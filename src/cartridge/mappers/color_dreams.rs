@@ -0,0 +1,87 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, CartridgeMemory, Mapper},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the PRG banks Color Dreams switches */
+pub const PRG_BANK_SIZE: usize = 0x8000;
+
+/*
+ * Mapper 011, the unlicensed Color Dreams boards. Writes to
+ * $8000-$FFFF select the 32KB PRG bank with their low bits
+ * and the 8KB CHR bank with their high ones.
+ */
+pub struct ColorDreams {
+    memory: CartridgeMemory,
+    /* Last value written to $8000-$FFFF */
+    latch: Cell<u8>,
+}
+
+impl ColorDreams {
+    /* Initializes a new Color Dreams board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            latch: Cell::new(0),
+        }
+    }
+
+    /* 8KB CHR bank mapped at $0000-$1FFF */
+    fn get_chr_bank(&self) -> usize {
+        (self.latch.get() >> 4) as usize
+    }
+}
+
+impl Mapper for ColorDreams {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, (self.latch.get() & 0x03) as usize, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = self.memory.get_bus_value(self.cpu_read(address), value);
+            self.latch.set(value);
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.latch.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.latch.set(state.read_u8()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.memory.mirroring
+    }
+}
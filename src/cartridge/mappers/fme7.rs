@@ -0,0 +1,225 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks FME-7 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+pub const COMMAND_ADDRESS_LO: u16 = 0x8000;
+pub const COMMAND_ADDRESS_HI: u16 = 0x9FFF;
+pub const PARAMETER_ADDRESS_LO: u16 = 0xA000;
+pub const PARAMETER_ADDRESS_HI: u16 = 0xBFFF;
+
+/*
+ * Mapper 069, Sunsoft's FME-7 and its 5A and 5B variants.
+ * Registers are written by selecting one of 16 commands at
+ * $8000, then sending its parameter at $A000. Eight 1KB CHR
+ * banks and three 8KB PRG banks are switchable, the last one
+ * being fixed, and $6000 maps either PRG ROM or RAM.
+ *
+ * A 16 bit counter is decremented on every CPU cycle, raising
+ * an interrupt when it wraps. The 5B sound chip registers sit
+ * at $C000 and $E000, above the mapper ones.
+ */
+pub struct Fme7 {
+    memory: CartridgeMemory,
+
+    command: Cell<u8>,
+    chr_banks: Cell<[u8; 8]>,
+    /* Bank mapped at $6000, bit 6 selecting RAM and bit 7 enabling it */
+    prg_ram_bank: Cell<u8>,
+    prg_banks: Cell<[u8; 3]>,
+    mirroring: Cell<u8>,
+
+    irq_counter: Cell<u16>,
+    is_irq_enabled: Cell<bool>,
+    is_irq_counter_enabled: Cell<bool>,
+    is_irq_pending: Cell<bool>,
+}
+
+impl Fme7 {
+    /* Initializes a new FME-7 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            command: Cell::new(0),
+            chr_banks: Cell::new([0; 8]),
+            prg_ram_bank: Cell::new(0),
+            prg_banks: Cell::new([0; 3]),
+            mirroring: Cell::new(0),
+            irq_counter: Cell::new(0),
+            is_irq_enabled: Cell::new(false),
+            is_irq_counter_enabled: Cell::new(false),
+            is_irq_pending: Cell::new(false),
+        }
+    }
+
+    /* Whether $6000 maps RAM rather than PRG ROM */
+    fn is_prg_ram_selected(&self) -> bool {
+        self.prg_ram_bank.get() & 0x40 != 0
+    }
+
+    /* Whether the RAM mapped at $6000 can be accessed */
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_ram_bank.get() & 0x80 != 0
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        match address {
+            0x8000..=0xDFFF => {
+                self.prg_banks.get()[(address as usize - 0x8000) / PRG_BANK_SIZE] as usize
+            }
+            _ => self.memory.last_prg_bank(PRG_BANK_SIZE),
+        }
+    }
+
+    /* 1KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        self.chr_banks.get()[address as usize / CHR_BANK_SIZE] as usize
+    }
+
+    /* Runs the selected command with its parameter */
+    fn write_parameter(&self, value: u8) {
+        match self.command.get() {
+            command @ 0x00..=0x07 => {
+                let mut chr_banks = self.chr_banks.get();
+                chr_banks[command as usize] = value;
+                self.chr_banks.set(chr_banks);
+            }
+            0x08 => self.prg_ram_bank.set(value),
+            command @ 0x09..=0x0B => {
+                let mut prg_banks = self.prg_banks.get();
+                prg_banks[command as usize - 0x09] = value & 0x3F;
+                self.prg_banks.set(prg_banks);
+            }
+            0x0C => self.mirroring.set(value & 0x03),
+            0x0D => {
+                self.is_irq_enabled.set(value & 0x01 != 0);
+                self.is_irq_counter_enabled.set(value & 0x80 != 0);
+                self.is_irq_pending.set(false);
+            }
+            0x0E => self
+                .irq_counter
+                .set((self.irq_counter.get() & 0xFF00) | value as u16),
+            _ => self
+                .irq_counter
+                .set((self.irq_counter.get() & 0x00FF) | ((value as u16) << 8)),
+        }
+    }
+}
+
+impl Mapper for Fme7 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if !self.is_prg_ram_selected() => {
+                self.memory.read_prg(
+                    PRG_BANK_SIZE,
+                    (self.prg_ram_bank.get() & 0x3F) as usize,
+                    address,
+                )
+            }
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if self.is_prg_ram_enabled() => {
+                self.memory.read_prg_ram(address)
+            }
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI
+                if self.is_prg_ram_selected() && self.is_prg_ram_enabled() =>
+            {
+                self.memory.write_prg_ram(address, value)
+            }
+            COMMAND_ADDRESS_LO..=COMMAND_ADDRESS_HI => self.command.set(value & 0x0F),
+            PARAMETER_ADDRESS_LO..=PARAMETER_ADDRESS_HI => self.write_parameter(value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.command.get());
+        state.write_bytes(&self.chr_banks.get());
+        state.write_u8(self.prg_ram_bank.get());
+        state.write_bytes(&self.prg_banks.get());
+        state.write_u8(self.mirroring.get());
+        state.write_u16(self.irq_counter.get());
+        state.write_bool(self.is_irq_enabled.get());
+        state.write_bool(self.is_irq_counter_enabled.get());
+        state.write_bool(self.is_irq_pending.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.command.set(state.read_u8()?);
+        self.chr_banks.set(state.read_array()?);
+        self.prg_ram_bank.set(state.read_u8()?);
+        self.prg_banks.set(state.read_array()?);
+        self.mirroring.set(state.read_u8()?);
+        self.irq_counter.set(state.read_u16()?);
+        self.is_irq_enabled.set(state.read_bool()?);
+        self.is_irq_counter_enabled.set(state.read_bool()?);
+        self.is_irq_pending.set(state.read_bool()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring.get() {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn clock(&self) {
+        if !self.is_irq_counter_enabled.get() {
+            return;
+        }
+
+        let counter = self.irq_counter.get();
+        self.irq_counter.set(counter.wrapping_sub(1));
+
+        if counter == 0 && self.is_irq_enabled.get() {
+            self.is_irq_pending.set(true);
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.is_irq_pending.get()
+    }
+
+    fn expansion_chips(&self) -> ExpansionChips {
+        ExpansionChips::SUNSOFT
+    }
+}
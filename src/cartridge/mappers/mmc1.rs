@@ -0,0 +1,246 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks MMC1 switches */
+pub const PRG_BANK_SIZE: usize = 0x4000;
+pub const CHR_BANK_SIZE: usize = 0x1000;
+
+/* Value of the control register at power on, fixing the last PRG bank */
+const CONTROL_INITIAL: u8 = 0x0C;
+
+/* Boards with more PRG ROM than this select the upper half through the CHR registers */
+const PRG_OUTER_BANK_SIZE: usize = 0x40000;
+
+/*
+ * Mapper 001, Nintendo's MMC1. Its registers are loaded one
+ * bit at a time through a shift register, the fifth write to
+ * $8000-$FFFF moving the collected value to the register
+ * picked by the address of that write:
+ *
+ * $8000-$9FFF: control (mirroring, PRG and CHR modes)
+ * $A000-$BFFF: CHR bank for $0000
+ * $C000-$DFFF: CHR bank for $1000
+ * $E000-$FFFF: PRG bank and work RAM disable
+ */
+pub struct Mmc1 {
+    memory: CartridgeMemory,
+
+    shift_register: Cell<u8>,
+    shift_count: Cell<u8>,
+
+    control: Cell<u8>,
+    chr_bank_0: Cell<u8>,
+    chr_bank_1: Cell<u8>,
+    prg_bank: Cell<u8>,
+
+    /* CPU cycles elapsed, and the one of the last register write */
+    cycles: Cell<u64>,
+    last_write_cycle: Cell<Option<u64>>,
+}
+
+impl Mmc1 {
+    /* Initializes a new MMC1 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            shift_register: Cell::new(0),
+            shift_count: Cell::new(0),
+            control: Cell::new(CONTROL_INITIAL),
+            chr_bank_0: Cell::new(0),
+            chr_bank_1: Cell::new(0),
+            prg_bank: Cell::new(0),
+            cycles: Cell::new(0),
+            last_write_cycle: Cell::new(None),
+        }
+    }
+
+    /*
+     * Feeds a write to $8000-$FFFF to the shift register. Writes
+     * on consecutive cycles, like the two writes of a
+     * read-modify-write instruction, only count once.
+     */
+    fn write_shift_register(&self, address: u16, value: u8) {
+        let cycles = self.cycles.get();
+        let last_write_cycle = self.last_write_cycle.replace(Some(cycles));
+
+        if last_write_cycle.is_some_and(|last| cycles - last <= 1) {
+            return;
+        }
+
+        /* Setting bit 7 clears the shift register and fixes the last PRG bank */
+        if value & 0x80 != 0 {
+            self.shift_register.set(0);
+            self.shift_count.set(0);
+            self.control.set(self.control.get() | CONTROL_INITIAL);
+            return;
+        }
+
+        let shift_register = (self.shift_register.get() >> 1) | ((value & 0x01) << 4);
+        let shift_count = self.shift_count.get() + 1;
+
+        if shift_count < 5 {
+            self.shift_register.set(shift_register);
+            self.shift_count.set(shift_count);
+            return;
+        }
+
+        match address {
+            0x8000..=0x9FFF => self.control.set(shift_register),
+            0xA000..=0xBFFF => self.chr_bank_0.set(shift_register),
+            0xC000..=0xDFFF => self.chr_bank_1.set(shift_register),
+            _ => self.prg_bank.set(shift_register),
+        }
+
+        self.shift_register.set(0);
+        self.shift_count.set(0);
+    }
+
+    /* Work RAM is enabled unless bit 4 of the PRG bank register is set */
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_bank.get() & 0x10 == 0
+    }
+
+    /* PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        let bank = (self.prg_bank.get() & 0x0F) as usize;
+        let is_upper_half = address >= 0xC000;
+
+        /* The last bank is the last one of the selected 256KB half */
+        let last_bank = PRG_OUTER_BANK_SIZE / PRG_BANK_SIZE - 1;
+
+        let bank = match (self.control.get() >> 2) & 0x03 {
+            /* 32KB mode, ignoring the lowest bit of the bank */
+            0 | 1 => (bank & !0x01) | is_upper_half as usize,
+            /* First bank fixed at $8000 */
+            2 => {
+                if is_upper_half {
+                    bank
+                } else {
+                    0
+                }
+            }
+            /* Last bank fixed at $C000 */
+            _ => {
+                if is_upper_half {
+                    last_bank
+                } else {
+                    bank
+                }
+            }
+        };
+
+        self.get_prg_outer_bank() + (bank & last_bank)
+    }
+
+    /* 512KB boards pick their 256KB half with bit 4 of the first CHR register */
+    fn get_prg_outer_bank(&self) -> usize {
+        if self.memory.prg_rom.len() > PRG_OUTER_BANK_SIZE && self.chr_bank_0.get() & 0x10 != 0 {
+            PRG_OUTER_BANK_SIZE / PRG_BANK_SIZE
+        } else {
+            0
+        }
+    }
+
+    /* 4KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let is_upper_half = address >= 0x1000;
+
+        if self.control.get() & 0x10 == 0 {
+            /* 8KB mode, ignoring the lowest bit of the bank */
+            (self.chr_bank_0.get() & !0x01) as usize | is_upper_half as usize
+        } else if is_upper_half {
+            self.chr_bank_1.get() as usize
+        } else {
+            self.chr_bank_0.get() as usize
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if self.is_prg_ram_enabled() => {
+                self.memory.read_prg_ram(address)
+            }
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if self.is_prg_ram_enabled() => {
+                self.memory.write_prg_ram(address, value)
+            }
+            0x8000..=0xFFFF => self.write_shift_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.shift_register.get());
+        state.write_u8(self.shift_count.get());
+        state.write_u8(self.control.get());
+        state.write_u8(self.chr_bank_0.get());
+        state.write_u8(self.chr_bank_1.get());
+        state.write_u8(self.prg_bank.get());
+        state.write_u64(self.cycles.get());
+        state.write_bool(self.last_write_cycle.get().is_some());
+        state.write_u64(self.last_write_cycle.get().unwrap_or(0));
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.shift_register.set(state.read_u8()?);
+        self.shift_count.set(state.read_u8()?);
+        self.control.set(state.read_u8()?);
+        self.chr_bank_0.set(state.read_u8()?);
+        self.chr_bank_1.set(state.read_u8()?);
+        self.prg_bank.set(state.read_u8()?);
+        self.cycles.set(state.read_u64()?);
+        let has_written = state.read_bool()?;
+        let last_write_cycle = state.read_u64()?;
+        self.last_write_cycle
+            .set(has_written.then_some(last_write_cycle));
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control.get() & 0x03 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn clock(&self) {
+        self.cycles.set(self.cycles.get() + 1);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks MMC2 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x1000;
+
+/* Values of a latch, named after the tiles setting them */
+const LATCH_FD: usize = 0;
+const LATCH_FE: usize = 1;
+
+/*
+ * Mapper 009, Nintendo's MMC2 used by Punch-Out!!. An 8KB
+ * PRG bank is switchable at $8000, the rest being fixed to
+ * the last three banks.
+ *
+ * Each 4KB pattern table has two CHR banks, the one in use
+ * being picked by a latch. The PPU flips the latch itself by
+ * fetching tile $FD or $FE of that table, which lets games
+ * switch graphics mid-screen without any CPU timing.
+ */
+pub struct Mmc2 {
+    memory: CartridgeMemory,
+
+    prg_bank: Cell<u8>,
+    /* The FD and FE banks of each pattern table */
+    chr_banks: Cell<[[u8; 2]; 2]>,
+    latches: Cell<[usize; 2]>,
+    /* Latch change of the last fetch, applied once the fetch is over */
+    pending_latch: Cell<Option<(usize, usize)>>,
+    mirroring: Cell<Mirroring>,
+}
+
+impl Mmc2 {
+    /* Initializes a new MMC2 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            mirroring: Cell::new(memory.mirroring),
+            memory,
+            prg_bank: Cell::new(0),
+            chr_banks: Cell::new([[0; 2]; 2]),
+            latches: Cell::new([LATCH_FD, LATCH_FD]),
+            pending_latch: Cell::new(None),
+        }
+    }
+
+    /* Latch flipped by a fetch of the given address, if any */
+    fn get_latch_change(&self, address: u16) -> Option<(usize, usize)> {
+        match address {
+            0x0FD8 => Some((0, LATCH_FD)),
+            0x0FE8 => Some((0, LATCH_FE)),
+            0x1FD8..=0x1FDF => Some((1, LATCH_FD)),
+            0x1FE8..=0x1FEF => Some((1, LATCH_FE)),
+            _ => None,
+        }
+    }
+
+    /* CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let table = (address as usize >> 12) & 0x01;
+
+        self.chr_banks.get()[table][self.latches.get()[table]] as usize
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9FFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.prg_bank.get() as usize, address)
+            }
+            0xA000..=0xFFFF => {
+                let slot = (address as usize - 0xA000) / PRG_BANK_SIZE;
+                let bank = (self.memory.last_prg_bank(PRG_BANK_SIZE) + slot).saturating_sub(2);
+
+                self.memory.read_prg(PRG_BANK_SIZE, bank, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        let mut chr_banks = self.chr_banks.get();
+
+        match address {
+            0xA000..=0xAFFF => self.prg_bank.set(value & 0x0F),
+            0xB000..=0xBFFF => chr_banks[0][LATCH_FD] = value & 0x1F,
+            0xC000..=0xCFFF => chr_banks[0][LATCH_FE] = value & 0x1F,
+            0xD000..=0xDFFF => chr_banks[1][LATCH_FD] = value & 0x1F,
+            0xE000..=0xEFFF => chr_banks[1][LATCH_FE] = value & 0x1F,
+            0xF000..=0xFFFF => self.mirroring.set(if value & 0x01 == 0 {
+                Mirroring::Vertical
+            } else {
+                Mirroring::Horizontal
+            }),
+            _ => {}
+        }
+
+        self.chr_banks.set(chr_banks);
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.prg_bank.get());
+        let chr_banks = self.chr_banks.get();
+        state.write_bytes(&chr_banks[0]);
+        state.write_bytes(&chr_banks[1]);
+        for latch in self.latches.get() {
+            state.write_u8(latch as u8);
+        }
+        let pending_latch = self.pending_latch.get();
+        state.write_bool(pending_latch.is_some());
+        let (table, latch) = pending_latch.unwrap_or_default();
+        state.write_u8(table as u8);
+        state.write_u8(latch as u8);
+        self.mirroring.get().save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_bank.set(state.read_u8()?);
+        self.chr_banks
+            .set([state.read_array()?, state.read_array()?]);
+        self.latches.set([
+            state.read_u8()? as usize & 0x01,
+            state.read_u8()? as usize & 0x01,
+        ]);
+        let has_pending_latch = state.read_bool()?;
+        let table = state.read_u8()? as usize & 0x01;
+        let latch = state.read_u8()? as usize & 0x01;
+        self.pending_latch
+            .set(has_pending_latch.then_some((table, latch)));
+        self.mirroring.set(Mirroring::load_state(state)?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring.get()
+    }
+
+    fn on_ppu_read(&self, address: u16) {
+        if let Some((table, latch)) = self.pending_latch.take() {
+            let mut latches = self.latches.get();
+            latches[table] = latch;
+            self.latches.set(latches);
+        }
+
+        self.pending_latch.set(self.get_latch_change(address));
+    }
+}
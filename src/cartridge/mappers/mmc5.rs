@@ -0,0 +1,702 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+    },
+    errors::AppResult,
+    memory::memory::Memory,
+    ppu::ppu::{PPUCTRL, PPUMASK},
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::RefCell;
+
+/* Size of the PRG banks, every mode being expressed in 8KB units */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+
+/* MMC5 boards carry up to 64KB of work RAM, all of it is provided */
+pub const PRG_RAM_MAX_SIZE: usize = 0x10000;
+
+/* Internal RAM of the chip, usable as a nametable or for extended attributes */
+pub const EXRAM_SIZE: usize = 0x0400;
+pub const EXRAM_ADDRESS_LO: u16 = 0x5C00;
+pub const EXRAM_ADDRESS_HI: u16 = 0x5FFF;
+
+/* Registers of the chip */
+pub const PRG_MODE_ADDRESS: u16 = 0x5100;
+pub const CHR_MODE_ADDRESS: u16 = 0x5101;
+pub const PRG_RAM_PROTECT_1_ADDRESS: u16 = 0x5102;
+pub const PRG_RAM_PROTECT_2_ADDRESS: u16 = 0x5103;
+pub const EXRAM_MODE_ADDRESS: u16 = 0x5104;
+pub const NAMETABLE_MAPPING_ADDRESS: u16 = 0x5105;
+pub const FILL_TILE_ADDRESS: u16 = 0x5106;
+pub const FILL_ATTRIBUTE_ADDRESS: u16 = 0x5107;
+pub const PRG_BANKS_ADDRESS_LO: u16 = 0x5113;
+pub const PRG_BANKS_ADDRESS_HI: u16 = 0x5117;
+pub const CHR_BANKS_ADDRESS_LO: u16 = 0x5120;
+pub const CHR_BANKS_ADDRESS_HI: u16 = 0x512B;
+pub const CHR_UPPER_BITS_ADDRESS: u16 = 0x5130;
+pub const SPLIT_CONTROL_ADDRESS: u16 = 0x5200;
+pub const SPLIT_SCROLL_ADDRESS: u16 = 0x5201;
+pub const SPLIT_BANK_ADDRESS: u16 = 0x5202;
+pub const IRQ_COMPARE_ADDRESS: u16 = 0x5203;
+pub const IRQ_STATUS_ADDRESS: u16 = 0x5204;
+pub const MULTIPLIER_LO_ADDRESS: u16 = 0x5205;
+pub const MULTIPLIER_HI_ADDRESS: u16 = 0x5206;
+
+/* Index of the first CHR register of the background set, $5128 */
+const BACKGROUND_CHR_BANKS: usize = 8;
+
+/* CPU cycles without PPU reads after which rendering is considered over */
+const IDLE_CYCLES: u8 = 3;
+
+/* Number of visible scanlines, which the split scroll wraps around */
+const SPLIT_HEIGHT: u16 = 240;
+
+/*
+ * Where the four nametables are taken from, two bits each
+ * in $5105
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NametableSource {
+    Vram(u8),
+    ExRam,
+    Fill,
+}
+
+/* Registers written by the CPU, along with what the chip learnt from the PPU */
+#[derive(Debug, Clone, Default)]
+struct Mmc5State {
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+
+    /* $5113 to $5117 */
+    prg_banks: [u8; 5],
+    /* $5120 to $512B, the upper bits from $5130 included */
+    chr_banks: [u16; 12],
+    chr_upper_bits: u8,
+    /* Whether the background set ($5128-$512B) was written last */
+    is_background_set_last: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    is_irq_enabled: bool,
+    is_irq_pending: bool,
+    is_in_frame: bool,
+    scanline: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    /* Sprites are 8x16, as seen through the PPUCTRL writes */
+    is_8x16_sprites: bool,
+
+    /* Last PPU read and how many times in a row it was repeated */
+    last_read: Option<u16>,
+    repeated_reads: u8,
+    idle_cycles: u8,
+
+    /* Background tile being fetched on the current line, and that line */
+    tile: u8,
+    fetch_line: u16,
+    /* Pattern reads left for the background tile whose nametable byte was read */
+    background_patterns_left: u8,
+    is_fetching_sprites: bool,
+    is_background_fetch: bool,
+    is_split_fetch: bool,
+    /* ExRAM byte of the current tile, in extended attribute mode */
+    extended_attribute: u8,
+}
+
+impl Mmc5State {
+    /* Saves every register and fetch tracking field, in declaration order */
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.prg_mode);
+        state.write_u8(self.chr_mode);
+        state.write_bytes(&self.prg_ram_protect);
+        state.write_u8(self.exram_mode);
+        state.write_u8(self.nametable_mapping);
+        state.write_u8(self.fill_tile);
+        state.write_u8(self.fill_attribute);
+        state.write_bytes(&self.prg_banks);
+        for bank in self.chr_banks {
+            state.write_u16(bank);
+        }
+        state.write_u8(self.chr_upper_bits);
+        state.write_bool(self.is_background_set_last);
+        state.write_u8(self.split_control);
+        state.write_u8(self.split_scroll);
+        state.write_u8(self.split_bank);
+        state.write_u8(self.irq_compare);
+        state.write_bool(self.is_irq_enabled);
+        state.write_bool(self.is_irq_pending);
+        state.write_bool(self.is_in_frame);
+        state.write_u8(self.scanline);
+        state.write_u8(self.multiplicand);
+        state.write_u8(self.multiplier);
+        state.write_bool(self.is_8x16_sprites);
+        state.write_bool(self.last_read.is_some());
+        state.write_u16(self.last_read.unwrap_or(0));
+        state.write_u8(self.repeated_reads);
+        state.write_u8(self.idle_cycles);
+        state.write_u8(self.tile);
+        state.write_u16(self.fetch_line);
+        state.write_u8(self.background_patterns_left);
+        state.write_bool(self.is_fetching_sprites);
+        state.write_bool(self.is_background_fetch);
+        state.write_bool(self.is_split_fetch);
+        state.write_u8(self.extended_attribute);
+    }
+
+    /* Restores the fields saved by save_state */
+    fn load_state(&mut self, state: &mut StateReader) -> AppResult<()> {
+        self.prg_mode = state.read_u8()?;
+        self.chr_mode = state.read_u8()?;
+        self.prg_ram_protect = state.read_array()?;
+        self.exram_mode = state.read_u8()?;
+        self.nametable_mapping = state.read_u8()?;
+        self.fill_tile = state.read_u8()?;
+        self.fill_attribute = state.read_u8()?;
+        self.prg_banks = state.read_array()?;
+        for bank in self.chr_banks.iter_mut() {
+            *bank = state.read_u16()?;
+        }
+        self.chr_upper_bits = state.read_u8()?;
+        self.is_background_set_last = state.read_bool()?;
+        self.split_control = state.read_u8()?;
+        self.split_scroll = state.read_u8()?;
+        self.split_bank = state.read_u8()?;
+        self.irq_compare = state.read_u8()?;
+        self.is_irq_enabled = state.read_bool()?;
+        self.is_irq_pending = state.read_bool()?;
+        self.is_in_frame = state.read_bool()?;
+        self.scanline = state.read_u8()?;
+        self.multiplicand = state.read_u8()?;
+        self.multiplier = state.read_u8()?;
+        self.is_8x16_sprites = state.read_bool()?;
+        let has_last_read = state.read_bool()?;
+        let last_read = state.read_u16()?;
+        self.last_read = has_last_read.then_some(last_read);
+        self.repeated_reads = state.read_u8()?;
+        self.idle_cycles = state.read_u8()?;
+        self.tile = state.read_u8()?;
+        self.fetch_line = state.read_u16()?;
+        self.background_patterns_left = state.read_u8()?;
+        self.is_fetching_sprites = state.read_bool()?;
+        self.is_background_fetch = state.read_bool()?;
+        self.is_split_fetch = state.read_bool()?;
+        self.extended_attribute = state.read_u8()?;
+
+        Ok(())
+    }
+}
+
+/*
+ * Mapper 005, Nintendo's MMC5, the most capable of the
+ * official mappers. Besides its many PRG and CHR banking
+ * modes, it brings:
+ *
+ * - 1KB of ExRAM, used as an extra nametable, as per-tile
+ *   CHR banks and palettes (extended attributes) or as RAM
+ * - a fill mode nametable made of a single tile and palette
+ * - a vertical split screen drawn from ExRAM
+ * - a scanline IRQ, relying on its detection of the PPU
+ *   fetching the same nametable byte three times in a row at
+ *   the end of every rendered line
+ * - an 8 bit multiplier
+ *
+ * The chip tells background and sprite fetches apart by
+ * following the reads of the PPU, as sprite patterns are the
+ * only ones not preceded by a nametable byte.
+ */
+pub struct Mmc5 {
+    memory: CartridgeMemory,
+    exram: Memory,
+    state: RefCell<Mmc5State>,
+}
+
+impl Mmc5 {
+    /* Initializes a new MMC5 board */
+    pub fn new(mut memory: CartridgeMemory) -> Self {
+        if memory.prg_ram.len() < PRG_RAM_MAX_SIZE {
+            memory.prg_ram = Memory::new(PRG_RAM_MAX_SIZE);
+        }
+
+        let state = Mmc5State {
+            prg_mode: 3,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            ..Default::default()
+        };
+
+        Self {
+            memory,
+            exram: Memory::new(EXRAM_SIZE),
+            state: RefCell::new(state),
+        }
+    }
+
+    /* Reads $5204, acknowledging the scanline interrupt */
+    fn read_irq_status(&self) -> u8 {
+        let mut state = self.state.borrow_mut();
+        let status = ((state.is_irq_pending as u8) << 7) | ((state.is_in_frame as u8) << 6);
+        state.is_irq_pending = false;
+
+        status
+    }
+
+    /* Writes one of the registers between $5100 and $5206 */
+    fn write_register(&self, address: u16, value: u8) {
+        let mut state = self.state.borrow_mut();
+
+        match address {
+            PRG_MODE_ADDRESS => state.prg_mode = value & 0x03,
+            CHR_MODE_ADDRESS => state.chr_mode = value & 0x03,
+            PRG_RAM_PROTECT_1_ADDRESS => state.prg_ram_protect[0] = value & 0x03,
+            PRG_RAM_PROTECT_2_ADDRESS => state.prg_ram_protect[1] = value & 0x03,
+            EXRAM_MODE_ADDRESS => state.exram_mode = value & 0x03,
+            NAMETABLE_MAPPING_ADDRESS => state.nametable_mapping = value,
+            FILL_TILE_ADDRESS => state.fill_tile = value,
+            FILL_ATTRIBUTE_ADDRESS => state.fill_attribute = value & 0x03,
+            PRG_BANKS_ADDRESS_LO..=PRG_BANKS_ADDRESS_HI => {
+                state.prg_banks[(address - PRG_BANKS_ADDRESS_LO) as usize] = value
+            }
+            CHR_BANKS_ADDRESS_LO..=CHR_BANKS_ADDRESS_HI => {
+                let index = (address - CHR_BANKS_ADDRESS_LO) as usize;
+
+                state.chr_banks[index] = value as u16 | ((state.chr_upper_bits as u16) << 8);
+                state.is_background_set_last = index >= BACKGROUND_CHR_BANKS;
+            }
+            CHR_UPPER_BITS_ADDRESS => state.chr_upper_bits = value & 0x03,
+            SPLIT_CONTROL_ADDRESS => state.split_control = value,
+            SPLIT_SCROLL_ADDRESS => state.split_scroll = value,
+            SPLIT_BANK_ADDRESS => state.split_bank = value,
+            IRQ_COMPARE_ADDRESS => state.irq_compare = value,
+            IRQ_STATUS_ADDRESS => state.is_irq_enabled = value & 0x80 != 0,
+            MULTIPLIER_LO_ADDRESS => state.multiplicand = value,
+            MULTIPLIER_HI_ADDRESS => state.multiplier = value,
+            _ => {}
+        }
+    }
+
+    /*
+     * Writes the ExRAM from the CPU. While used for rendering it
+     * can only be written during a frame, 0 being written
+     * otherwise, and it is read-only in mode 3.
+     */
+    fn write_exram(&self, address: u16, value: u8) {
+        let state = self.state.borrow();
+        let address = (address - EXRAM_ADDRESS_LO) as usize;
+
+        match state.exram_mode {
+            0 | 1 => {
+                let value = if state.is_in_frame { value } else { 0 };
+                self.exram.write(address, value);
+            }
+            2 => self.exram.write(address, value),
+            _ => {}
+        }
+    }
+
+    /*
+     * 8KB bank mapped at a CPU address between $6000 and $FFFF,
+     * along with whether it is a ROM bank. The last register
+     * always maps ROM and $6000-$7FFF always maps RAM.
+     */
+    fn get_prg_bank(&self, address: u16) -> (bool, usize) {
+        let state = self.state.borrow();
+        let slot = ((address as usize) >> 13) & 0x03;
+
+        if address < 0x8000 {
+            return (false, (state.prg_banks[0] & 0x07) as usize);
+        }
+
+        /* Register used for the slot and number of 8KB banks it maps at once */
+        let (register, size) = match (state.prg_mode, slot) {
+            (0, _) => (4, 4),
+            (1 | 2, 0 | 1) => (2, 2),
+            (1, _) => (4, 2),
+            (_, slot) => (slot + 1, 1),
+        };
+
+        let value = state.prg_banks[register];
+        let is_rom = register == 4 || value & 0x80 != 0;
+        let bank = if is_rom { value & 0x7F } else { value & 0x07 } as usize;
+
+        (is_rom, (bank & !(size - 1)) | (slot & (size - 1)))
+    }
+
+    /* Work RAM writes are only allowed once both protect registers are unlocked */
+    fn is_prg_ram_writable(&self) -> bool {
+        self.state.borrow().prg_ram_protect == [0x02, 0x01]
+    }
+
+    /* Reads the work RAM through an 8KB bank */
+    fn read_prg_ram(&self, bank: usize, address: u16) -> u8 {
+        self.memory
+            .prg_ram
+            .read(self.get_prg_ram_address(bank, address))
+    }
+
+    /* Writes the work RAM through an 8KB bank */
+    fn write_prg_ram(&self, bank: usize, address: u16, value: u8) {
+        if self.is_prg_ram_writable() {
+            self.memory
+                .prg_ram
+                .write(self.get_prg_ram_address(bank, address), value);
+        }
+    }
+
+    /* Address inside the work RAM for an 8KB bank */
+    fn get_prg_ram_address(&self, bank: usize, address: u16) -> usize {
+        (bank * PRG_BANK_SIZE + (address as usize & (PRG_BANK_SIZE - 1)))
+            % self.memory.prg_ram.len()
+    }
+
+    /*
+     * CHR bank and bank size for a PPU address between $0000 and
+     * $1FFF. Sprites use the first set of registers and the
+     * background the second one while 8x16 sprites are drawn,
+     * otherwise the set written last is used for everything.
+     */
+    fn get_chr_bank(&self, address: u16) -> (usize, usize) {
+        let state = self.state.borrow();
+        let size = 0x2000 >> state.chr_mode;
+        /* Number of 1KB registers a bank spans */
+        let span = 1 << (3 - state.chr_mode);
+
+        let is_background_set = if state.is_8x16_sprites && state.is_in_frame {
+            state.is_background_fetch
+        } else {
+            state.is_background_set_last
+        };
+
+        /* The 4 background registers cover $0000-$0FFF and are mirrored at $1000 */
+        let register = if is_background_set {
+            BACKGROUND_CHR_BANKS + ((((address as usize & 0x0FFF) / size) * span + span - 1) & 0x03)
+        } else {
+            (address as usize / size) * span + span - 1
+        };
+
+        (state.chr_banks[register] as usize, size)
+    }
+
+    /* Where one of the four nametables is taken from */
+    fn get_nametable_source(&self, table: u16) -> NametableSource {
+        match (self.state.borrow().nametable_mapping >> (table * 2)) & 0x03 {
+            0 => NametableSource::Vram(0),
+            1 => NametableSource::Vram(1),
+            2 => NametableSource::ExRam,
+            _ => NametableSource::Fill,
+        }
+    }
+
+    /*
+     * Follows the fetches of the PPU. Three identical nametable
+     * reads in a row happen at the end of every rendered line,
+     * which is how the chip counts scanlines.
+     */
+    fn track_fetch(&self, address: u16) {
+        let mut state = self.state.borrow_mut();
+        state.idle_cycles = 0;
+
+        if state.last_read == Some(address) {
+            state.repeated_reads += 1;
+        } else {
+            state.repeated_reads = 0;
+        }
+        state.last_read = Some(address);
+
+        match address {
+            0x0000..=0x1FFF => {
+                state.is_background_fetch = state.background_patterns_left > 0;
+
+                if state.is_background_fetch {
+                    state.background_patterns_left -= 1;
+                } else {
+                    state.is_fetching_sprites = true;
+                    state.is_split_fetch = false;
+                }
+            }
+            0x2000..=0x2FFF if address & 0x03FF < 0x03C0 => {
+                if state.repeated_reads == 2 {
+                    Self::detect_scanline(&mut state);
+                }
+
+                if state.repeated_reads == 0 {
+                    /* The first tiles of a line are fetched right after the sprites */
+                    if state.is_fetching_sprites {
+                        state.is_fetching_sprites = false;
+                        state.tile = 0;
+                        state.fetch_line = if state.is_in_frame {
+                            state.scanline as u16 + 1
+                        } else {
+                            0
+                        };
+                    } else {
+                        state.tile = state.tile.wrapping_add(1);
+                    }
+                }
+
+                state.background_patterns_left = 2;
+                state.extended_attribute = self.exram.read(address as usize & 0x03FF);
+
+                let threshold = state.split_control & 0x1F;
+                let is_right_side = state.split_control & 0x40 != 0;
+                let is_split_side = if is_right_side {
+                    state.tile >= threshold
+                } else {
+                    state.tile < threshold
+                };
+
+                state.is_split_fetch = state.split_control & 0x80 != 0
+                    && state.exram_mode <= 1
+                    && state.is_in_frame
+                    && is_split_side;
+            }
+            _ => {}
+        }
+    }
+
+    /* Moves to the next scanline, raising the interrupt on the compared one */
+    fn detect_scanline(state: &mut Mmc5State) {
+        if !state.is_in_frame {
+            state.is_in_frame = true;
+            state.scanline = 0;
+            state.is_irq_pending = false;
+        } else {
+            state.scanline = state.scanline.wrapping_add(1);
+
+            if state.scanline == state.irq_compare {
+                state.is_irq_pending = true;
+            }
+        }
+    }
+
+    /* Line of the split screen being fetched, between 0 and 239 */
+    fn get_split_y(&self) -> u16 {
+        let state = self.state.borrow();
+        (state.split_scroll as u16 + state.fetch_line) % SPLIT_HEIGHT
+    }
+
+    /* Nametable or attribute byte of the split screen, taken from ExRAM */
+    fn read_split_nametable(&self, address: u16) -> u8 {
+        let tile = (self.state.borrow().tile & 0x1F) as usize;
+        let row = (self.get_split_y() / 8) as usize;
+
+        if address & 0x03FF < 0x03C0 {
+            self.exram.read(row * 32 + tile)
+        } else {
+            let attribute = self.exram.read(0x03C0 + (row / 4) * 8 + tile / 4);
+            let shift = ((row & 0x02) << 1) | (tile & 0x02);
+
+            get_repeated_palette((attribute >> shift) & 0x03)
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            IRQ_STATUS_ADDRESS => self.read_irq_status(),
+            MULTIPLIER_LO_ADDRESS | MULTIPLIER_HI_ADDRESS => {
+                let state = self.state.borrow();
+                let product = state.multiplicand as u16 * state.multiplier as u16;
+
+                if address == MULTIPLIER_LO_ADDRESS {
+                    product as u8
+                } else {
+                    (product >> 8) as u8
+                }
+            }
+            EXRAM_ADDRESS_LO..=EXRAM_ADDRESS_HI if self.state.borrow().exram_mode >= 2 => {
+                self.exram.read((address - EXRAM_ADDRESS_LO) as usize)
+            }
+            PRG_RAM_ADDRESS_LO..=0xFFFF => match self.get_prg_bank(address) {
+                (true, bank) => self.memory.read_prg(PRG_BANK_SIZE, bank, address),
+                (false, bank) => self.read_prg_ram(bank, address),
+            },
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        match address {
+            PRG_MODE_ADDRESS..=MULTIPLIER_HI_ADDRESS => self.write_register(address, value),
+            EXRAM_ADDRESS_LO..=EXRAM_ADDRESS_HI => self.write_exram(address, value),
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI | 0x8000..=0xFFFF => {
+                if let (false, bank) = self.get_prg_bank(address) {
+                    self.write_prg_ram(bank, address, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        let (is_background_fetch, is_split_fetch, exram_mode, extended_attribute) = {
+            let state = self.state.borrow();
+            let is_background_fetch = state.is_background_fetch && state.is_in_frame;
+
+            (
+                is_background_fetch,
+                is_background_fetch && state.is_split_fetch,
+                state.exram_mode,
+                state.extended_attribute,
+            )
+        };
+
+        if is_split_fetch {
+            let fine_y = self.get_split_y() & 0x07;
+            let bank = self.state.borrow().split_bank as usize;
+
+            return self
+                .memory
+                .read_chr(0x1000, bank, (address & 0x0FF8) | fine_y);
+        }
+
+        /* Extended attributes pick a 4KB bank for every background tile */
+        if is_background_fetch && exram_mode == 1 {
+            let bank = (extended_attribute & 0x3F) as usize
+                | ((self.state.borrow().chr_upper_bits as usize) << 6);
+
+            return self.memory.read_chr(0x1000, bank, address);
+        }
+
+        let (bank, size) = self.get_chr_bank(address);
+        self.memory.read_chr(size, bank, address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        let (bank, size) = self.get_chr_bank(address);
+        self.memory.write_chr(size, bank, address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        self.exram.save_state(state);
+        self.state.borrow().save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.exram.load_state(state)?;
+        self.state.borrow_mut().load_state(state)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        let pages = [0, 1, 2, 3].map(|table| match self.get_nametable_source(table) {
+            NametableSource::Vram(page) => page,
+            _ => 0,
+        });
+
+        Mirroring::Custom(pages)
+    }
+
+    fn clock(&self) {
+        let mut state = self.state.borrow_mut();
+
+        if state.idle_cycles < IDLE_CYCLES {
+            state.idle_cycles += 1;
+
+            if state.idle_cycles == IDLE_CYCLES {
+                state.is_in_frame = false;
+                state.last_read = None;
+            }
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        let state = self.state.borrow();
+        state.is_irq_pending && state.is_irq_enabled
+    }
+
+    fn on_ppu_read(&self, address: u16) {
+        self.track_fetch(address);
+    }
+
+    fn needs_dot_renderer(&self) -> bool {
+        true
+    }
+
+    fn on_ppu_register_write(&self, register: u16, value: u8) {
+        let mut state = self.state.borrow_mut();
+
+        match register {
+            PPUCTRL => state.is_8x16_sprites = value & 0x20 != 0,
+            /* Turning rendering off ends the frame right away */
+            PPUMASK if value & 0x18 == 0 => state.is_in_frame = false,
+            _ => {}
+        }
+    }
+
+    fn read_nametable(&self, address: u16) -> Option<u8> {
+        let (is_in_frame, is_split_fetch, exram_mode, extended_attribute) = {
+            let state = self.state.borrow();
+            (
+                state.is_in_frame,
+                state.is_split_fetch,
+                state.exram_mode,
+                state.extended_attribute,
+            )
+        };
+        let is_attribute = address & 0x03FF >= 0x03C0;
+
+        if is_in_frame && is_split_fetch {
+            return Some(self.read_split_nametable(address));
+        }
+
+        /* Extended attributes give every tile its own palette */
+        if is_in_frame && is_attribute && exram_mode == 1 {
+            return Some(get_repeated_palette(extended_attribute >> 6));
+        }
+
+        match self.get_nametable_source((address >> 10) & 0x03) {
+            NametableSource::Vram(_) => None,
+            NametableSource::ExRam if exram_mode <= 1 => {
+                Some(self.exram.read(address as usize & 0x03FF))
+            }
+            NametableSource::ExRam => Some(0),
+            NametableSource::Fill => {
+                let state = self.state.borrow();
+
+                if is_attribute {
+                    Some(get_repeated_palette(state.fill_attribute))
+                } else {
+                    Some(state.fill_tile)
+                }
+            }
+        }
+    }
+
+    fn write_nametable(&self, address: u16, value: u8) -> bool {
+        match self.get_nametable_source((address >> 10) & 0x03) {
+            NametableSource::Vram(_) => false,
+            NametableSource::ExRam => {
+                if self.state.borrow().exram_mode <= 1 {
+                    self.exram.write(address as usize & 0x03FF, value);
+                }
+                true
+            }
+            NametableSource::Fill => true,
+        }
+    }
+}
+
+/*
+ * Attribute byte giving the same palette to its four areas,
+ * so the PPU finds it whatever the position of the tile
+ */
+fn get_repeated_palette(palette: u8) -> u8 {
+    (palette & 0x03) * 0x55
+}
@@ -0,0 +1,17 @@
+pub mod axrom;
+pub mod camerica;
+pub mod cnrom;
+pub mod color_dreams;
+pub mod fme7;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc5;
+pub mod namco108;
+pub mod namco163;
+pub mod nrom;
+pub mod rambo1;
+pub mod uxrom;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+pub mod vrc_irq;
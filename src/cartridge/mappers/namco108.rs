@@ -0,0 +1,125 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks Namco 108 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/*
+ * Mapper 206, Namco's 108 and the DxROM boards. The chip is
+ * the ancestor of the MMC3 and switches banks the same way,
+ * selecting one of 8 registers at $8000 then writing its
+ * value at $8001, but without any banking mode, mirroring
+ * control or IRQ counter.
+ *
+ * Two 2KB then four 1KB CHR banks are switchable, along with
+ * the two first 8KB PRG banks, the last two being fixed.
+ */
+pub struct Namco108 {
+    memory: CartridgeMemory,
+
+    bank_select: Cell<u8>,
+    registers: Cell<[u8; 8]>,
+}
+
+impl Namco108 {
+    /* Initializes a new Namco 108 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            bank_select: Cell::new(0),
+            registers: Cell::new([0; 8]),
+        }
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        let last_bank = self.memory.last_prg_bank(PRG_BANK_SIZE);
+
+        match address {
+            0x8000..=0x9FFF => (self.registers.get()[6] & 0x0F) as usize,
+            0xA000..=0xBFFF => (self.registers.get()[7] & 0x0F) as usize,
+            0xC000..=0xDFFF => last_bank.saturating_sub(1),
+            _ => last_bank,
+        }
+    }
+
+    /* 1KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let registers = self.registers.get();
+        let slot = address as usize / CHR_BANK_SIZE;
+
+        let bank = match slot {
+            0 => registers[0] & 0x3E,
+            1 => registers[0] | 0x01,
+            2 => registers[1] & 0x3E,
+            3 => registers[1] | 0x01,
+            _ => registers[slot - 2],
+        };
+
+        (bank & 0x3F) as usize
+    }
+}
+
+impl Mapper for Namco108 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if let 0x8000..=0x9FFF = address {
+            if address & 0x01 == 0 {
+                self.bank_select.set(value & 0x07);
+            } else {
+                let mut registers = self.registers.get();
+                registers[self.bank_select.get() as usize] = value;
+                self.registers.set(registers);
+            }
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.bank_select.get());
+        state.write_bytes(&self.registers.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.bank_select.set(state.read_u8()?);
+        self.registers.set(state.read_array()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.memory.mirroring
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+    },
+    errors::AppResult,
+    memory::memory::Memory,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks the Namco 163 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/* Internal RAM holding the sound channel registers and waveforms */
+pub const SOUND_RAM_SIZE: usize = 0x80;
+pub const SOUND_DATA_ADDRESS_LO: u16 = 0x4800;
+pub const SOUND_DATA_ADDRESS_HI: u16 = 0x4FFF;
+
+pub const IRQ_COUNTER_LO_ADDRESS_LO: u16 = 0x5000;
+pub const IRQ_COUNTER_LO_ADDRESS_HI: u16 = 0x57FF;
+pub const IRQ_COUNTER_HI_ADDRESS_LO: u16 = 0x5800;
+pub const IRQ_COUNTER_HI_ADDRESS_HI: u16 = 0x5FFF;
+
+/* Banks from this value on select a page of the PPU VRAM instead of CHR ROM */
+const VRAM_BANKS: u8 = 0xE0;
+
+/* Value the IRQ counter stops at, raising the interrupt */
+const IRQ_COUNTER_MAX: u16 = 0x7FFF;
+
+/*
+ * Mapper 019, Namco's 129 and 163. Three 8KB PRG banks are
+ * switchable, the last one being fixed, along with eight
+ * 1KB CHR banks and four nametable banks. Nametables may come
+ * from either the PPU VRAM or the CHR ROM.
+ *
+ * The chip counts CPU cycles up to $7FFF for its interrupt
+ * and holds 128 bytes of RAM, reached through a data port,
+ * used by its wavetable sound channels. Using the VRAM as
+ * pattern tables is not supported.
+ */
+pub struct Namco163 {
+    memory: CartridgeMemory,
+    sound_ram: Memory,
+
+    prg_banks: Cell<[u8; 3]>,
+    chr_banks: Cell<[u8; 8]>,
+    nametable_banks: Cell<[u8; 4]>,
+
+    /* Address of the data port inside the sound RAM, bit 7 enabling auto-increment */
+    sound_address: Cell<u8>,
+
+    irq_counter: Cell<u16>,
+    is_irq_enabled: Cell<bool>,
+    is_irq_pending: Cell<bool>,
+}
+
+impl Namco163 {
+    /* Initializes a new Namco 163 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            sound_ram: Memory::new(SOUND_RAM_SIZE),
+            prg_banks: Cell::new([0; 3]),
+            chr_banks: Cell::new([0; 8]),
+            nametable_banks: Cell::new([VRAM_BANKS; 4]),
+            sound_address: Cell::new(0),
+            irq_counter: Cell::new(0),
+            is_irq_enabled: Cell::new(false),
+            is_irq_pending: Cell::new(false),
+        }
+    }
+
+    /* Gives access to the RAM of the sound channels */
+    pub fn sound_ram(&self) -> &Memory {
+        &self.sound_ram
+    }
+
+    /* Moves to the next byte of sound RAM after a port access, if enabled */
+    fn step_sound_address(&self) {
+        let address = self.sound_address.get();
+
+        if address & 0x80 != 0 {
+            self.sound_address
+                .set(0x80 | (address.wrapping_add(1) & 0x7F));
+        }
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        match address {
+            0x8000..=0xDFFF => {
+                self.prg_banks.get()[(address as usize - 0x8000) / PRG_BANK_SIZE] as usize
+            }
+            _ => self.memory.last_prg_bank(PRG_BANK_SIZE),
+        }
+    }
+
+    /* 1KB CHR ROM bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        self.chr_banks.get()[address as usize / CHR_BANK_SIZE] as usize
+    }
+
+    /* Bank of one of the four nametables */
+    fn get_nametable_bank(&self, address: u16) -> u8 {
+        self.nametable_banks.get()[((address >> 10) & 0x03) as usize]
+    }
+}
+
+impl Mapper for Namco163 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            SOUND_DATA_ADDRESS_LO..=SOUND_DATA_ADDRESS_HI => {
+                let value = self
+                    .sound_ram
+                    .read((self.sound_address.get() & 0x7F) as usize);
+                self.step_sound_address();
+
+                value
+            }
+            IRQ_COUNTER_LO_ADDRESS_LO..=IRQ_COUNTER_LO_ADDRESS_HI => self.irq_counter.get() as u8,
+            IRQ_COUNTER_HI_ADDRESS_LO..=IRQ_COUNTER_HI_ADDRESS_HI => {
+                ((self.irq_counter.get() >> 8) as u8) | ((self.is_irq_enabled.get() as u8) << 7)
+            }
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        match address {
+            SOUND_DATA_ADDRESS_LO..=SOUND_DATA_ADDRESS_HI => {
+                self.sound_ram
+                    .write((self.sound_address.get() & 0x7F) as usize, value);
+                self.step_sound_address();
+            }
+            IRQ_COUNTER_LO_ADDRESS_LO..=IRQ_COUNTER_LO_ADDRESS_HI => {
+                self.irq_counter
+                    .set((self.irq_counter.get() & 0x7F00) | value as u16);
+                self.is_irq_pending.set(false);
+            }
+            IRQ_COUNTER_HI_ADDRESS_LO..=IRQ_COUNTER_HI_ADDRESS_HI => {
+                self.irq_counter
+                    .set((self.irq_counter.get() & 0x00FF) | ((value as u16 & 0x7F) << 8));
+                self.is_irq_enabled.set(value & 0x80 != 0);
+                self.is_irq_pending.set(false);
+            }
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI => self.memory.write_prg_ram(address, value),
+            0x8000..=0xBFFF => {
+                let mut chr_banks = self.chr_banks.get();
+                chr_banks[(address as usize - 0x8000) / 0x0800] = value;
+                self.chr_banks.set(chr_banks);
+            }
+            0xC000..=0xDFFF => {
+                let mut nametable_banks = self.nametable_banks.get();
+                nametable_banks[(address as usize - 0xC000) / 0x0800] = value;
+                self.nametable_banks.set(nametable_banks);
+            }
+            0xE000..=0xF7FF => {
+                let mut prg_banks = self.prg_banks.get();
+                prg_banks[(address as usize - 0xE000) / 0x0800] = value & 0x3F;
+                self.prg_banks.set(prg_banks);
+            }
+            0xF800..=0xFFFF => self.sound_address.set(value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        self.sound_ram.save_state(state);
+        state.write_bytes(&self.prg_banks.get());
+        state.write_bytes(&self.chr_banks.get());
+        state.write_bytes(&self.nametable_banks.get());
+        state.write_u8(self.sound_address.get());
+        state.write_u16(self.irq_counter.get());
+        state.write_bool(self.is_irq_enabled.get());
+        state.write_bool(self.is_irq_pending.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.sound_ram.load_state(state)?;
+        self.prg_banks.set(state.read_array()?);
+        self.chr_banks.set(state.read_array()?);
+        self.nametable_banks.set(state.read_array()?);
+        self.sound_address.set(state.read_u8()?);
+        self.irq_counter.set(state.read_u16()?);
+        self.is_irq_enabled.set(state.read_bool()?);
+        self.is_irq_pending.set(state.read_bool()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Custom(self.nametable_banks.get().map(|bank| bank & 0x01))
+    }
+
+    fn clock(&self) {
+        let counter = self.irq_counter.get();
+
+        if self.is_irq_enabled.get() && counter < IRQ_COUNTER_MAX {
+            self.irq_counter.set(counter + 1);
+
+            if counter + 1 == IRQ_COUNTER_MAX {
+                self.is_irq_pending.set(true);
+            }
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.is_irq_pending.get()
+    }
+
+    fn read_nametable(&self, address: u16) -> Option<u8> {
+        let bank = self.get_nametable_bank(address);
+
+        /* Nametables below the VRAM banks come from CHR ROM */
+        (bank < VRAM_BANKS).then(|| self.memory.read_chr(CHR_BANK_SIZE, bank as usize, address))
+    }
+
+    fn write_nametable(&self, address: u16, _value: u8) -> bool {
+        self.get_nametable_bank(address) < VRAM_BANKS
+    }
+
+    fn expansion_chips(&self) -> ExpansionChips {
+        ExpansionChips::N163
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{
+            CHR_BANK_SIZE, CartridgeMemory, Mapper, PRG_BANK_SIZE, PRG_RAM_ADDRESS_HI,
+            PRG_RAM_ADDRESS_LO,
+        },
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+
+/*
+ * Mapper 000, found on boards without any banking. Either
+ * 16KB of PRG ROM mirrored twice or 32KB of it are mapped at
+ * $8000-$FFFF, along with 8KB of CHR ROM. Boards fitted with
+ * work RAM map it at $6000-$7FFF.
+ */
+pub struct Nrom {
+    memory: CartridgeMemory,
+}
+
+impl Nrom {
+    /* Initializes a new NROM board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self { memory }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                let bank = (address as usize - 0x8000) / PRG_BANK_SIZE;
+                self.memory.read_prg(PRG_BANK_SIZE, bank, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if let PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI = address {
+            self.memory.write_prg_ram(address, value);
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE, 0, address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE, 0, address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.memory.mirroring
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks RAMBO-1 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/* Number of CPU cycles between two clocks of the IRQ counter in cycle mode */
+const IRQ_PRESCALER_PERIOD: u8 = 4;
+
+/*
+ * Mapper 064, Tengen's RAMBO-1. Banks are switched like on
+ * the MMC3, by selecting one of 16 registers at $8000 then
+ * writing its value at $8001. Three 8KB PRG banks are
+ * switchable, the last one being fixed, and the 2KB CHR
+ * banks may be split into 1KB ones.
+ *
+ * The IRQ counter is clocked either by rises of the PPU A12
+ * line, once per scanline, or every 4 CPU cycles.
+ */
+pub struct Rambo1 {
+    memory: CartridgeMemory,
+
+    /* $8000, selecting the register written along with the banking modes */
+    bank_select: Cell<u8>,
+    registers: Cell<[u8; 16]>,
+    mirroring: Cell<u8>,
+
+    irq_latch: Cell<u8>,
+    irq_counter: Cell<u16>,
+    /* Set by writes to $C001, the counter being reloaded on its next clock */
+    is_irq_reload: Cell<bool>,
+    is_irq_cycle_mode: Cell<bool>,
+    irq_prescaler: Cell<u8>,
+    is_irq_enabled: Cell<bool>,
+    is_irq_pending: Cell<bool>,
+}
+
+impl Rambo1 {
+    /* Initializes a new RAMBO-1 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            bank_select: Cell::new(0),
+            registers: Cell::new([0; 16]),
+            mirroring: Cell::new(0),
+            irq_latch: Cell::new(0),
+            irq_counter: Cell::new(0),
+            is_irq_reload: Cell::new(false),
+            is_irq_cycle_mode: Cell::new(false),
+            irq_prescaler: Cell::new(0),
+            is_irq_enabled: Cell::new(false),
+            is_irq_pending: Cell::new(false),
+        }
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        let registers = self.registers.get();
+        let is_swapped = self.bank_select.get() & 0x40 != 0;
+
+        let bank = match (address, is_swapped) {
+            (0x8000..=0x9FFF, false) | (0xA000..=0xBFFF, true) => registers[6],
+            (0xA000..=0xBFFF, false) | (0xC000..=0xDFFF, true) => registers[7],
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => registers[15],
+            _ => return self.memory.last_prg_bank(PRG_BANK_SIZE),
+        };
+
+        bank as usize
+    }
+
+    /* 1KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let registers = self.registers.get();
+        let bank_select = self.bank_select.get();
+        let is_1k_mode = bank_select & 0x20 != 0;
+
+        /* Bit 7 swaps the two pattern tables */
+        let address = if bank_select & 0x80 != 0 {
+            address ^ 0x1000
+        } else {
+            address
+        };
+        let slot = address as usize / CHR_BANK_SIZE;
+
+        let bank = match (slot, is_1k_mode) {
+            (0, false) => registers[0] & 0xFE,
+            (1, false) => registers[0] | 0x01,
+            (2, false) => registers[1] & 0xFE,
+            (3, false) => registers[1] | 0x01,
+            (0, true) => registers[0],
+            (1, true) => registers[8],
+            (2, true) => registers[1],
+            (3, true) => registers[9],
+            _ => registers[slot - 2],
+        };
+
+        bank as usize
+    }
+
+    /* Counts down a scanline or 4 CPU cycles, raising the interrupt at 0 */
+    fn clock_irq_counter(&self) {
+        let latch = self.irq_latch.get() as u16;
+
+        let reloaded = if self.is_irq_reload.get() {
+            self.is_irq_reload.set(false);
+
+            /* Small latches are not adjusted the same, as Hard Drivin' relies on */
+            if latch <= 1 { latch + 1 } else { latch + 2 }
+        } else if self.irq_counter.get() == 0 {
+            latch + 1
+        } else {
+            self.irq_counter.get()
+        };
+        let counter = reloaded - 1;
+
+        self.irq_counter.set(counter);
+
+        if counter == 0 && self.is_irq_enabled.get() {
+            self.is_irq_pending.set(true);
+        }
+    }
+}
+
+impl Mapper for Rambo1 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        let is_odd = address & 0x01 != 0;
+
+        match (address, is_odd) {
+            (PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI, _) => {
+                self.memory.write_prg_ram(address, value)
+            }
+            (0x8000..=0x9FFF, false) => self.bank_select.set(value),
+            (0x8000..=0x9FFF, true) => {
+                let mut registers = self.registers.get();
+                registers[(self.bank_select.get() & 0x0F) as usize] = value;
+                self.registers.set(registers);
+            }
+            (0xA000..=0xBFFF, false) => self.mirroring.set(value & 0x01),
+            (0xC000..=0xDFFF, false) => self.irq_latch.set(value),
+            (0xC000..=0xDFFF, true) => {
+                self.is_irq_cycle_mode.set(value & 0x01 != 0);
+                self.is_irq_reload.set(true);
+                self.irq_prescaler.set(0);
+            }
+            (0xE000..=0xFFFF, false) => {
+                self.is_irq_enabled.set(false);
+                self.is_irq_pending.set(false);
+            }
+            (0xE000..=0xFFFF, true) => self.is_irq_enabled.set(true),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.bank_select.get());
+        state.write_bytes(&self.registers.get());
+        state.write_u8(self.mirroring.get());
+        state.write_u8(self.irq_latch.get());
+        state.write_u16(self.irq_counter.get());
+        state.write_bool(self.is_irq_reload.get());
+        state.write_bool(self.is_irq_cycle_mode.get());
+        state.write_u8(self.irq_prescaler.get());
+        state.write_bool(self.is_irq_enabled.get());
+        state.write_bool(self.is_irq_pending.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.bank_select.set(state.read_u8()?);
+        self.registers.set(state.read_array()?);
+        self.mirroring.set(state.read_u8()?);
+        self.irq_latch.set(state.read_u8()?);
+        self.irq_counter.set(state.read_u16()?);
+        self.is_irq_reload.set(state.read_bool()?);
+        self.is_irq_cycle_mode.set(state.read_bool()?);
+        self.irq_prescaler.set(state.read_u8()?);
+        self.is_irq_enabled.set(state.read_bool()?);
+        self.is_irq_pending.set(state.read_bool()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.mirroring.get() == 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    fn clock(&self) {
+        if !self.is_irq_cycle_mode.get() {
+            return;
+        }
+
+        let prescaler = (self.irq_prescaler.get() + 1) % IRQ_PRESCALER_PERIOD;
+        self.irq_prescaler.set(prescaler);
+
+        if prescaler == 0 {
+            self.clock_irq_counter();
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.is_irq_pending.get()
+    }
+
+    fn on_a12_rise(&self) {
+        if !self.is_irq_cycle_mode.get() {
+            self.clock_irq_counter();
+        }
+    }
+}
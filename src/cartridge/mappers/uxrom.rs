@@ -0,0 +1,81 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, CartridgeMemory, Mapper, PRG_BANK_SIZE},
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/*
+ * Mapper 002, the UxROM boards. Any write to $8000-$FFFF
+ * selects the 16KB PRG bank mapped at $8000, while the last
+ * bank stays fixed at $C000. Graphics come from 8KB of CHR
+ * RAM.
+ */
+pub struct Uxrom {
+    memory: CartridgeMemory,
+    prg_bank: Cell<u8>,
+}
+
+impl Uxrom {
+    /* Initializes a new UxROM board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            prg_bank: Cell::new(0),
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xBFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.prg_bank.get() as usize, address)
+            }
+            0xC000..=0xFFFF => {
+                let last_bank = self.memory.last_prg_bank(PRG_BANK_SIZE);
+                self.memory.read_prg(PRG_BANK_SIZE, last_bank, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = self.memory.get_bus_value(self.cpu_read(address), value);
+            self.prg_bank.set(value);
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE, 0, address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE, 0, address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.prg_bank.get());
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_bank.set(state.read_u8()?);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.memory.mirroring
+    }
+}
@@ -0,0 +1,253 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+        mappers::vrc_irq::VrcIrq,
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks VRC2 and VRC4 switch */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/*
+ * Boards of the family, each wiring the two register select
+ * inputs of the chip to different CPU address lines. Where a
+ * mapper number covers two boards, the lines of both are
+ * combined as games only write the registers through one.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vrc4Variant {
+    /* Mapper 21, VRC4a (A1, A2) and VRC4c (A6, A7) */
+    Vrc4ac,
+    /* Mapper 22, VRC2a (A1, A0), with CHR banks in 2KB units */
+    Vrc2a,
+    /* Mapper 23, VRC2b (A0, A1) and VRC4e (A2, A3) */
+    Vrc2bVrc4e,
+    /* Mapper 25, VRC4b (A1, A0) and VRC4d (A3, A2) */
+    Vrc4bd,
+}
+
+impl Vrc4Variant {
+    /* CPU address lines driving the first and the second register select input */
+    fn get_address_lines(&self) -> (u16, u16) {
+        match self {
+            Vrc4Variant::Vrc4ac => (0x02 | 0x40, 0x04 | 0x80),
+            Vrc4Variant::Vrc2a => (0x02, 0x01),
+            Vrc4Variant::Vrc2bVrc4e => (0x01 | 0x04, 0x02 | 0x08),
+            Vrc4Variant::Vrc4bd => (0x02 | 0x08, 0x01 | 0x04),
+        }
+    }
+}
+
+/*
+ * Mappers 021, 022, 023 and 025, Konami's VRC2 and VRC4. Two
+ * 8KB PRG banks are switchable, along with eight 1KB CHR
+ * banks written a nibble at a time. The VRC4 adds a PRG swap
+ * mode, single screen mirroring and the VRC IRQ counter.
+ */
+pub struct Vrc4 {
+    memory: CartridgeMemory,
+    variant: Vrc4Variant,
+
+    prg_banks: Cell<[u8; 2]>,
+    /* Whether the first switchable bank is moved to $C000 */
+    is_prg_swapped: Cell<bool>,
+    chr_banks: Cell<[u16; 8]>,
+    mirroring: Cell<Mirroring>,
+    irq: Cell<VrcIrq>,
+}
+
+impl Vrc4 {
+    /* Initializes a new VRC2 or VRC4 board */
+    pub fn new(memory: CartridgeMemory, variant: Vrc4Variant) -> Self {
+        Self {
+            mirroring: Cell::new(memory.mirroring),
+            memory,
+            variant,
+            prg_banks: Cell::new([0; 2]),
+            is_prg_swapped: Cell::new(false),
+            chr_banks: Cell::new([0; 8]),
+            irq: Cell::new(VrcIrq::new()),
+        }
+    }
+
+    /* Turns a CPU address into its $x000-$x003 register, undoing the board wiring */
+    fn get_register(&self, address: u16) -> u16 {
+        let (line_0, line_1) = self.variant.get_address_lines();
+        let select_0 = (address & line_0 != 0) as u16;
+        let select_1 = (address & line_1 != 0) as u16;
+
+        (address & 0xF000) | (select_1 << 1) | select_0
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        let [bank_0, bank_1] = self.prg_banks.get().map(|bank| bank as usize);
+        let second_last = self.memory.last_prg_bank(PRG_BANK_SIZE).saturating_sub(1);
+
+        match (address, self.is_prg_swapped.get()) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => bank_0,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => bank_1,
+            _ => self.memory.last_prg_bank(PRG_BANK_SIZE),
+        }
+    }
+
+    /* 1KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let bank = self.chr_banks.get()[address as usize / CHR_BANK_SIZE] as usize;
+
+        /* VRC2a ignores the lowest bit of the banks */
+        if self.variant == Vrc4Variant::Vrc2a {
+            bank >> 1
+        } else {
+            bank
+        }
+    }
+
+    /* Writes a nibble of one of the CHR banks, from $B000 to $E003 */
+    fn write_chr_bank(&self, register: u16, value: u8) {
+        let index = (((register >> 12) - 0x0B) * 2 + ((register >> 1) & 0x01)) as usize;
+        let mut chr_banks = self.chr_banks.get();
+
+        chr_banks[index] = if register & 0x01 == 0 {
+            (chr_banks[index] & 0x1F0) | (value as u16 & 0x0F)
+        } else {
+            (chr_banks[index] & 0x00F) | ((value as u16 & 0x1F) << 4)
+        };
+
+        self.chr_banks.set(chr_banks);
+    }
+
+    /* Writes the IRQ registers, from $F000 to $F003 */
+    fn write_irq(&self, register: u16, value: u8) {
+        let mut irq = self.irq.get();
+
+        match register & 0x03 {
+            0 => irq.set_latch((irq.latch() & 0xF0) | (value & 0x0F)),
+            1 => irq.set_latch((irq.latch() & 0x0F) | (value << 4)),
+            2 => irq.write_control(value),
+            _ => irq.acknowledge(),
+        }
+
+        self.irq.set(irq);
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if let PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI = address {
+            self.memory.write_prg_ram(address, value);
+            return;
+        }
+
+        let register = self.get_register(address);
+
+        match register {
+            0x8000..=0x8003 => {
+                let mut prg_banks = self.prg_banks.get();
+                prg_banks[0] = value & 0x1F;
+                self.prg_banks.set(prg_banks);
+            }
+            0x9000 | 0x9001 => {
+                /* Single screen mirroring only exists on the VRC4 */
+                let mode = if self.variant == Vrc4Variant::Vrc2a {
+                    value & 0x01
+                } else {
+                    value & 0x03
+                };
+
+                self.mirroring.set(match mode {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                });
+            }
+            0x9002 | 0x9003 if self.variant != Vrc4Variant::Vrc2a => {
+                self.is_prg_swapped.set(value & 0x02 != 0)
+            }
+            0xA000..=0xA003 => {
+                let mut prg_banks = self.prg_banks.get();
+                prg_banks[1] = value & 0x1F;
+                self.prg_banks.set(prg_banks);
+            }
+            0xB000..=0xE003 => self.write_chr_bank(register, value),
+            0xF000..=0xF003 if self.variant != Vrc4Variant::Vrc2a => {
+                self.write_irq(register, value)
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_bytes(&self.prg_banks.get());
+        state.write_bool(self.is_prg_swapped.get());
+        for bank in self.chr_banks.get() {
+            state.write_u16(bank);
+        }
+        self.mirroring.get().save_state(state);
+        self.irq.get().save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_banks.set(state.read_array()?);
+        self.is_prg_swapped.set(state.read_bool()?);
+        let mut chr_banks = [0; 8];
+        for bank in chr_banks.iter_mut() {
+            *bank = state.read_u16()?;
+        }
+        self.chr_banks.set(chr_banks);
+        self.mirroring.set(Mirroring::load_state(state)?);
+        let mut irq = VrcIrq::new();
+        irq.load_state(state)?;
+        self.irq.set(irq);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring.get()
+    }
+
+    fn clock(&self) {
+        let mut irq = self.irq.get();
+        irq.clock();
+        self.irq.set(irq);
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.get().is_pending()
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+        mappers::vrc_irq::VrcIrq,
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks VRC6 switches */
+pub const PRG_16K_BANK_SIZE: usize = 0x4000;
+pub const PRG_8K_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/*
+ * Mappers 024 and 026, Konami's VRC6. A 16KB and an 8KB PRG
+ * bank are switchable, the last 8KB being fixed, along with
+ * eight CHR registers used as 1KB or 2KB banks. The chip
+ * also has the VRC IRQ counter and three sound channels,
+ * which are emulated by the APU.
+ *
+ * Mapper 026 boards swap the A0 and A1 address lines, so
+ * their register addresses are translated first.
+ */
+pub struct Vrc6 {
+    memory: CartridgeMemory,
+    are_lines_swapped: bool,
+
+    prg_16k_bank: Cell<u8>,
+    prg_8k_bank: Cell<u8>,
+    chr_banks: Cell<[u8; 8]>,
+    /* $B003, selecting the CHR layout, the mirroring and enabling work RAM */
+    banking_control: Cell<u8>,
+    irq: Cell<VrcIrq>,
+}
+
+impl Vrc6 {
+    /* Initializes a new VRC6 board, the address lines being swapped on mapper 026 */
+    pub fn new(memory: CartridgeMemory, are_lines_swapped: bool) -> Self {
+        Self {
+            memory,
+            are_lines_swapped,
+            prg_16k_bank: Cell::new(0),
+            prg_8k_bank: Cell::new(0),
+            chr_banks: Cell::new([0; 8]),
+            banking_control: Cell::new(0),
+            irq: Cell::new(VrcIrq::new()),
+        }
+    }
+
+    /*
+     * Turns a CPU address into the register the chip sees, as
+     * wired on mapper 024. Only A0, A1 and A12-A15 are decoded.
+     */
+    fn get_register(&self, address: u16) -> u16 {
+        let address = address & 0xF003;
+
+        if self.are_lines_swapped {
+            (address & 0xFFFC) | ((address & 0x01) << 1) | ((address >> 1) & 0x01)
+        } else {
+            address
+        }
+    }
+
+    /* Work RAM is enabled by bit 7 of $B003 */
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.banking_control.get() & 0x80 != 0
+    }
+
+    /*
+     * 1KB CHR bank mapped at a PPU address between $0000 and
+     * $1FFF. Registers are used as eight 1KB banks, four 2KB
+     * banks, or 1KB banks followed by 2KB ones.
+     */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        let slot = address as usize / CHR_BANK_SIZE;
+        let chr_banks = self.chr_banks.get();
+
+        let (register, is_2k_bank) = match self.banking_control.get() & 0x03 {
+            0 => (slot, false),
+            1 => (slot / 2, true),
+            _ if slot < 4 => (slot, false),
+            _ => (4 + (slot - 4) / 2, true),
+        };
+
+        if is_2k_bank {
+            (chr_banks[register] as usize & !0x01) | (slot & 0x01)
+        } else {
+            chr_banks[register] as usize
+        }
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if self.is_prg_ram_enabled() => {
+                self.memory.read_prg_ram(address)
+            }
+            0x8000..=0xBFFF => {
+                self.memory
+                    .read_prg(PRG_16K_BANK_SIZE, self.prg_16k_bank.get() as usize, address)
+            }
+            0xC000..=0xDFFF => {
+                self.memory
+                    .read_prg(PRG_8K_BANK_SIZE, self.prg_8k_bank.get() as usize, address)
+            }
+            0xE000..=0xFFFF => {
+                let last_bank = self.memory.last_prg_bank(PRG_8K_BANK_SIZE);
+                self.memory.read_prg(PRG_8K_BANK_SIZE, last_bank, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if let PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI = address {
+            if self.is_prg_ram_enabled() {
+                self.memory.write_prg_ram(address, value);
+            }
+            return;
+        }
+
+        let register = self.get_register(address);
+
+        match register {
+            0x8000..=0x8FFF => self.prg_16k_bank.set(value & 0x0F),
+            0xB003 => self.banking_control.set(value),
+            0xC000..=0xCFFF => self.prg_8k_bank.set(value & 0x1F),
+            0xD000..=0xEFFF => {
+                let index = (((register >> 12) - 0x0D) * 4 + (register & 0x03)) as usize;
+                let mut chr_banks = self.chr_banks.get();
+                chr_banks[index] = value;
+                self.chr_banks.set(chr_banks);
+            }
+            0xF000..=0xFFFF => {
+                let mut irq = self.irq.get();
+
+                match register & 0x03 {
+                    0 => irq.set_latch(value),
+                    1 => irq.write_control(value),
+                    2 => irq.acknowledge(),
+                    _ => {}
+                }
+
+                self.irq.set(irq);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_u8(self.prg_16k_bank.get());
+        state.write_u8(self.prg_8k_bank.get());
+        state.write_bytes(&self.chr_banks.get());
+        state.write_u8(self.banking_control.get());
+        self.irq.get().save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_16k_bank.set(state.read_u8()?);
+        self.prg_8k_bank.set(state.read_u8()?);
+        self.chr_banks.set(state.read_array()?);
+        self.banking_control.set(state.read_u8()?);
+        let mut irq = VrcIrq::new();
+        irq.load_state(state)?;
+        self.irq.set(irq);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control.get() >> 2) & 0x03 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn clock(&self) {
+        let mut irq = self.irq.get();
+        irq.clock();
+        self.irq.set(irq);
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.get().is_pending()
+    }
+
+    fn expansion_chips(&self) -> ExpansionChips {
+        ExpansionChips::VRC6
+    }
+
+    fn audio_address(&self, address: u16) -> u16 {
+        self.get_register(address)
+    }
+}
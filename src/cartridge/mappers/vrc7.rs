@@ -0,0 +1,179 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CartridgeMemory, Mapper, PRG_RAM_ADDRESS_HI, PRG_RAM_ADDRESS_LO},
+        mappers::vrc_irq::VrcIrq,
+    },
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+use std::cell::Cell;
+
+/* Size of the banks VRC7 switches */
+pub const PRG_BANK_SIZE: usize = 0x2000;
+pub const CHR_BANK_SIZE: usize = 0x0400;
+
+/*
+ * Mapper 085, Konami's VRC7. Three 8KB PRG banks and eight
+ * 1KB CHR banks are switchable, the last PRG bank being
+ * fixed, and the chip has the VRC IRQ counter.
+ *
+ * Every register pair is told apart by A4 on VRC7a boards
+ * and by A3 on VRC7b ones, both lines are accepted. The FM
+ * sound channels of the chip are not emulated.
+ */
+pub struct Vrc7 {
+    memory: CartridgeMemory,
+
+    prg_banks: Cell<[u8; 3]>,
+    chr_banks: Cell<[u8; 8]>,
+    /* $E000, selecting the mirroring and enabling work RAM */
+    control: Cell<u8>,
+    irq: Cell<VrcIrq>,
+}
+
+impl Vrc7 {
+    /* Initializes a new VRC7 board */
+    pub fn new(memory: CartridgeMemory) -> Self {
+        Self {
+            memory,
+            prg_banks: Cell::new([0; 3]),
+            chr_banks: Cell::new([0; 8]),
+            control: Cell::new(0),
+            irq: Cell::new(VrcIrq::new()),
+        }
+    }
+
+    /* Turns a CPU address into its $x000 or $x010 register */
+    fn get_register(&self, address: u16) -> u16 {
+        let is_second = address & 0x0018 != 0;
+
+        (address & 0xF000) | ((is_second as u16) << 4)
+    }
+
+    /* Work RAM is enabled by bit 7 of $E000 */
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.control.get() & 0x80 != 0
+    }
+
+    /* 8KB PRG bank mapped at a CPU address between $8000 and $FFFF */
+    fn get_prg_bank(&self, address: u16) -> usize {
+        match address {
+            0x8000..=0xDFFF => {
+                self.prg_banks.get()[(address as usize - 0x8000) / PRG_BANK_SIZE] as usize
+            }
+            _ => self.memory.last_prg_bank(PRG_BANK_SIZE),
+        }
+    }
+
+    /* 1KB CHR bank mapped at a PPU address between $0000 and $1FFF */
+    fn get_chr_bank(&self, address: u16) -> usize {
+        self.chr_banks.get()[address as usize / CHR_BANK_SIZE] as usize
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_read(&self, address: u16) -> u8 {
+        match address {
+            PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI if self.is_prg_ram_enabled() => {
+                self.memory.read_prg_ram(address)
+            }
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg(PRG_BANK_SIZE, self.get_prg_bank(address), address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&self, address: u16, value: u8) {
+        if let PRG_RAM_ADDRESS_LO..=PRG_RAM_ADDRESS_HI = address {
+            if self.is_prg_ram_enabled() {
+                self.memory.write_prg_ram(address, value);
+            }
+            return;
+        }
+
+        let register = self.get_register(address);
+        let mut prg_banks = self.prg_banks.get();
+        let mut chr_banks = self.chr_banks.get();
+        let mut irq = self.irq.get();
+
+        match register {
+            0x8000 => prg_banks[0] = value & 0x3F,
+            0x8010 => prg_banks[1] = value & 0x3F,
+            0x9000 => prg_banks[2] = value & 0x3F,
+            0xA000..=0xD010 => {
+                let index = (((register >> 12) - 0x0A) * 2 + (register >> 4 & 0x01)) as usize;
+                chr_banks[index] = value;
+            }
+            0xE000 => self.control.set(value),
+            0xE010 => irq.set_latch(value),
+            0xF000 => irq.write_control(value),
+            0xF010 => irq.acknowledge(),
+            _ => {}
+        }
+
+        self.prg_banks.set(prg_banks);
+        self.chr_banks.set(chr_banks);
+        self.irq.set(irq);
+    }
+
+    fn ppu_read(&self, address: u16) -> u8 {
+        self.memory
+            .read_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address)
+    }
+
+    fn ppu_write(&self, address: u16, value: u8) {
+        self.memory
+            .write_chr(CHR_BANK_SIZE, self.get_chr_bank(address), address, value);
+    }
+
+    fn memory(&self) -> &CartridgeMemory {
+        &self.memory
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        self.memory.save_state(state);
+        state.write_bytes(&self.prg_banks.get());
+        state.write_bytes(&self.chr_banks.get());
+        state.write_u8(self.control.get());
+        self.irq.get().save_state(state);
+    }
+
+    fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        self.memory.load_state(state)?;
+        self.prg_banks.set(state.read_array()?);
+        self.chr_banks.set(state.read_array()?);
+        self.control.set(state.read_u8()?);
+        let mut irq = VrcIrq::new();
+        irq.load_state(state)?;
+        self.irq.set(irq);
+
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control.get() & 0x03 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn clock(&self) {
+        let mut irq = self.irq.get();
+        irq.clock();
+        self.irq.set(irq);
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.get().is_pending()
+    }
+
+    fn expansion_chips(&self) -> ExpansionChips {
+        ExpansionChips::VRC7
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
+
+/* CPU cycles per scanline, times 3 to stay in whole numbers */
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+/*
+ * The IRQ counter shared by Konami's VRC4, VRC6 and VRC7. An
+ * 8 bit counter is increased either every CPU cycle or once
+ * per scanline, timed by a prescaler as the chip cannot see
+ * the PPU. Reaching $FF reloads it from the latch and raises
+ * the interrupt.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+
+    is_enabled: bool,
+    /* Value the enabled flag takes back once the interrupt is acknowledged */
+    is_enabled_after_ack: bool,
+    is_cycle_mode: bool,
+    is_pending: bool,
+}
+
+impl VrcIrq {
+    /* Initializes a new disabled IRQ counter */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Saves the counter along with its prescaler and flags */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.latch);
+        state.write_u8(self.counter);
+        state.write_u16(self.prescaler as u16);
+        state.write_bool(self.is_enabled);
+        state.write_bool(self.is_enabled_after_ack);
+        state.write_bool(self.is_cycle_mode);
+        state.write_bool(self.is_pending);
+    }
+
+    /* Restores a counter saved by save_state */
+    pub fn load_state(&mut self, state: &mut StateReader) -> AppResult<()> {
+        self.latch = state.read_u8()?;
+        self.counter = state.read_u8()?;
+        self.prescaler = state.read_u16()? as i16;
+        self.is_enabled = state.read_bool()?;
+        self.is_enabled_after_ack = state.read_bool()?;
+        self.is_cycle_mode = state.read_bool()?;
+        self.is_pending = state.read_bool()?;
+
+        Ok(())
+    }
+
+    /* Value the counter is reloaded with */
+    pub fn latch(&self) -> u8 {
+        self.latch
+    }
+
+    /* Changes the value the counter is reloaded with */
+    pub fn set_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /* Writes the control register, reloading the counter when enabled */
+    pub fn write_control(&mut self, value: u8) {
+        self.is_enabled_after_ack = value & 0x01 != 0;
+        self.is_enabled = value & 0x02 != 0;
+        self.is_cycle_mode = value & 0x04 != 0;
+        self.is_pending = false;
+
+        if self.is_enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    /* Acknowledges the interrupt */
+    pub fn acknowledge(&mut self) {
+        self.is_pending = false;
+        self.is_enabled = self.is_enabled_after_ack;
+    }
+
+    /* Specifies whether the interrupt is raised */
+    pub fn is_pending(&self) -> bool {
+        self.is_pending
+    }
+
+    /* Advances the counter by a single CPU cycle */
+    pub fn clock(&mut self) {
+        if !self.is_enabled {
+            return;
+        }
+
+        if self.is_cycle_mode {
+            self.clock_counter();
+            return;
+        }
+
+        self.prescaler -= PRESCALER_STEP;
+
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_PERIOD;
+            self.clock_counter();
+        }
+    }
+
+    /* Increases the counter, reloading it and raising the interrupt on overflow */
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.is_pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
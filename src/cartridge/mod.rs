@@ -1,2 +1,10 @@
+pub mod archive;
+pub mod battery;
 pub mod cartridge;
+pub mod crc32;
+pub mod game_database;
 pub mod mapper;
+pub mod mappers;
+pub mod rom_info;
+pub mod sha1;
+pub mod unif;
@@ -0,0 +1,108 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        crc32::crc32,
+        sha1::{SHA1_SIZE, sha1},
+    },
+    console::region::Region,
+    input::ports::InputDevice,
+};
+
+/* Header format a ROM was dumped with */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RomFormat {
+    #[default]
+    INes,
+    Nes20,
+    Unif,
+}
+
+/* Kind of system the game was made for */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleType {
+    #[default]
+    Nes,
+    VsSystem,
+    PlayChoice10,
+    /* Famiclones and other systems told apart by the extended console type */
+    Extended,
+}
+
+/* Checksums identifying a piece of a ROM */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RomHash {
+    pub crc32: u32,
+    pub sha1: [u8; SHA1_SIZE],
+}
+
+impl RomHash {
+    /* Computes the checksums of the given data */
+    pub fn new(bytes: &[u8]) -> Self {
+        Self {
+            crc32: crc32(bytes),
+            sha1: sha1(bytes),
+        }
+    }
+
+    /* SHA-1 digest written in hexadecimal, the way databases list it */
+    pub fn sha1_hex(&self) -> String {
+        self.sha1
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect()
+    }
+}
+
+/* Header fields the game database had to correct */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderCorrection {
+    Mapper,
+    Submapper,
+    Mirroring,
+    PrgRamSize,
+    Battery,
+    Region,
+    InputDevice,
+}
+
+/*
+ * Everything known about a ROM from its header. iNES files
+ * only tell the sizes of the ROMs and the mapper, the rest
+ * being filled with the usual defaults, while NES 2.0 ones
+ * describe the board precisely. Bad iNES headers of known
+ * games are corrected by the game database.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RomInfo {
+    pub format: RomFormat,
+    pub mapper_id: u16,
+    /* Variant of the mapper, always 0 for iNES files */
+    pub submapper: u8,
+
+    /* Sizes in bytes of the memories found on the board */
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+
+    pub mirroring: Mirroring,
+    pub is_battery_backed: bool,
+    pub has_trainer: bool,
+
+    pub console_type: ConsoleType,
+    /* Timing the game expects, multi-region games running as NTSC */
+    pub region: Region,
+    /* What the game expects to be plugged into the controller ports */
+    pub input_device: InputDevice,
+
+    /* CRC32 of the PRG ROM followed by the CHR ROM, identifying the game */
+    pub crc: u32,
+    /* Checksums of the whole file, header included, and of each ROM */
+    pub file_hash: RomHash,
+    pub prg_hash: RomHash,
+    pub chr_hash: RomHash,
+    /* Header fields overridden by the game database */
+    pub corrections: Vec<HeaderCorrection>,
+}
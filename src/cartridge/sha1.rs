@@ -0,0 +1,79 @@
+/* Size of a SHA-1 digest in bytes */
+pub const SHA1_SIZE: usize = 20;
+
+/* Size of the blocks SHA-1 processes at once */
+const BLOCK_SIZE: usize = 64;
+
+/* Computes the SHA-1 digest of the given data */
+pub fn sha1(bytes: &[u8]) -> [u8; SHA1_SIZE] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    /* The data is padded with a set bit, zeros and its length in bits */
+    let mut data = bytes.to_vec();
+    data.push(0x80);
+
+    while data.len() % BLOCK_SIZE != BLOCK_SIZE - 8 {
+        data.push(0);
+    }
+    data.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+    for block in data.chunks_exact(BLOCK_SIZE) {
+        process_block(&mut state, block);
+    }
+
+    let mut digest = [0; SHA1_SIZE];
+
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+
+    digest
+}
+
+/* Mixes a 64 bytes block into the state */
+fn process_block(state: &mut [u32; 5], block: &[u8]) {
+    let mut words = [0u32; 80];
+
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+
+    for index in 16..80 {
+        words[index] =
+            (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                .rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+    for (index, word) in words.iter().enumerate() {
+        let (f, k) = match index {
+            0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+            _ => (b ^ c ^ d, 0xCA62_C1D6),
+        };
+
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+        *value = value.wrapping_add(added);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    cartridge::{
+        cartridge::Mirroring,
+        mapper::{CHR_BANK_SIZE, PRG_RAM_SIZE},
+        rom_info::{RomFormat, RomInfo},
+    },
+    console::region::Region,
+    errors::{AppError, AppResult},
+};
+
+/* Size of the UNIF header, the chunks follow right after it */
+pub const UNIF_HEADER_SIZE: usize = 32;
+pub const UNIF_MAGIC: &[u8] = b"UNIF";
+
+/* Size of the id and length preceding the data of every chunk */
+const CHUNK_HEADER_SIZE: usize = 8;
+
+/* Prefixes telling who made a board, stripped before looking its name up */
+const BOARD_PREFIXES: [&str; 6] = ["NES-", "HVC-", "UNL-", "BTL-", "BMC-", "IREM-"];
+
+/*
+ * Represents a UNIF file. Instead of a mapper number, the
+ * board is named, and the ROMs are stored in chunks which
+ * are put back together in order.
+ */
+pub struct Unif {
+    board: String,
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+    mirroring: Option<Mirroring>,
+    is_battery_backed: bool,
+    region: Region,
+}
+
+impl Unif {
+    /* Parses the content of a UNIF file */
+    pub fn new(bytes: &[u8]) -> AppResult<Self> {
+        if bytes.len() < UNIF_HEADER_SIZE || !bytes.starts_with(UNIF_MAGIC) {
+            return Err(AppError::InvalidUnifFile);
+        }
+
+        let mut board = None;
+        let mut prg_chunks: [&[u8]; 16] = [&[]; 16];
+        let mut chr_chunks: [&[u8]; 16] = [&[]; 16];
+        let mut mirroring = None;
+        let mut is_battery_backed = false;
+        let mut region = Region::Ntsc;
+
+        let mut offset = UNIF_HEADER_SIZE;
+
+        while offset + CHUNK_HEADER_SIZE <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let length = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+            offset += CHUNK_HEADER_SIZE;
+
+            let data = bytes
+                .get(offset..offset + length as usize)
+                .ok_or(AppError::InvalidUnifFile)?;
+            offset += length as usize;
+
+            match id {
+                b"MAPR" => board = Some(Self::get_text(data)),
+                b"MIRR" => mirroring = data.first().and_then(|&mode| Self::get_mirroring(mode)),
+                b"BATR" => is_battery_backed = data.first().is_some_and(|&value| value != 0),
+                b"TVCI" if data.first() == Some(&1) => region = Region::Pal,
+                [b'P', b'R', b'G', index] => {
+                    prg_chunks[Self::get_chunk_index(*index)?] = data;
+                }
+                [b'C', b'H', b'R', index] => {
+                    chr_chunks[Self::get_chunk_index(*index)?] = data;
+                }
+                _ => {}
+            }
+        }
+
+        let board = board.ok_or(AppError::InvalidUnifFile)?;
+        let prg_rom = prg_chunks.concat();
+
+        if prg_rom.is_empty() {
+            return Err(AppError::InvalidUnifFile);
+        }
+
+        Ok(Self {
+            board,
+            prg_rom,
+            chr_rom: chr_chunks.concat(),
+            mirroring,
+            is_battery_backed,
+            region,
+        })
+    }
+
+    /* Name of the board, as found in the file */
+    pub fn board(&self) -> &str {
+        &self.board
+    }
+
+    /* PRG ROM put back together from its chunks */
+    pub fn prg_rom(&self) -> &[u8] {
+        &self.prg_rom
+    }
+
+    /* CHR ROM put back together from its chunks, empty on CHR RAM boards */
+    pub fn chr_rom(&self) -> &[u8] {
+        &self.chr_rom
+    }
+
+    /*
+     * Describes the ROM the way an iNES header would, the board
+     * being turned into the mapper emulating it
+     */
+    pub fn get_rom_info(&self) -> AppResult<RomInfo> {
+        let (mapper_id, submapper, mirroring) = self
+            .get_mapper()
+            .ok_or_else(|| AppError::UnsupportedUnifBoard(self.board.clone()))?;
+
+        Ok(RomInfo {
+            format: RomFormat::Unif,
+            mapper_id,
+            submapper,
+            prg_rom_size: self.prg_rom.len(),
+            chr_rom_size: self.chr_rom.len(),
+            prg_ram_size: PRG_RAM_SIZE,
+            chr_ram_size: if self.chr_rom.is_empty() {
+                CHR_BANK_SIZE
+            } else {
+                0
+            },
+            mirroring: self.mirroring.or(mirroring).unwrap_or_default(),
+            is_battery_backed: self.is_battery_backed,
+            region: self.region,
+            ..RomInfo::default()
+        })
+    }
+
+    /*
+     * Mapper and submapper emulating the board, along with the
+     * mirroring of boards where it is hard-wired. Submappers
+     * follow NES 2.0, telling which discrete boards have bus
+     * conflicts.
+     */
+    fn get_mapper(&self) -> Option<(u16, u8, Option<Mirroring>)> {
+        let board = BOARD_PREFIXES
+            .iter()
+            .find_map(|prefix| self.board.strip_prefix(prefix))
+            .unwrap_or(&self.board);
+
+        let mapper = match board {
+            "NROM" | "NROM-128" | "NROM-256" | "RROM" | "RROM-128" => (0, 0, None),
+            "SAROM" | "SBROM" | "SCROM" | "SEROM" | "SFROM" | "SGROM" | "SHROM" | "SJROM"
+            | "SKROM" | "SLROM" | "SL1ROM" | "SNROM" | "SOROM" | "SUROM" | "SXROM" => (1, 0, None),
+            "UNROM" | "UOROM" => (2, 2, None),
+            "CNROM" => (3, 2, None),
+            "EKROM" | "ELROM" | "ETROM" | "EWROM" => (5, 0, None),
+            "AMROM" => (7, 2, Some(Mirroring::SingleScreenLower)),
+            "ANROM" | "AN1ROM" | "AOROM" => (7, 1, Some(Mirroring::SingleScreenLower)),
+            "PNROM" | "PEEOROM" => (9, 0, None),
+            "DEROM" | "DE1ROM" | "DRROM" => (206, 0, None),
+            _ => return None,
+        };
+
+        Some(mapper)
+    }
+
+    /* Index of a PRG or CHR chunk, given as a hexadecimal digit */
+    fn get_chunk_index(digit: u8) -> AppResult<usize> {
+        (digit as char)
+            .to_digit(16)
+            .map(|index| index as usize)
+            .ok_or(AppError::InvalidUnifFile)
+    }
+
+    /* Mirroring of a MIRR chunk, mapper controlled mirroring being left to the board */
+    fn get_mirroring(mode: u8) -> Option<Mirroring> {
+        match mode {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::SingleScreenLower),
+            3 => Some(Mirroring::SingleScreenUpper),
+            4 => Some(Mirroring::FourScreen),
+            _ => None,
+        }
+    }
+
+    /* Converts a null terminated string */
+    fn get_text(bytes: &[u8]) -> String {
+        let length = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+
+        String::from_utf8_lossy(&bytes[..length]).into_owned()
+    }
+}
@@ -0,0 +1,194 @@
+use crate::errors::{AppError, AppResult};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/*
+ * A raw cheat, making reads of a CPU address return a fixed
+ * value. With a compare value, the cheat only applies while
+ * the actual value matches it, which lets cheats target a
+ * single bank of switchable PRG ROM.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+    pub is_enabled: bool,
+}
+
+impl Cheat {
+    /* Initializes a new enabled cheat */
+    pub fn new(name: &str, address: u16, value: u8, compare: Option<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            address,
+            value,
+            compare,
+            is_enabled: true,
+        }
+    }
+
+    /*
+     * Parses a line of a cheat file, such as "+ 0075:09 Lives"
+     * or "- 8123:A5:C9 Invincible". The sign tells whether the
+     * cheat is enabled, followed by the address, the value and
+     * the optional compare value in hexadecimal, then the name.
+     */
+    fn from_line(line: &str) -> Option<Self> {
+        let (is_enabled, line) = match line.split_at_checked(1)? {
+            ("+", line) => (true, line),
+            ("-", line) => (false, line),
+            _ => return None,
+        };
+
+        let line = line.trim_start();
+        let (code, name) = line.split_once(' ').unwrap_or((line, ""));
+        let mut fields = code.split(':');
+
+        let address = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let value = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let compare = match fields.next() {
+            Some(compare) => Some(u8::from_str_radix(compare, 16).ok()?),
+            None => None,
+        };
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            name: name.trim().to_string(),
+            address,
+            value,
+            compare,
+            is_enabled,
+        })
+    }
+
+    /* Formats the cheat as a line of a cheat file */
+    fn to_line(&self) -> String {
+        let sign = if self.is_enabled { '+' } else { '-' };
+
+        match self.compare {
+            Some(compare) => format!(
+                "{} {:04X}:{:02X}:{:02X} {}",
+                sign, self.address, self.value, compare, self.name
+            ),
+            None => format!(
+                "{} {:04X}:{:02X} {}",
+                sign, self.address, self.value, self.name
+            ),
+        }
+    }
+}
+
+/*
+ * The cheats of a game, applied to every CPU read. They are
+ * kept in a .cht file next to the ROM, one cheat per line.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct CheatList {
+    cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    /* Initializes a new empty list */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Parses the content of a cheat file, lines starting with # being ignored */
+    pub fn from_text(text: &str) -> AppResult<Self> {
+        let mut cheats = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            cheats.push(Cheat::from_line(line).ok_or(AppError::InvalidCheat(index + 1))?);
+        }
+
+        Ok(Self { cheats })
+    }
+
+    /* Loads a cheat file from disk */
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let text = fs::read_to_string(path)?;
+
+        Self::from_text(&text)
+    }
+
+    /* Cheat file path of a ROM, sitting next to it with the .cht extension */
+    pub fn path_for_rom<P: AsRef<Path>>(rom_path: P) -> PathBuf {
+        rom_path.as_ref().with_extension("cht")
+    }
+
+    /* Formats the list as the content of a cheat file */
+    pub fn to_text(&self) -> String {
+        self.cheats
+            .iter()
+            .map(|cheat| cheat.to_line() + "\n")
+            .collect()
+    }
+
+    /* Writes the list to a cheat file */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> AppResult<()> {
+        fs::write(path, self.to_text())?;
+
+        Ok(())
+    }
+
+    /* Adds a cheat at the end of the list */
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    /* Removes the cheat with the given name, if there is one */
+    pub fn remove(&mut self, name: &str) -> Option<Cheat> {
+        let index = self.cheats.iter().position(|cheat| cheat.name == name)?;
+
+        Some(self.cheats.remove(index))
+    }
+
+    /* Enables or disables the cheat with the given name, returning whether it exists */
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.cheats.iter_mut().find(|cheat| cheat.name == name) {
+            Some(cheat) => {
+                cheat.is_enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /* Flips the cheat with the given name on or off, returning whether it exists */
+    pub fn toggle(&mut self, name: &str) -> bool {
+        match self.cheats.iter_mut().find(|cheat| cheat.name == name) {
+            Some(cheat) => {
+                cheat.is_enabled = !cheat.is_enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /* The cheats of the list, in order */
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /* Value a CPU read of the address returns once the enabled cheats are applied */
+    pub fn apply(&self, address: u16, value: u8) -> u8 {
+        self.cheats
+            .iter()
+            .filter(|cheat| cheat.is_enabled && cheat.address == address)
+            .find(|cheat| cheat.compare.is_none_or(|compare| compare == value))
+            .map_or(value, |cheat| cheat.value)
+    }
+}
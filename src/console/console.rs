@@ -0,0 +1,708 @@
+use crate::{
+    apu::{
+        apu::APU,
+        mixer::Channel,
+        recorder::{RecordingMode, WavRecorder},
+        sample_buffer::SampleBuffer,
+    },
+    bus::{cpu_bus::CpuBus, ppu_bus::PpuBus},
+    cartridge::{
+        battery::{BatterySave, SAVE_INTERVAL_FRAMES},
+        cartridge::Cartridge,
+    },
+    cheats::cheats::CheatList,
+    console::region::Region,
+    cpu::cpu::CPU,
+    errors::{AppError, AppResult},
+    input::{
+        controller::{Buttons, Controller},
+        keyboard::FamilyKeyboard,
+        overlay::draw_input_overlay,
+        ports::{CONTROLLER_COUNT, InputDevice, InputPorts},
+        provider::{InputProvider, Port},
+        zapper::Zapper,
+    },
+    memory::memory::Memory,
+    ppu::{
+        frame::{Frame, ProcessedFrame},
+        ntsc::VideoFilter,
+        palette::Palette,
+        ppu::{PPU, Renderer},
+    },
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    rc::Rc,
+};
+
+/*
+ * Represents the whole console, wiring the CPU, the PPU and
+ * the cartridge together and driving them with a shared
+ * master clock.
+ */
+pub struct Console {
+    cpu: CPU,
+    region: Region,
+    cycles: u64,
+
+    /*
+     * Accumulates CPU cycle fractions on every PPU dot, as the
+     * clock ratio between them is not an integer on PAL consoles
+     */
+    cpu_clock_counter: u64,
+
+    /* APU output resampled for the audio device */
+    audio: SampleBuffer,
+    /* Frame the audio was last flushed on */
+    audio_frame: u64,
+    /* Recording of the APU output in progress, if any */
+    recorder: Option<WavRecorder>,
+    /* File keeping the battery backed PRG RAM, if the game has one */
+    battery_save: Option<BatterySave>,
+    /* Error of the last periodic write of the .sav file, until taken */
+    save_error: Option<AppError>,
+    /* Whether the buttons held down are drawn over the output frames */
+    is_input_overlay_enabled: bool,
+    /* Buttons queued for the controllers, by the frame they are held from */
+    input_queue: BTreeMap<u64, [Option<Buttons>; CONTROLLER_COUNT]>,
+    /* Whether step_frame leaves the console frozen */
+    is_paused: bool,
+    /* Number of frames the next calls to step_frame run while paused */
+    pending_frame_advances: u32,
+}
+
+impl Console {
+    /*
+     * Initializes a new Console with the given cartridge inserted,
+     * running at the timing its header asks for
+     */
+    pub fn new(cartridge: Cartridge) -> Self {
+        let region = cartridge.info().region;
+        let input_device = cartridge.info().input_device;
+        let cartridge = Rc::new(cartridge);
+        let ram = Memory::new(2048);
+
+        let ppu_bus = PpuBus::new(cartridge.clone());
+        let ppu = PPU::new(ppu_bus);
+
+        let mut apu = APU::new();
+        apu.set_expansion_chips(cartridge.expansion_chips());
+
+        let cpu_bus = CpuBus::new(ram, ppu, apu, cartridge);
+        let cpu = CPU::new(cpu_bus);
+
+        let mut console = Self {
+            cpu,
+            region: Region::Ntsc,
+            cycles: 0,
+            cpu_clock_counter: 0,
+            audio: SampleBuffer::new(Region::Ntsc.cpu_clock_rate()),
+            audio_frame: 0,
+            recorder: None,
+            battery_save: None,
+            save_error: None,
+            is_input_overlay_enabled: false,
+            input_queue: BTreeMap::new(),
+            is_paused: false,
+            pending_frame_advances: 0,
+        };
+        console.set_region(region);
+        console.set_input_device(input_device);
+
+        console
+    }
+
+    /* Switches the console to the timing of another region */
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu_clock_counter = 0;
+        self.cpu.bus_mut().ppu_mut().set_region(region);
+        self.cpu.bus_mut().apu_mut().set_region(region);
+        self.audio.set_input_rate(region.cpu_clock_rate());
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.set_input_rate(region.cpu_clock_rate());
+        }
+    }
+
+    /* The region the console is emulating */
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /*
+     * Advances the console by a single PPU dot. The CPU runs
+     * three (or 3.2 on PAL) times slower than the PPU, so it
+     * is only clocked once enough dots have elapsed.
+     */
+    pub fn clock(&mut self) -> AppResult<()> {
+        self.cpu.bus_mut().ppu_mut().clock();
+
+        let (dots, cycles) = self.region.dots_per_cpu_cycle();
+
+        if self.cpu_clock_counter < cycles {
+            self.cpu.clock()?;
+            self.cpu.bus_mut().clock_apu();
+            self.cpu.bus().cartridge().clock();
+            let apu = self.cpu.bus().apu();
+
+            if apu.mixer().is_stereo() {
+                let (left, right) = apu.stereo_sample();
+                self.audio.push_stereo(left, right);
+            } else {
+                self.audio.push(apu.sample());
+            }
+
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.push(self.cpu.bus().apu());
+            }
+            self.cpu_clock_counter += dots;
+        }
+
+        self.cpu_clock_counter -= cycles;
+
+        if self.cpu.bus_mut().ppu_mut().poll_nmi() {
+            self.cpu.nmi();
+        }
+
+        /* Audio callbacks receive a batch of samples every frame */
+        let frame = self.cpu.bus().ppu().frame_number();
+
+        if frame != self.audio_frame {
+            self.audio_frame = frame;
+            self.audio.flush();
+
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.flush()?;
+            }
+        }
+
+        self.cycles += 1;
+        Ok(())
+    }
+
+    /* Presses the reset button, restarting the CPU, the PPU and the APU */
+    pub fn reset(&mut self) {
+        self.cpu.bus_mut().ppu_mut().reset();
+        self.cpu.bus_mut().apu_mut().reset();
+        self.cpu.reset();
+        self.cpu_clock_counter = 0;
+    }
+
+    /* Runs the console until the PPU has produced a whole frame, unless it is paused */
+    pub fn step_frame(&mut self) -> AppResult<()> {
+        if self.is_paused {
+            if self.pending_frame_advances == 0 {
+                return Ok(());
+            }
+
+            self.pending_frame_advances -= 1;
+        }
+
+        self.apply_queued_input();
+
+        while !self.cpu.bus_mut().ppu_mut().take_frame_ready() {
+            self.clock()?;
+        }
+
+        /* A failed write is retried on the next interval, without stopping the emulation */
+        if self.frame_number().is_multiple_of(SAVE_INTERVAL_FRAMES)
+            && let Err(err) = self.flush_battery_save()
+        {
+            self.save_error = Some(err);
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Pauses or resumes the console. While paused, stepping a
+     * frame does nothing: the last frame stays on screen and no
+     * audio is produced, while the controllers can still be set.
+     */
+    pub fn set_paused(&mut self, paused: bool) {
+        self.is_paused = paused;
+        self.pending_frame_advances = 0;
+    }
+
+    /* Specifies whether the console is paused */
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /*
+     * Pauses the console and lets step_frame run a single frame
+     * more, with the buttons set on the controllers by then.
+     * Used to go through a game one frame at a time.
+     */
+    pub fn advance_frame(&mut self) {
+        self.is_paused = true;
+        self.pending_frame_advances += 1;
+    }
+
+    /*
+     * Keeps the PRG RAM of a battery backed game in the given
+     * .sav file, loading it right away. Games without a battery
+     * are left alone.
+     */
+    pub fn set_battery_save_path<P: AsRef<Path>>(&mut self, path: P) -> AppResult<()> {
+        let cartridge = self.cpu.bus().cartridge();
+
+        if cartridge.is_battery_backed() {
+            self.battery_save = Some(BatterySave::open(path, cartridge)?);
+        }
+
+        Ok(())
+    }
+
+    /* Writes the PRG RAM to the .sav file if it changed, to be called before exiting */
+    pub fn flush_battery_save(&mut self) -> AppResult<()> {
+        match self.battery_save.as_mut() {
+            Some(save) => save.flush(self.cpu.bus().cartridge()),
+            None => Ok(()),
+        }
+    }
+
+    /*
+     * Takes the error of the last periodic write of the .sav
+     * file made while stepping frames, if it failed since the
+     * last call
+     */
+    pub fn take_save_error(&mut self) -> Option<AppError> {
+        self.save_error.take()
+    }
+
+    /*
+     * Enables or lifts the limit of 8 sprites per scanline,
+     * it is enforced by default for accuracy
+     */
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.cpu.bus_mut().ppu_mut().set_sprite_limit(enabled);
+    }
+
+    /*
+     * Enables or disables the PPU warm-up period after power and
+     * reset, it is emulated by default
+     */
+    pub fn set_warm_up(&mut self, enabled: bool) {
+        self.cpu.bus_mut().ppu_mut().set_warm_up(enabled);
+    }
+
+    /*
+     * Enables or disables the decay of OAM left without refresh
+     * while rendering is disabled, it is not emulated by default
+     */
+    pub fn set_oam_decay(&mut self, enabled: bool) {
+        self.cpu.bus_mut().ppu_mut().set_oam_decay(enabled);
+    }
+
+    /*
+     * Switches between the dot accurate renderer and the faster
+     * scanline based one. Cartridges whose mapper follows the
+     * fetches of the PPU dot by dot keep the dot renderer, so
+     * the renderer actually selected is returned.
+     */
+    pub fn set_renderer(&mut self, renderer: Renderer) -> Renderer {
+        let renderer = if self.cpu.bus().cartridge().needs_dot_renderer() {
+            Renderer::Dot
+        } else {
+            renderer
+        };
+
+        self.cpu.bus_mut().ppu_mut().set_renderer(renderer);
+        renderer
+    }
+
+    /*
+     * Reads the PPU address space (pattern tables, nametables
+     * and palette) without the side effects of PPUDATA
+     */
+    pub fn ppu_peek(&self, address: u16) -> u8 {
+        self.cpu.bus().ppu().peek(address)
+    }
+
+    /* Writes the PPU address space without the side effects of PPUDATA */
+    pub fn ppu_poke(&mut self, address: u16, value: u8) {
+        self.cpu.bus_mut().ppu_mut().poke(address, value);
+    }
+
+    /* Changes the rate audio samples are produced at, 44100 Hz by default */
+    pub fn set_sample_rate(&mut self, rate: u32) {
+        self.audio.set_output_rate(rate);
+    }
+
+    /*
+     * Enables or disables the high-pass and low-pass filters of
+     * the console audio output, they are applied by default
+     */
+    pub fn set_audio_filters(&mut self, enabled: bool) {
+        self.audio.set_filters_enabled(enabled);
+    }
+
+    /*
+     * Nudges the rate audio samples are produced at by the given
+     * ratio, to follow the actual consumption of the audio device
+     */
+    pub fn set_sample_rate_adjustment(&mut self, adjustment: f64) {
+        self.audio.set_rate_adjustment(adjustment);
+    }
+
+    /* Moves the audio samples produced so far to the end of the given vector */
+    pub fn take_samples(&mut self, samples: &mut Vec<i16>) {
+        self.audio.take_samples(samples);
+    }
+
+    /*
+     * Registers a callback receiving the audio samples produced
+     * during every frame, instead of polling them
+     */
+    pub fn on_samples<F: FnMut(&[i16]) + 'static>(&mut self, callback: F) {
+        self.audio.on_samples(callback);
+    }
+
+    /* Sets the volume of an audio channel, 1.0 being its original level */
+    pub fn set_channel_volume(&mut self, channel: Channel, volume: f32) {
+        self.cpu
+            .bus_mut()
+            .apu_mut()
+            .mixer_mut()
+            .set_volume(channel, volume);
+    }
+
+    /* Mutes or unmutes an audio channel */
+    pub fn set_channel_muted(&mut self, channel: Channel, muted: bool) {
+        self.cpu
+            .bus_mut()
+            .apu_mut()
+            .mixer_mut()
+            .set_muted(channel, muted);
+    }
+
+    /* Only lets the given audio channel through, or all of them again with None */
+    pub fn set_solo_channel(&mut self, channel: Option<Channel>) {
+        self.cpu.bus_mut().apu_mut().mixer_mut().set_solo(channel);
+    }
+
+    /*
+     * Switches the audio output between mono and stereo, the
+     * latter producing interleaved left and right samples
+     */
+    pub fn set_stereo(&mut self, enabled: bool) {
+        self.cpu.bus_mut().apu_mut().mixer_mut().set_stereo(enabled);
+        self.audio.set_stereo(enabled);
+    }
+
+    /* Number of interleaved channels in the audio samples */
+    pub fn audio_channel_count(&self) -> usize {
+        self.audio.channel_count()
+    }
+
+    /* Sets the position of an audio channel, from -1.0 (left) to 1.0 (right) */
+    pub fn set_channel_pan(&mut self, channel: Channel, pan: f32) {
+        self.cpu
+            .bus_mut()
+            .apu_mut()
+            .mixer_mut()
+            .set_pan(channel, pan);
+    }
+
+    /* Pans the audio channels apart in the classic fake stereo way */
+    pub fn set_fake_stereo_pans(&mut self) {
+        self.cpu
+            .bus_mut()
+            .apu_mut()
+            .mixer_mut()
+            .set_fake_stereo_pans();
+    }
+
+    /*
+     * Starts or stops keeping the recent output of each audio
+     * channel, for oscilloscope views
+     */
+    pub fn set_waveform_capture(&mut self, enabled: bool) {
+        self.cpu
+            .bus_mut()
+            .apu_mut()
+            .waveforms_mut()
+            .set_enabled(enabled);
+    }
+
+    /* The recent output of an audio channel, between 0.0 and 1.0, oldest first */
+    pub fn waveform(&self, channel: Channel) -> &VecDeque<f32> {
+        self.cpu.bus().apu().waveforms().channel(channel)
+    }
+
+    /*
+     * Starts writing the audio output to a WAV file, at the
+     * current sample rate. A recording in progress is stopped.
+     */
+    pub fn start_recording<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mode: RecordingMode,
+    ) -> AppResult<()> {
+        self.stop_recording()?;
+
+        self.recorder = Some(WavRecorder::create(
+            path,
+            mode,
+            self.region.cpu_clock_rate(),
+            self.audio.output_rate(),
+        )?);
+
+        Ok(())
+    }
+
+    /* Stops the recording in progress, completing its file */
+    pub fn stop_recording(&mut self) -> AppResult<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /* Specifies whether the audio output is being recorded */
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /* Gives access to the cheats applied to the CPU reads */
+    pub fn cheats(&self) -> &CheatList {
+        self.cpu.bus().cheats()
+    }
+
+    /* Gives mutable access to the cheats, to add or toggle them */
+    pub fn cheats_mut(&mut self) -> &mut CheatList {
+        self.cpu.bus_mut().cheats_mut()
+    }
+
+    /* Gives access to the devices plugged into the controller ports */
+    pub fn input(&self) -> &InputPorts {
+        self.cpu.bus().input()
+    }
+
+    /* Gives access to a controller, 0 and 1 being plugged into the ports */
+    pub fn controller(&self, index: usize) -> &Controller {
+        self.input().controller(index)
+    }
+
+    /* Gives mutable access to a controller, for the frontend to update its buttons */
+    pub fn controller_mut(&mut self, index: usize) -> &mut Controller {
+        self.cpu.bus_mut().input_mut().controller_mut(index)
+    }
+
+    /* Registers the source the controllers are polled from when the game latches them */
+    pub fn set_input_provider<P: InputProvider + 'static>(&mut self, provider: P) {
+        self.cpu.bus_mut().input_mut().set_provider(provider);
+    }
+
+    /* Removes the input provider, going back to setting the buttons directly */
+    pub fn clear_input_provider(&mut self) {
+        self.cpu.bus_mut().input_mut().clear_provider();
+    }
+
+    /*
+     * Queues the buttons a controller holds from a given frame
+     * on, frames being numbered like frame_number counts them.
+     * They are applied by step_frame before running the frame,
+     * so scripts, tests and movies drive the console the same
+     * way on every run. Once applied, they take priority over
+     * the input provider until the queue is cleared.
+     */
+    pub fn set_input(&mut self, frame: u64, port: Port, buttons: Buttons) {
+        self.input_queue.entry(frame).or_default()[port.index()] = Some(buttons);
+    }
+
+    /*
+     * Drops every queued button state not applied yet, handing
+     * the controllers back to the input provider
+     */
+    pub fn clear_input_queue(&mut self) {
+        self.input_queue.clear();
+        self.cpu.bus_mut().input_mut().clear_queued_buttons();
+    }
+
+    /* Sets the buttons queued for the frame about to run, and those left behind */
+    fn apply_queued_input(&mut self) {
+        let frame = self.frame_number();
+
+        while let Some(entry) = self.input_queue.first_entry()
+            && *entry.key() <= frame
+        {
+            for (index, buttons) in entry.remove().into_iter().enumerate() {
+                if let Some(buttons) = buttons {
+                    self.cpu
+                        .bus_mut()
+                        .input_mut()
+                        .set_queued_buttons(index, buttons);
+                }
+            }
+        }
+    }
+
+    /* Changes what is plugged into the ports, the game choosing it by default */
+    pub fn set_input_device(&mut self, device: InputDevice) {
+        self.cpu.bus_mut().input_mut().set_device(device);
+    }
+
+    /* Gives mutable access to the Zapper, for the frontend to aim it with the mouse */
+    pub fn zapper_mut(&mut self) -> &mut Zapper {
+        self.cpu.bus_mut().input_mut().zapper_mut()
+    }
+
+    /* Gives mutable access to the Family BASIC keyboard, for the frontend to pass keys through */
+    pub fn keyboard_mut(&mut self) -> &mut FamilyKeyboard {
+        self.cpu.bus_mut().input_mut().keyboard_mut()
+    }
+
+    /* Gives access to the CPU */
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    /* Gives mutable access to the CPU */
+    pub fn cpu_mut(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    /* Gives access to the APU, mainly for debugging purposes */
+    pub fn apu(&self) -> &APU {
+        self.cpu.bus().apu()
+    }
+
+    /* Gives access to the PPU, mainly for debugging purposes */
+    pub fn ppu(&self) -> &PPU {
+        self.cpu.bus().ppu()
+    }
+
+    /* The most recent picture produced by the PPU */
+    pub fn frame(&self) -> &Frame {
+        self.cpu.bus().ppu().frame()
+    }
+
+    /* The most recent picture converted to RGBA bytes */
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        self.cpu.bus().ppu().frame_rgba()
+    }
+
+    /*
+     * The most recent picture ready for display, after the
+     * post-processor or the selected video filter
+     */
+    pub fn output_frame(&mut self) -> ProcessedFrame {
+        let mut frame = self.cpu.bus_mut().ppu_mut().output_frame();
+
+        if self.is_input_overlay_enabled {
+            let input = self.input();
+            let controllers: Vec<Buttons> = (0..input.controller_count())
+                .map(|index| input.controller(index).buttons())
+                .collect();
+
+            draw_input_overlay(&mut frame, &controllers);
+        }
+
+        frame
+    }
+
+    /* Shows or hides the buttons held down on every controller over the output frames */
+    pub fn set_input_overlay(&mut self, enabled: bool) {
+        self.is_input_overlay_enabled = enabled;
+    }
+
+    /* Specifies whether the input overlay is shown */
+    pub fn is_input_overlay_enabled(&self) -> bool {
+        self.is_input_overlay_enabled
+    }
+
+    /*
+     * Registers a stage run on every frame before display, in
+     * place of the built-in conversion
+     */
+    pub fn set_post_processor<F: FnMut(&Frame) -> ProcessedFrame + 'static>(
+        &mut self,
+        processor: F,
+    ) {
+        self.cpu.bus_mut().ppu_mut().set_post_processor(processor);
+    }
+
+    /* Width and height of the pictures produced by frame_rgba */
+    pub fn output_size(&self) -> (usize, usize) {
+        self.cpu.bus().ppu().output_size()
+    }
+
+    /*
+     * Selects between the clean RGB output and the NTSC filter
+     * for the conversion done by frame_rgba
+     */
+    pub fn set_video_filter(&mut self, filter: VideoFilter) {
+        self.cpu.bus_mut().ppu_mut().set_video_filter(filter);
+    }
+
+    /* Replaces the palette used to convert frames to RGB */
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.cpu.bus_mut().ppu_mut().set_palette(palette);
+    }
+
+    /*
+     * Registers a callback fired every frame when the PPU
+     * enters the given scanline
+     */
+    pub fn on_scanline<F: FnMut(&PPU) + 'static>(&mut self, scanline: u16, callback: F) {
+        self.cpu.bus_mut().ppu_mut().on_scanline(scanline, callback);
+    }
+
+    /* Registers a callback fired with every frame once it is complete */
+    pub fn on_frame<F: FnMut(&Frame) + 'static>(&mut self, callback: F) {
+        self.cpu.bus_mut().ppu_mut().on_frame(callback);
+    }
+
+    /* Number of frames produced since power on */
+    pub fn frame_number(&self) -> u64 {
+        self.cpu.bus().ppu().frame_number()
+    }
+
+    /* Number of PPU dots the master clock has run for since power on */
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::mapper::{CHR_BANK_SIZE, PRG_BANK_SIZE};
+
+    /* Console running an NROM image with nothing but BRK in it */
+    fn get_console() -> Console {
+        let mut bytes = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        bytes.resize(16 + PRG_BANK_SIZE + CHR_BANK_SIZE, 0);
+
+        Console::new(Cartridge::new(&bytes).unwrap())
+    }
+
+    /* Strobes the controllers and reads the 8 buttons of the first one through $4016 */
+    fn get_latched_buttons(console: &mut Console) -> u8 {
+        let bus = console.cpu.bus_mut();
+        bus.write(0x4016, 1);
+        bus.write(0x4016, 0);
+
+        (0..8).fold(0, |buttons, bit| buttons | (bus.read(0x4016) & 0x01) << bit)
+    }
+
+    #[test]
+    fn queued_input_wins_over_the_provider_when_latched() {
+        let mut console = get_console();
+        console.set_input_provider(|_| Buttons::B);
+        console.set_input(0, Port::One, Buttons::A | Buttons::START);
+        console.step_frame().unwrap();
+
+        assert_eq!(
+            get_latched_buttons(&mut console),
+            (Buttons::A | Buttons::START).bits()
+        );
+
+        console.clear_input_queue();
+        assert_eq!(get_latched_buttons(&mut console), Buttons::B.bits());
+    }
+}
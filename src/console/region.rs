@@ -0,0 +1,82 @@
+/*
+ * The different console models, each with its own video
+ * timing, CPU clock and color generation.
+ *
+ * Dendy is a popular Famiclone combining a PAL-like frame
+ * with the NTSC CPU/PPU clock ratio.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl Region {
+    /* Number of scanlines in a single frame, pre-render scanline included */
+    pub fn scanlines_per_frame(&self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /* Scanline on which the vertical blank flag is raised */
+    pub fn vblank_start_scanline(&self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    /* Last scanline of the frame, used to prefetch the first visible one */
+    pub fn pre_render_scanline(&self) -> u16 {
+        self.scanlines_per_frame() - 1
+    }
+
+    /* Only the NTSC PPU skips a dot on odd frames while rendering */
+    pub fn skips_odd_frame_dot(&self) -> bool {
+        matches!(self, Region::Ntsc)
+    }
+
+    /* The PAL PPUs swap the meaning of the red and green emphasis bits */
+    pub fn swaps_emphasis_bits(&self) -> bool {
+        matches!(self, Region::Pal | Region::Dendy)
+    }
+
+    /*
+     * Ratio between the PPU and CPU clocks as a (dots, cycles)
+     * pair. A PAL CPU runs a single cycle every 3.2 dots.
+     */
+    pub fn dots_per_cpu_cycle(&self) -> (u64, u64) {
+        match self {
+            Region::Ntsc | Region::Dendy => (3, 1),
+            Region::Pal => (16, 5),
+        }
+    }
+
+    /* CPU clock rate in Hz */
+    pub fn cpu_clock_rate(&self) -> f64 {
+        match self {
+            Region::Ntsc => 1_789_773.0,
+            Region::Pal => 1_662_607.0,
+            Region::Dendy => 1_773_448.0,
+        }
+    }
+
+    /* PPU clock rate in Hz, the rate of the master clock of the console */
+    pub fn ppu_clock_rate(&self) -> f64 {
+        let (dots, cycles) = self.dots_per_cpu_cycle();
+        self.cpu_clock_rate() * dots as f64 / cycles as f64
+    }
+
+    /* Number of frames produced every second */
+    pub fn frame_rate(&self) -> f64 {
+        match self {
+            Region::Ntsc => 60.0988,
+            Region::Pal => 50.007,
+            Region::Dendy => 50.0,
+        }
+    }
+}
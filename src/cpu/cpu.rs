@@ -47,14 +47,14 @@ pub struct CPU {
 
     bus: CpuBus,
 
-    cycles: u8,
+    cycles: u16,
     absolute_address: u16,
     relative_address: i16,
 }
 
 impl CPU {
     /* Initialize a new CPU */
-    pub fn new(bus: CpuBus) -> Self {
+    pub fn new(mut bus: CpuBus) -> Self {
         let lo = bus.read(RESET_VECTOR_ADDRESS_LO) as u16;
         let hi = bus.read(RESET_VECTOR_ADDRESS_HI) as u16;
 
@@ -65,7 +65,7 @@ impl CPU {
             sp: STACK_POINTER_INITIAL_OFFSET,
             pc: (hi << 8) | lo,
             status: Status::UNUSED | Status::INTERRUPT,
-            bus: bus,
+            bus,
             cycles: 0,
             absolute_address: 0,
             relative_address: 0,
@@ -79,16 +79,21 @@ impl CPU {
      * of a real CPU.
      */
     pub fn clock(&mut self) -> AppResult<()> {
-        if self.cycles == 0 {
+        if self.cycles == 0 && self.is_irq_requested() {
+            self.irq();
+        } else if self.cycles == 0 {
             let byte = self.bus.read(self.pc);
             self.increment_pc();
 
             match Opcode::decode(byte) {
                 Some(opcode) => {
-                    self.cycles = opcode.cycles;
+                    self.cycles = opcode.cycles as u16;
 
                     self.execute_addressing_mode(opcode.addressing_mode);
                     self.execute_instruction(opcode.instruction, opcode.addressing_mode);
+
+                    /* Devices such as the OAM DMA may halt the CPU for a while */
+                    self.cycles += self.bus.take_stall_cycles();
                 }
                 None => return Err(AppError::InvalidOpcode),
             }
@@ -125,7 +130,7 @@ impl CPU {
      * address in the cartridge to handle the interrupt
      */
     pub fn irq(&mut self) {
-        if !self.get_status_flag(Status::INTERRUPT) {
+        if self.get_status_flag(Status::INTERRUPT) {
             return;
         }
 
@@ -134,7 +139,7 @@ impl CPU {
         self.write_to_stack((pc >> 8) as u8);
         self.write_to_stack(pc as u8);
 
-        let status = self.status;
+        let status = (self.status | Status::UNUSED) & !Status::BREAK;
 
         self.write_to_stack(status.bits());
 
@@ -163,11 +168,58 @@ impl CPU {
 
         self.set_status_flag(Status::INTERRUPT, true);
 
-        let lo = self.bus.read(NMI_VECTOR_ADDRESS_LO) as u8;
-        let hi = self.bus.read(NMI_VECTOR_ADDRESS_HI) as u8;
+        let lo = self.bus.read(NMI_VECTOR_ADDRESS_LO);
+        let hi = self.bus.read(NMI_VECTOR_ADDRESS_HI);
         self.pc = self.get_bytes_to_address(hi, lo);
 
-        self.cycles = 7;
+        /* Cycles still owed, such as those of an OAM DMA, are kept */
+        self.cycles += 7;
+    }
+
+    /*
+     * Calls a subroutine from outside of the running program,
+     * with the given A and X registers, as if a JSR was made
+     * from right before the return address
+     */
+    pub fn call_subroutine(&mut self, address: u16, return_address: u16, a: u8, x: u8) {
+        let return_address = return_address.wrapping_sub(1);
+
+        self.write_to_stack((return_address >> 8) as u8);
+        self.write_to_stack(return_address as u8);
+
+        self.a = a;
+        self.x = x;
+        self.pc = address;
+        self.cycles = 0;
+    }
+
+    /* Address of the next instruction to be executed */
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /* Specifies whether the current instruction is done, so the next one can start */
+    pub fn is_at_instruction_boundary(&self) -> bool {
+        self.cycles == 0
+    }
+
+    /* Gives access to the bus the CPU is connected to */
+    pub fn bus(&self) -> &CpuBus {
+        &self.bus
+    }
+
+    /* Gives mutable access to the bus the CPU is connected to */
+    pub fn bus_mut(&mut self) -> &mut CpuBus {
+        &mut self.bus
+    }
+
+    /*
+     * The IRQ line is level triggered and only looked at between
+     * instructions, an interrupt is taken as long as a device
+     * keeps asking for it and the INTERRUPT flag is clear
+     */
+    fn is_irq_requested(&self) -> bool {
+        self.bus.is_irq_pending() && !self.get_status_flag(Status::INTERRUPT)
     }
 
     /* Simply increments the program counter */
@@ -517,13 +569,13 @@ impl CPU {
                 self.write_a_or_absolute(addressing_mode, value);
             }
             Instruction::INC => {
-                let mut value = self.bus.read(self.absolute_address);
+                let mut value = self.read_modify_absolute();
                 value = value.wrapping_add(1);
                 self.bus.write(self.absolute_address, value);
                 self.update_zero_negative_flags(value);
             }
             Instruction::DEC => {
-                let mut value = self.bus.read(self.absolute_address);
+                let mut value = self.read_modify_absolute();
                 value = value.wrapping_sub(1);
                 self.bus.write(self.absolute_address, value);
                 self.update_zero_negative_flags(value);
@@ -637,13 +689,25 @@ impl CPU {
     }
 
     /* Reads a value from an absolute address or the accumulator register */
-    fn read_a_or_absolute(&self, addressing_mode: AddressingMode) -> u8 {
+    fn read_a_or_absolute(&mut self, addressing_mode: AddressingMode) -> u8 {
         match addressing_mode {
             AddressingMode::Accumulator => self.a,
-            _ => self.bus.read(self.absolute_address),
+            _ => self.read_modify_absolute(),
         }
     }
 
+    /*
+     * Reads the operand of a read-modify-write instruction. The
+     * CPU writes the unmodified value back before the result,
+     * which some mappers and registers react to.
+     */
+    fn read_modify_absolute(&mut self) -> u8 {
+        let value = self.bus.read(self.absolute_address);
+        self.bus.write(self.absolute_address, value);
+
+        value
+    }
+
     /* Writes a value to an absolute address or the accumulator register */
     fn write_a_or_absolute(&mut self, addressing_mode: AddressingMode, value: u8) {
         match addressing_mode {
@@ -652,3 +716,130 @@ impl CPU {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apu::apu::APU,
+        bus::ppu_bus::PpuBus,
+        cartridge::{
+            cartridge::{Cartridge, Mirroring},
+            mapper::{CartridgeMemory, Mapper},
+        },
+        memory::memory::Memory,
+        ppu::ppu::PPU,
+        state::state::{StateReader, StateWriter},
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    /* Address of the byte the programs under test work on */
+    const DATA_ADDRESS: u16 = 0x6000;
+
+    /* Writes made to the cartridge, in order */
+    type Writes = Rc<RefCell<Vec<(u16, u8)>>>;
+
+    /*
+     * Board running a program from $8000, with a single byte of
+     * data at $6000 whose writes are all recorded
+     */
+    struct RecordingMapper {
+        memory: CartridgeMemory,
+        program: Vec<u8>,
+        data: Cell<u8>,
+        writes: Writes,
+    }
+
+    impl Mapper for RecordingMapper {
+        fn cpu_read(&self, address: u16) -> u8 {
+            match address {
+                DATA_ADDRESS => self.data.get(),
+                RESET_VECTOR_ADDRESS_HI => 0x80,
+                0x8000.. => self
+                    .program
+                    .get(address as usize - 0x8000)
+                    .copied()
+                    .unwrap_or(0),
+                _ => 0,
+            }
+        }
+
+        fn cpu_write(&self, address: u16, value: u8) {
+            if address == DATA_ADDRESS {
+                self.data.set(value);
+            }
+            self.writes.borrow_mut().push((address, value));
+        }
+
+        fn ppu_read(&self, _address: u16) -> u8 {
+            0
+        }
+
+        fn ppu_write(&self, _address: u16, _value: u8) {}
+
+        fn memory(&self) -> &CartridgeMemory {
+            &self.memory
+        }
+
+        fn mirroring(&self) -> Mirroring {
+            Mirroring::Horizontal
+        }
+
+        fn save_state(&self, _state: &mut StateWriter) {}
+
+        fn load_state(&self, _state: &mut StateReader) -> AppResult<()> {
+            Ok(())
+        }
+    }
+
+    /* CPU about to run the given program, along with the writes it makes to the cartridge */
+    fn get_cpu(program: &[u8], data: u8) -> (CPU, Writes) {
+        let writes = Writes::default();
+        let mapper = RecordingMapper {
+            memory: CartridgeMemory {
+                prg_rom: Memory::new(0),
+                prg_ram: Memory::new(0),
+                chr: Memory::new(0),
+                is_chr_ram: false,
+                mirroring: Mirroring::Horizontal,
+                has_bus_conflicts: false,
+            },
+            program: program.to_vec(),
+            data: Cell::new(data),
+            writes: writes.clone(),
+        };
+        let cartridge = Rc::new(Cartridge::from_mapper(Box::new(mapper)));
+        let ppu = PPU::new(PpuBus::new(cartridge.clone()));
+        let bus = CpuBus::new(Memory::new(2048), ppu, APU::new(), cartridge);
+
+        (CPU::new(bus), writes)
+    }
+
+    #[test]
+    fn inc_absolute_writes_the_old_value_back_before_the_result() {
+        /* INC $6000 */
+        let (mut cpu, writes) = get_cpu(&[0xEE, 0x00, 0x60], 0x41);
+        cpu.clock().unwrap();
+
+        assert_eq!(
+            *writes.borrow(),
+            [(DATA_ADDRESS, 0x41), (DATA_ADDRESS, 0x42)]
+        );
+    }
+
+    #[test]
+    fn nmi_adds_its_cycles_on_top_of_an_oam_dma_stall() {
+        /* STA $4014 */
+        let (mut cpu, _) = get_cpu(&[0x8D, 0x14, 0x40], 0);
+        cpu.clock().unwrap();
+
+        let owed = cpu.cycles;
+        assert!(owed > 512);
+
+        cpu.nmi();
+        assert_eq!(cpu.cycles, owed + 7);
+    }
+}
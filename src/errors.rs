@@ -10,6 +10,50 @@ pub enum AppError {
     InvalidCartridgeHeaderSize,
     #[error("invalid NES file")]
     InvalidNesFile,
-    #[error("invalid cartridge mapper id, only 0 is supported")]
-    InvalidCartridgeMapper,
+    #[error("truncated ROM, the {section} needs {expected} bytes but only {actual} are left")]
+    TruncatedRom {
+        section: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("ROM has no PRG data")]
+    MissingPrgRom,
+    #[error("unsupported cartridge mapper {0}")]
+    InvalidCartridgeMapper(u16),
+    #[error("invalid UNIF file")]
+    InvalidUnifFile,
+    #[error("unsupported UNIF board {0}")]
+    UnsupportedUnifBoard(String),
+    #[error("invalid game database entry on line {0}")]
+    InvalidGameDatabase(usize),
+    #[error("invalid or truncated patch")]
+    InvalidPatch,
+    #[error("patch expects a ROM with CRC32 {expected:08X}, got {actual:08X}")]
+    PatchSourceMismatch { expected: u32, actual: u32 },
+    #[error("invalid cheat on line {0}")]
+    InvalidCheat(usize),
+    #[error("invalid key binding {0}")]
+    InvalidBinding(String),
+    #[error("invalid NSF file")]
+    InvalidNsfFile,
+    #[error("invalid palette file, expected 192 or 1536 bytes")]
+    InvalidPaletteFile,
+    #[error("invalid save state")]
+    InvalidSaveState,
+    #[error("audio error: {0}")]
+    Audio(String),
+    #[error("gamepad error: {0}")]
+    Gamepad(String),
+    #[error("video error: {0}")]
+    Video(String),
+    #[error("wav error: {0}")]
+    Wav(#[from] hound::Error),
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("toml error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("no ROM found in the archive")]
+    NoRomInArchive,
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
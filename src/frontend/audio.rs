@@ -0,0 +1,233 @@
+use cpal::{
+    FromSample, SizedSample, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use nes_sandbox::errors::{AppError, AppResult};
+use ringbuf::{
+    HeapCons, HeapProd, HeapRb,
+    traits::{Consumer, Observer, Producer, Split},
+};
+use std::time::Duration;
+
+/* Length of audio the ring buffer is able to hold, in milliseconds */
+const BUFFER_LENGTH_MS: u32 = 100;
+
+/*
+ * Ratio the last played sample is scaled by on every missing
+ * sample during an underrun, fading it out instead of
+ * dropping to silence with an audible click
+ */
+const UNDERRUN_FADE: f32 = 0.995;
+
+/*
+ * Largest change, as a ratio, dynamic rate control applies to
+ * the sample rate of the console by default. Half a percent
+ * is not heard as a pitch change.
+ */
+pub const DEFAULT_MAX_RATE_DELTA: f64 = 0.005;
+
+/* What is heard while fast-forwarding */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FastForwardAudio {
+    /*
+     * Frames of audio are played at their normal pitch as long
+     * as the buffer has room for them, the others are skipped
+     */
+    #[default]
+    Skip,
+    /* Nothing is played */
+    Mute,
+}
+
+impl FastForwardAudio {
+    /* Looks up a behavior by its name in the configuration */
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(FastForwardAudio::Skip),
+            "mute" => Some(FastForwardAudio::Mute),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Plays the samples produced by the console on the default
+ * output device. The emulation thread pushes samples into a
+ * lock-free ring buffer which the device callback drains.
+ */
+pub struct AudioOutput {
+    /* Dropping the stream stops the playback */
+    _stream: Stream,
+    producer: HeapProd<i16>,
+    capacity: usize,
+    sample_rate: u32,
+    /* Number of interleaved channels in the queued samples */
+    channels: usize,
+    max_rate_delta: f64,
+
+    /* Behavior while fast-forwarding, if the emulation is */
+    fast_forward: Option<FastForwardAudio>,
+}
+
+impl AudioOutput {
+    /*
+     * Opens the default output device with its preferred
+     * configuration, for mono or interleaved stereo samples
+     */
+    pub fn new(input_channels: usize) -> AppResult<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| AppError::Audio("no output device available".to_string()))?;
+        let supported = device
+            .default_output_config()
+            .map_err(|err| AppError::Audio(err.to_string()))?;
+
+        let config = supported.config();
+        let sample_rate = config.sample_rate.0;
+        let capacity = (sample_rate * BUFFER_LENGTH_MS / 1000) as usize * input_channels;
+        let (producer, consumer) = HeapRb::<i16>::new(capacity).split();
+
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::I16 => {
+                Self::get_stream::<i16>(&device, &config, consumer, input_channels)
+            }
+            cpal::SampleFormat::U16 => {
+                Self::get_stream::<u16>(&device, &config, consumer, input_channels)
+            }
+            cpal::SampleFormat::F32 => {
+                Self::get_stream::<f32>(&device, &config, consumer, input_channels)
+            }
+            format => {
+                return Err(AppError::Audio(format!(
+                    "unsupported sample format {}",
+                    format
+                )));
+            }
+        }?;
+
+        stream
+            .play()
+            .map_err(|err| AppError::Audio(err.to_string()))?;
+
+        Ok(Self {
+            _stream: stream,
+            producer,
+            capacity,
+            sample_rate,
+            channels: input_channels,
+            max_rate_delta: DEFAULT_MAX_RATE_DELTA,
+            fast_forward: None,
+        })
+    }
+
+    /* Rate the device plays samples at, in Hz */
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /*
+     * Sets the largest change dynamic rate control may apply to
+     * the sample rate, 0.0 turning it off
+     */
+    pub fn set_max_rate_delta(&mut self, delta: f64) {
+        self.max_rate_delta = delta;
+    }
+
+    /*
+     * Ratio the console should produce samples at for the ring
+     * buffer to stay half full. The clocks of the emulation and
+     * of the device never match exactly, so without it the
+     * buffer slowly drains or fills up over a long session.
+     */
+    pub fn rate_adjustment(&self) -> f64 {
+        let fill = self.producer.occupied_len() as f64 / self.capacity as f64;
+
+        1.0 + self.max_rate_delta * (1.0 - 2.0 * fill)
+    }
+
+    /*
+     * Length of the queued audio beyond the half full buffer
+     * dynamic rate control aims for. Waiting for it to be played
+     * paces the emulation off the clock of the device.
+     */
+    pub fn queued_ahead(&self) -> Duration {
+        let ahead = self
+            .producer
+            .occupied_len()
+            .saturating_sub(self.capacity / 2);
+
+        Duration::from_secs_f64(ahead as f64 / (self.sample_rate as usize * self.channels) as f64)
+    }
+
+    /*
+     * Tells the output the emulation is being fast-forwarded,
+     * with what should be heard meanwhile, or is back to normal
+     * with None
+     */
+    pub fn set_fast_forward(&mut self, audio: Option<FastForwardAudio>) {
+        self.fast_forward = audio;
+    }
+
+    /*
+     * Queues samples for playback. Samples that do not fit are
+     * dropped, which only happens if the emulation runs ahead.
+     */
+    pub fn queue(&mut self, samples: &[i16]) {
+        match self.fast_forward {
+            None => {}
+            Some(FastForwardAudio::Skip) if self.queued_ahead().is_zero() => {}
+            Some(_) => return,
+        }
+
+        self.producer.push_slice(samples);
+    }
+
+    /*
+     * Builds the output stream for a device sample type. A mono
+     * input is copied to every channel of the device, a stereo
+     * one to the first two, the last one being repeated.
+     */
+    fn get_stream<T>(
+        device: &cpal::Device,
+        config: &StreamConfig,
+        mut consumer: HeapCons<i16>,
+        input_channels: usize,
+    ) -> AppResult<Stream>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let channels = config.channels as usize;
+        let mut last = vec![0.0f32; input_channels];
+
+        device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _| {
+                    for frame in data.chunks_mut(channels) {
+                        /* Left and right samples are only taken together */
+                        let is_available = consumer.occupied_len() >= input_channels;
+
+                        for sample in last.iter_mut() {
+                            let value = if is_available {
+                                consumer.try_pop()
+                            } else {
+                                None
+                            };
+
+                            *sample = match value {
+                                Some(value) => value as f32 / i16::MAX as f32,
+                                None => *sample * UNDERRUN_FADE,
+                            };
+                        }
+
+                        for (index, sample) in frame.iter_mut().enumerate() {
+                            *sample = T::from_sample(last[index.min(input_channels - 1)]);
+                        }
+                    }
+                },
+                |err| eprintln!("audio stream error: {}", err),
+                None,
+            )
+            .map_err(|err| AppError::Audio(err.to_string()))
+    }
+}
@@ -0,0 +1,124 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use nes_sandbox::{
+    errors::{AppError, AppResult},
+    input::{bindings::Bindings, controller::Buttons, provider::Port},
+};
+
+/* Gamepad buttons looked up in the bindings, by the names gilrs gives them */
+const GAMEPAD_BUTTONS: [Button; 19] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::C,
+    Button::Z,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+/*
+ * Reads the gamepads connected to the host. Each one drives
+ * the controller of a port, gamepads being given the first
+ * free port as they are plugged in and freeing it once they
+ * are unplugged. The left stick also drives the D-pad.
+ */
+pub struct GamepadInput {
+    gilrs: Gilrs,
+    /* Gamepad driving the controller of every port */
+    ports: [Option<GamepadId>; 4],
+}
+
+impl GamepadInput {
+    /* Starts listening to gamepads, those already connected taking the first ports */
+    pub fn new() -> AppResult<Self> {
+        let gilrs = Gilrs::new().map_err(|err| AppError::Gamepad(err.to_string()))?;
+        let mut input = Self {
+            gilrs,
+            ports: [None; 4],
+        };
+
+        let connected: Vec<GamepadId> = input.gilrs.gamepads().map(|(id, _)| id).collect();
+
+        for id in connected {
+            input.connect(id);
+        }
+
+        Ok(input)
+    }
+
+    /* Handles the gamepads plugged in or out since the last call */
+    pub fn update(&mut self) {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::Connected => self.connect(event.id),
+                EventType::Disconnected => self.disconnect(event.id),
+                _ => {}
+            }
+        }
+    }
+
+    /* Buttons held down on the gamepad driving a port */
+    pub fn get_buttons(&self, port: Port, bindings: &Bindings) -> Buttons {
+        let Some(gamepad) =
+            self.ports[port.index()].and_then(|id| self.gilrs.connected_gamepad(id))
+        else {
+            return Buttons::empty();
+        };
+
+        let pressed: Vec<String> = GAMEPAD_BUTTONS
+            .iter()
+            .filter(|button| gamepad.is_pressed(**button))
+            .map(|button| format!("{:?}", button))
+            .collect();
+        let buttons =
+            bindings.get_gamepad_buttons(port, gamepad.name(), pressed.iter().map(String::as_str));
+
+        buttons
+            | Self::get_stick_buttons(
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+                bindings.deadzone(),
+            )
+    }
+
+    /* D-pad directions the left stick is pushed towards, beyond the deadzone */
+    fn get_stick_buttons(x: f32, y: f32, deadzone: f32) -> Buttons {
+        let mut buttons = Buttons::empty();
+
+        buttons.set(Buttons::LEFT, x < -deadzone);
+        buttons.set(Buttons::RIGHT, x > deadzone);
+        buttons.set(Buttons::UP, y > deadzone);
+        buttons.set(Buttons::DOWN, y < -deadzone);
+
+        buttons
+    }
+
+    /* Gives a newly plugged gamepad the first free port */
+    fn connect(&mut self, id: GamepadId) {
+        if self.ports.contains(&Some(id)) {
+            return;
+        }
+
+        if let Some(port) = self.ports.iter_mut().find(|port| port.is_none()) {
+            *port = Some(id);
+        }
+    }
+
+    /* Frees the port of an unplugged gamepad */
+    fn disconnect(&mut self, id: GamepadId) {
+        for port in self.ports.iter_mut().filter(|port| **port == Some(id)) {
+            *port = None;
+        }
+    }
+}
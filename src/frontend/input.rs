@@ -0,0 +1,74 @@
+#[cfg(feature = "gamepad")]
+use super::gamepad::GamepadInput;
+use nes_sandbox::input::{
+    bindings::Bindings,
+    controller::Buttons,
+    provider::{InputProvider, Port},
+};
+#[cfg(any(feature = "sdl", feature = "winit"))]
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+/* Names of the host keys held down, updated by the window */
+#[cfg(any(feature = "sdl", feature = "winit"))]
+pub type HeldKeys = Rc<RefCell<HashSet<String>>>;
+
+/*
+ * Buttons held down on the host, looked up in the bindings:
+ * the keys pressed on the window and the gamepads. The
+ * console polls it whenever the game latches the controllers.
+ */
+pub struct HostInput {
+    bindings: Bindings,
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    held_keys: HeldKeys,
+    /* Missing when the gamepads could not be reached */
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<GamepadInput>,
+}
+
+impl HostInput {
+    /* Initializes a new HostInput reading the host through the given bindings */
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            #[cfg(any(feature = "sdl", feature = "winit"))]
+            held_keys: HeldKeys::default(),
+            #[cfg(feature = "gamepad")]
+            gamepads: None,
+        }
+    }
+
+    /* Reads the keys held down on a window */
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    pub fn set_held_keys(&mut self, held_keys: HeldKeys) {
+        self.held_keys = held_keys;
+    }
+
+    /* Reads the gamepads connected to the host */
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepads(&mut self, gamepads: GamepadInput) {
+        self.gamepads = Some(gamepads);
+    }
+}
+
+impl InputProvider for HostInput {
+    fn poll(&mut self, port: Port) -> Buttons {
+        let mut buttons = Buttons::empty();
+
+        #[cfg(any(feature = "sdl", feature = "winit"))]
+        {
+            let held_keys = self.held_keys.borrow();
+            buttons |= self
+                .bindings
+                .get_keyboard_buttons(port, held_keys.iter().map(String::as_str));
+        }
+
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = self.gamepads.as_mut() {
+            gamepads.update();
+            buttons |= gamepads.get_buttons(port, &self.bindings);
+        }
+
+        buttons
+    }
+}
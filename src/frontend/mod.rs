@@ -0,0 +1,20 @@
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+#[cfg(any(feature = "sdl", feature = "winit", feature = "gamepad"))]
+pub mod input;
+pub mod pacer;
+#[cfg(any(feature = "sdl", feature = "winit"))]
+pub mod scaling;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+/* SDL is preferred when both windows are built */
+#[cfg(all(feature = "winit", not(feature = "sdl")))]
+pub mod winit;
+
+/* Window the console is shown in */
+#[cfg(all(feature = "winit", not(feature = "sdl")))]
+pub use self::winit::WinitWindow as Window;
+#[cfg(feature = "sdl")]
+pub use sdl::SdlWindow as Window;
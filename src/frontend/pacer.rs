@@ -0,0 +1,246 @@
+use nes_sandbox::console::region::Region;
+use std::{
+    hint, thread,
+    time::{Duration, Instant},
+};
+
+/*
+ * Time left before a deadline under which the pacer stops
+ * sleeping and spins instead, as the scheduler of the host
+ * can oversleep by about a millisecond
+ */
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+/*
+ * Lag behind the real time clock after which the pacer gives
+ * up catching up, such as after the window was dragged or
+ * the host was suspended
+ */
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+/*
+ * Largest difference, as a ratio, between the refresh rate of
+ * the display and the frame rate of the console for vsync to
+ * be used. Dynamic rate control makes up for it in the audio.
+ */
+#[cfg(any(feature = "sdl", feature = "winit"))]
+pub const VSYNC_TOLERANCE: f64 = 0.005;
+
+/* Clock the frames of the emulation are paced by */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncMode {
+    /* The refresh of the display, presenting a frame waits for it */
+    #[default]
+    Vsync,
+    /* The clock of the audio device, through the samples still queued */
+    Audio,
+    /* The real time clock of the host */
+    Timer,
+}
+
+impl SyncMode {
+    /* Looks up a mode by its name in the configuration */
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vsync" => Some(SyncMode::Vsync),
+            "audio" => Some(SyncMode::Audio),
+            "timer" => Some(SyncMode::Timer),
+            _ => None,
+        }
+    }
+
+    /* Whether a display refreshing at the given rate can pace the frames of a region */
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    pub fn is_vsync_usable(refresh_rate: f64, region: Region) -> bool {
+        let frame_rate = region.frame_rate();
+        (refresh_rate - frame_rate).abs() / frame_rate <= VSYNC_TOLERANCE
+    }
+}
+
+/* Speed the emulation runs at while fast-forwarding */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FastForwardSpeed {
+    Double,
+    Quadruple,
+    /* As fast as the host can go */
+    #[default]
+    Unlimited,
+}
+
+impl FastForwardSpeed {
+    /* Looks up a speed by its name in the configuration */
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "2x" => Some(FastForwardSpeed::Double),
+            "4x" => Some(FastForwardSpeed::Quadruple),
+            "unlimited" => Some(FastForwardSpeed::Unlimited),
+            _ => None,
+        }
+    }
+
+    /* Speed as a multiple of the one of the console, unless unlimited */
+    pub fn ratio(&self) -> Option<f64> {
+        match self {
+            FastForwardSpeed::Double => Some(2.0),
+            FastForwardSpeed::Quadruple => Some(4.0),
+            FastForwardSpeed::Unlimited => None,
+        }
+    }
+}
+
+/*
+ * Throttles the emulation to the speed of the real console.
+ *
+ * Deadlines are derived from the master clock, the number of
+ * PPU dots run since a fixed starting point, rather than by
+ * adding up frame durations. Frames keep their exact uneven
+ * lengths, such as the dot skipped on odd NTSC frames, and
+ * rounding errors never pile up over long sessions, so the
+ * average frame rate stays at 60.0988 Hz on NTSC and
+ * 50.007 Hz on PAL.
+ *
+ * Each deadline is reached by sleeping for most of the time
+ * left, then spinning for the last moments.
+ *
+ * The pacer can instead leave the pacing to the display or to
+ * the audio device, whose clocks drift from the one of the
+ * host. The real time clock is used when there is no audio.
+ *
+ * While fast-forwarding, frames are paced by the real time
+ * clock at a multiple of the normal speed, or not at all, and
+ * only as many frames as the display shows are presented.
+ *
+ * While the console is paused, the pacer waits for a frame
+ * at a time so that the host keeps polling input.
+ */
+pub struct FramePacer {
+    mode: SyncMode,
+    /* Rate of the master clock, in dots per second */
+    clock_rate: f64,
+    /* Real time and master clock the deadlines are counted from */
+    start: Instant,
+    start_cycles: u64,
+
+    fast_forward: Option<FastForwardSpeed>,
+    is_paused: bool,
+    /* Time between two frames presented while fast-forwarding */
+    present_interval: Duration,
+    next_present: Instant,
+}
+
+impl FramePacer {
+    /* Initializes a new FramePacer running at the speed of the given region */
+    pub fn new(region: Region, mode: SyncMode) -> Self {
+        Self {
+            mode,
+            clock_rate: region.ppu_clock_rate(),
+            start: Instant::now(),
+            start_cycles: 0,
+            fast_forward: None,
+            is_paused: false,
+            present_interval: Duration::from_secs_f64(1.0 / region.frame_rate()),
+            next_present: Instant::now(),
+        }
+    }
+
+    /*
+     * Starts fast-forwarding at the given speed, or goes back to
+     * the normal speed with None, from the given master clock
+     */
+    pub fn set_fast_forward(&mut self, speed: Option<FastForwardSpeed>, cycles: u64) {
+        self.fast_forward = speed;
+        self.resync(cycles);
+    }
+
+    /* Specifies whether the emulation is being fast-forwarded */
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.fast_forward.is_some()
+    }
+
+    /* Tells the pacer whether the console is paused, from the given master clock */
+    pub fn set_paused(&mut self, paused: bool, cycles: u64) {
+        self.is_paused = paused;
+        self.resync(cycles);
+    }
+
+    /* Specifies whether the pacer was told the console is paused */
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /*
+     * Whether the frame just produced should be presented. All of
+     * them are, unless fast-forwarding faster than the display.
+     */
+    pub fn should_present(&mut self) -> bool {
+        let now = Instant::now();
+
+        if self.fast_forward.is_some() && now < self.next_present {
+            return false;
+        }
+
+        self.next_present = (self.next_present + self.present_interval).max(now);
+        true
+    }
+
+    /*
+     * Blocks until the next frame is due, given the master clock
+     * of the console in PPU dots and the length of the audio
+     * queued ahead of the output device, if there is one
+     */
+    pub fn wait(&mut self, cycles: u64, audio_ahead: Option<Duration>) {
+        if self.is_paused {
+            thread::sleep(self.present_interval);
+            return;
+        }
+
+        if let Some(speed) = self.fast_forward {
+            if let Some(ratio) = speed.ratio() {
+                self.wait_for_clock(cycles, ratio);
+            }
+            return;
+        }
+
+        match (self.mode, audio_ahead) {
+            (SyncMode::Vsync, _) => {}
+            (SyncMode::Audio, Some(ahead)) => thread::sleep(ahead),
+            (SyncMode::Audio | SyncMode::Timer, _) => self.wait_for_clock(cycles, 1.0),
+        }
+    }
+
+    /*
+     * Blocks until the real time clock catches up with the
+     * master clock of the console, run at a multiple of its speed
+     */
+    fn wait_for_clock(&mut self, cycles: u64, speed: f64) {
+        let Some(elapsed) = cycles.checked_sub(self.start_cycles) else {
+            self.resync(cycles);
+            return;
+        };
+
+        let deadline =
+            self.start + Duration::from_secs_f64(elapsed as f64 / (self.clock_rate * speed));
+        let now = Instant::now();
+
+        if now > deadline {
+            if now - deadline > MAX_LAG {
+                self.resync(cycles);
+            }
+            return;
+        }
+
+        if let Some(sleep) = (deadline - now).checked_sub(SPIN_MARGIN) {
+            thread::sleep(sleep);
+        }
+
+        while Instant::now() < deadline {
+            hint::spin_loop();
+        }
+    }
+
+    /* Counts the following deadlines from the given master clock, reached right now */
+    pub fn resync(&mut self, cycles: u64) {
+        self.start = Instant::now();
+        self.start_cycles = cycles;
+    }
+}
@@ -0,0 +1,152 @@
+use nes_sandbox::ppu::frame::{FRAME_HEIGHT, FRAME_WIDTH};
+
+/*
+ * Width of a pixel of the PPU over its height on a television.
+ * The picture was meant to be seen slightly stretched.
+ */
+pub const PIXEL_ASPECT_RATIO: f64 = 8.0 / 7.0;
+
+/* Scanlines hidden at the top and at the bottom by most televisions */
+pub const OVERSCAN_LINES: usize = 8;
+
+/* How the window covers the screen when in fullscreen */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullscreenMode {
+    /* A borderless window the size of the desktop */
+    #[default]
+    Borderless,
+    /* Exclusive use of the display, at the resolution and refresh rate of the desktop */
+    Exclusive,
+}
+
+impl FullscreenMode {
+    /* Looks up a mode by its name in the configuration */
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "borderless" => Some(FullscreenMode::Borderless),
+            "exclusive" => Some(FullscreenMode::Exclusive),
+            _ => None,
+        }
+    }
+}
+
+/* An area of a picture or a window, in pixels */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/*
+ * Where a picture of the console is taken from, and where it
+ * is shown in the window. The window is left black around it.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Viewport {
+    /* Area of the picture shown, in its own pixels */
+    pub source: Rect,
+    /* Area of the window it is shown in */
+    pub destination: Rect,
+}
+
+/*
+ * How pictures of the console are fitted into the window.
+ *
+ * The PPU picture is scaled up by the largest whole factor
+ * that fits, keeping every dot the same size with nearest
+ * neighbor scaling, and is letterboxed in the window. Dots
+ * can be widened to their 8:7 shape on a television, and the
+ * overscan most televisions hid can be cropped.
+ *
+ * Pictures larger than the PPU one, such as those of the NTSC
+ * filter, are fitted by the size of the PPU picture they show.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Scaling {
+    pub is_aspect_corrected: bool,
+    pub is_overscan_cropped: bool,
+}
+
+impl Scaling {
+    /* Area a picture of the given size takes in a window of the given size */
+    pub fn get_viewport(
+        &self,
+        (frame_width, frame_height): (usize, usize),
+        (window_width, window_height): (usize, usize),
+    ) -> Viewport {
+        let cropped_lines = self.get_cropped_lines();
+        let crop = cropped_lines * frame_height / FRAME_HEIGHT;
+        let source = Rect {
+            x: 0,
+            y: crop,
+            width: frame_width,
+            height: frame_height - crop * 2,
+        };
+
+        let pixel_aspect_ratio = if self.is_aspect_corrected {
+            PIXEL_ASPECT_RATIO
+        } else {
+            1.0
+        };
+        let picture_width = FRAME_WIDTH as f64 * pixel_aspect_ratio;
+        let picture_height = (FRAME_HEIGHT - cropped_lines * 2) as f64;
+
+        /* Windows too small for the picture shrink it as much as needed */
+        let fit = (window_width as f64 / picture_width).min(window_height as f64 / picture_height);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+
+        let width = ((picture_width * scale).round() as usize).min(window_width);
+        let height = ((picture_height * scale).round() as usize).min(window_height);
+
+        Viewport {
+            source,
+            destination: Rect {
+                x: (window_width - width) / 2,
+                y: (window_height - height) / 2,
+                width,
+                height,
+            },
+        }
+    }
+
+    /*
+     * Dot of the PPU picture shown at a position of a window
+     * of the given size, if the picture covers it
+     */
+    pub fn get_frame_position(
+        &self,
+        window_size: (usize, usize),
+        (x, y): (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let destination = self
+            .get_viewport((FRAME_WIDTH, FRAME_HEIGHT), window_size)
+            .destination;
+
+        if destination.width == 0
+            || destination.height == 0
+            || !(destination.x..destination.x + destination.width).contains(&x)
+            || !(destination.y..destination.y + destination.height).contains(&y)
+        {
+            return None;
+        }
+
+        let cropped_lines = self.get_cropped_lines();
+        let visible_lines = FRAME_HEIGHT - cropped_lines * 2;
+
+        Some((
+            (x - destination.x) * FRAME_WIDTH / destination.width,
+            cropped_lines + (y - destination.y) * visible_lines / destination.height,
+        ))
+    }
+
+    /* Scanlines of the PPU picture left out at the top and at the bottom */
+    fn get_cropped_lines(&self) -> usize {
+        if self.is_overscan_cropped {
+            OVERSCAN_LINES
+        } else {
+            0
+        }
+    }
+}
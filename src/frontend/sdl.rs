@@ -0,0 +1,454 @@
+use super::{
+    input::HeldKeys,
+    pacer::SyncMode,
+    scaling::{self, FullscreenMode, Scaling},
+};
+use nes_sandbox::{
+    console::{console::Console, region::Region},
+    errors::{AppError, AppResult},
+    input::{keyboard::Key, ports::InputDevice},
+    ppu::frame::{FRAME_HEIGHT, FRAME_WIDTH},
+};
+use sdl2::{
+    EventPump, Sdl,
+    event::{Event, WindowEvent},
+    hint,
+    keyboard::Keycode,
+    mouse::MouseButton,
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{Texture, WindowCanvas},
+    video::{FullscreenType, WindowPos},
+};
+
+pub const WINDOW_TITLE: &str = "nes-sandbox";
+
+/* Size of the window when opened, as a multiple of the PPU picture */
+pub const WINDOW_SCALE: u32 = 3;
+
+/* Key showing or hiding the buttons held on every controller */
+const INPUT_OVERLAY_KEY: Keycode = Keycode::F1;
+
+/* Key pausing and resuming the console */
+const PAUSE_KEY: Keycode = Keycode::F9;
+
+/* Key running a single frame, pausing the console first */
+const FRAME_ADVANCE_KEY: Keycode = Keycode::F12;
+
+/* Key switching between the window and fullscreen */
+const FULLSCREEN_KEY: Keycode = Keycode::F11;
+
+/* Keys fast-forwarding while held down, and until pressed again */
+const FAST_FORWARD_KEY: Keycode = Keycode::TAB;
+const FAST_FORWARD_TOGGLE_KEY: Keycode = Keycode::F10;
+
+/*
+ * A window showing the pictures of the console, through SDL.
+ *
+ * Every frame is uploaded to a streaming texture stretched
+ * over the window. The keys held down drive the controllers
+ * as mapped by the bindings, the mouse aims and fires the
+ * Zapper, and keys are passed through to the Family BASIC
+ * keyboard when it is plugged in.
+ */
+pub struct SdlWindow {
+    /* SDL is shut down once its context is dropped */
+    _sdl: Sdl,
+    canvas: WindowCanvas,
+    texture: Texture,
+    texture_size: (usize, usize),
+    event_pump: EventPump,
+    is_vsync_enabled: bool,
+    scaling: Scaling,
+    fullscreen_mode: FullscreenMode,
+    is_fast_forward_held: bool,
+    is_fast_forward_toggled: bool,
+    /* Position and size of the window to go back to, while in fullscreen */
+    windowed_geometry: Option<((i32, i32), (u32, u32))>,
+
+    /* Names of the host keys held down, shared with the input provider */
+    held_keys: HeldKeys,
+}
+
+impl SdlWindow {
+    /*
+     * Opens the window, sized for the pictures of the PPU. Vsync
+     * is only turned on if asked for and if the display refreshes
+     * at about the frame rate of the region.
+     */
+    pub fn new(sync: SyncMode, region: Region) -> AppResult<Self> {
+        let sdl = sdl2::init().map_err(AppError::Video)?;
+        let video = sdl.video().map_err(AppError::Video)?;
+
+        let window = video
+            .window(
+                WINDOW_TITLE,
+                FRAME_WIDTH as u32 * WINDOW_SCALE,
+                FRAME_HEIGHT as u32 * WINDOW_SCALE,
+            )
+            .position_centered()
+            .resizable()
+            .build()
+            .map_err(|err| AppError::Video(err.to_string()))?;
+
+        let refresh_rate = window
+            .display_index()
+            .and_then(|display| video.current_display_mode(display))
+            .map(|mode| mode.refresh_rate as f64)
+            .unwrap_or(0.0);
+        let is_vsync_enabled =
+            sync == SyncMode::Vsync && SyncMode::is_vsync_usable(refresh_rate, region);
+
+        /* Dots are scaled up as sharp squares, not blurred together */
+        hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+
+        let mut canvas = window.into_canvas();
+
+        if is_vsync_enabled {
+            canvas = canvas.present_vsync();
+        }
+
+        let canvas = canvas
+            .build()
+            .map_err(|err| AppError::Video(err.to_string()))?;
+
+        let texture_size = (FRAME_WIDTH, FRAME_HEIGHT);
+        let texture = Self::get_texture(&canvas, texture_size)?;
+        let event_pump = sdl.event_pump().map_err(AppError::Video)?;
+
+        Ok(Self {
+            _sdl: sdl,
+            canvas,
+            texture,
+            texture_size,
+            event_pump,
+            is_vsync_enabled,
+            scaling: Scaling::default(),
+            fullscreen_mode: FullscreenMode::default(),
+            windowed_geometry: None,
+            is_fast_forward_held: false,
+            is_fast_forward_toggled: false,
+            held_keys: HeldKeys::default(),
+        })
+    }
+
+    /*
+     * Handles the events received since the last call, feeding
+     * the Zapper and the Family BASIC keyboard of the console.
+     * Returns false once the window is closed.
+     */
+    pub fn handle_events(&mut self, console: &mut Console) -> bool {
+        let device = console.input().device();
+        let (width, height) = self.canvas.window().size();
+        let window_size = (width as usize, height as usize);
+        let mut is_fullscreen_toggled = false;
+
+        for event in self.event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return false,
+                Event::KeyDown {
+                    keycode: Some(Keycode::ESCAPE),
+                    ..
+                } if device != InputDevice::FamilyKeyboard => return false,
+                Event::KeyDown {
+                    keycode: Some(INPUT_OVERLAY_KEY),
+                    repeat: false,
+                    ..
+                } => console.set_input_overlay(!console.is_input_overlay_enabled()),
+                Event::KeyDown {
+                    keycode: Some(PAUSE_KEY),
+                    repeat: false,
+                    ..
+                } => console.set_paused(!console.is_paused()),
+                Event::KeyDown {
+                    keycode: Some(FRAME_ADVANCE_KEY),
+                    repeat: false,
+                    ..
+                } => console.advance_frame(),
+                Event::KeyDown {
+                    keycode: Some(FULLSCREEN_KEY),
+                    repeat: false,
+                    ..
+                } => is_fullscreen_toggled = !is_fullscreen_toggled,
+                Event::KeyDown {
+                    keycode: Some(FAST_FORWARD_KEY),
+                    ..
+                } => self.is_fast_forward_held = true,
+                Event::KeyUp {
+                    keycode: Some(FAST_FORWARD_KEY),
+                    ..
+                } => self.is_fast_forward_held = false,
+                Event::KeyDown {
+                    keycode: Some(FAST_FORWARD_TOGGLE_KEY),
+                    repeat: false,
+                    ..
+                } => self.is_fast_forward_toggled = !self.is_fast_forward_toggled,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => match Self::get_family_key(keycode) {
+                    Some(key) if device == InputDevice::FamilyKeyboard => {
+                        console.keyboard_mut().set_key(key, true)
+                    }
+                    _ => {
+                        self.held_keys.borrow_mut().insert(keycode.name());
+                    }
+                },
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = Self::get_family_key(keycode) {
+                        console.keyboard_mut().set_key(key, false);
+                    }
+                    self.held_keys.borrow_mut().remove(&keycode.name());
+                }
+                Event::MouseMotion { x, y, .. } => {
+                    let position = (x >= 0 && y >= 0)
+                        .then(|| {
+                            self.scaling
+                                .get_frame_position(window_size, (x as usize, y as usize))
+                        })
+                        .flatten();
+
+                    console.zapper_mut().set_position(position);
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => console.zapper_mut().set_trigger(true),
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => console.zapper_mut().set_trigger(false),
+                Event::Window {
+                    win_event: WindowEvent::Leave,
+                    ..
+                } => console.zapper_mut().set_position(None),
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => {
+                    self.held_keys.borrow_mut().clear();
+                    self.is_fast_forward_held = false;
+                    console.keyboard_mut().release_all();
+                }
+                _ => {}
+            }
+        }
+
+        if is_fullscreen_toggled && let Err(err) = self.set_fullscreen(!self.is_fullscreen()) {
+            eprintln!("failed to switch fullscreen: {}", err);
+        }
+
+        true
+    }
+
+    /* Changes how pictures are fitted into the window */
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+    }
+
+    /* Changes how the window covers the screen once in fullscreen */
+    pub fn set_fullscreen_mode(&mut self, mode: FullscreenMode) {
+        self.fullscreen_mode = mode;
+    }
+
+    /*
+     * Switches between the window and fullscreen. The window
+     * gets back its position and size when leaving fullscreen.
+     */
+    pub fn set_fullscreen(&mut self, enabled: bool) -> AppResult<()> {
+        if enabled == self.is_fullscreen() {
+            return Ok(());
+        }
+
+        let window = self.canvas.window_mut();
+
+        if enabled {
+            self.windowed_geometry = Some((window.position(), window.size()));
+
+            let fullscreen_type = match self.fullscreen_mode {
+                FullscreenMode::Borderless => FullscreenType::Desktop,
+                FullscreenMode::Exclusive => {
+                    let display = window.display_index().map_err(AppError::Video)?;
+                    let mode = window
+                        .subsystem()
+                        .desktop_display_mode(display)
+                        .map_err(AppError::Video)?;
+
+                    window.set_display_mode(mode).map_err(AppError::Video)?;
+                    FullscreenType::True
+                }
+            };
+
+            window
+                .set_fullscreen(fullscreen_type)
+                .map_err(AppError::Video)?;
+        } else {
+            window
+                .set_fullscreen(FullscreenType::Off)
+                .map_err(AppError::Video)?;
+
+            if let Some(((x, y), (width, height))) = self.windowed_geometry.take() {
+                window
+                    .set_size(width, height)
+                    .map_err(|err| AppError::Video(err.to_string()))?;
+                window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+            }
+        }
+
+        Ok(())
+    }
+
+    /* Specifies whether the window covers the screen */
+    pub fn is_fullscreen(&self) -> bool {
+        self.canvas.window().fullscreen_state() != FullscreenType::Off
+    }
+
+    /* Specifies whether a fast-forward key is held down or was toggled on */
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.is_fast_forward_held || self.is_fast_forward_toggled
+    }
+
+    /* Whether presenting a picture waits for the refresh of the display */
+    pub fn is_vsync_enabled(&self) -> bool {
+        self.is_vsync_enabled
+    }
+
+    /* Names of the host keys held down, for the input provider to read */
+    pub fn held_keys(&self) -> HeldKeys {
+        self.held_keys.clone()
+    }
+
+    /* Shows the latest picture of the console */
+    pub fn present(&mut self, console: &mut Console) -> AppResult<()> {
+        let frame = console.output_frame();
+
+        if self.texture_size != (frame.width, frame.height) {
+            self.texture_size = (frame.width, frame.height);
+            self.texture = Self::get_texture(&self.canvas, self.texture_size)?;
+        }
+
+        self.texture
+            .update(None, &frame.pixels, frame.width * 4)
+            .map_err(|err| AppError::Video(err.to_string()))?;
+
+        let (width, height) = self.canvas.output_size().map_err(AppError::Video)?;
+        let viewport = self
+            .scaling
+            .get_viewport(self.texture_size, (width as usize, height as usize));
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas
+            .copy(
+                &self.texture,
+                Self::get_rect(viewport.source),
+                Self::get_rect(viewport.destination),
+            )
+            .map_err(AppError::Video)?;
+        self.canvas.present();
+
+        Ok(())
+    }
+
+    /* Creates a streaming texture of RGBA pixels for pictures of the given size */
+    fn get_texture(canvas: &WindowCanvas, (width, height): (usize, usize)) -> AppResult<Texture> {
+        canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width as u32, height as u32)
+            .map_err(|err| AppError::Video(err.to_string()))
+    }
+
+    /* Area of a picture or a window, as SDL takes it */
+    fn get_rect(rect: scaling::Rect) -> Rect {
+        Rect::new(
+            rect.x as i32,
+            rect.y as i32,
+            rect.width as u32,
+            rect.height as u32,
+        )
+    }
+
+    /* Key of the Family BASIC keyboard a host key stands for */
+    fn get_family_key(keycode: Keycode) -> Option<Key> {
+        let key = match keycode {
+            Keycode::F1 => Key::F1,
+            Keycode::F2 => Key::F2,
+            Keycode::F3 => Key::F3,
+            Keycode::F4 => Key::F4,
+            Keycode::F5 => Key::F5,
+            Keycode::F6 => Key::F6,
+            Keycode::F7 => Key::F7,
+            Keycode::F8 => Key::F8,
+            Keycode::NUM_0 => Key::Num0,
+            Keycode::NUM_1 => Key::Num1,
+            Keycode::NUM_2 => Key::Num2,
+            Keycode::NUM_3 => Key::Num3,
+            Keycode::NUM_4 => Key::Num4,
+            Keycode::NUM_5 => Key::Num5,
+            Keycode::NUM_6 => Key::Num6,
+            Keycode::NUM_7 => Key::Num7,
+            Keycode::NUM_8 => Key::Num8,
+            Keycode::NUM_9 => Key::Num9,
+            Keycode::A => Key::A,
+            Keycode::B => Key::B,
+            Keycode::C => Key::C,
+            Keycode::D => Key::D,
+            Keycode::E => Key::E,
+            Keycode::F => Key::F,
+            Keycode::G => Key::G,
+            Keycode::H => Key::H,
+            Keycode::I => Key::I,
+            Keycode::J => Key::J,
+            Keycode::K => Key::K,
+            Keycode::L => Key::L,
+            Keycode::M => Key::M,
+            Keycode::N => Key::N,
+            Keycode::O => Key::O,
+            Keycode::P => Key::P,
+            Keycode::Q => Key::Q,
+            Keycode::R => Key::R,
+            Keycode::S => Key::S,
+            Keycode::T => Key::T,
+            Keycode::U => Key::U,
+            Keycode::V => Key::V,
+            Keycode::W => Key::W,
+            Keycode::X => Key::X,
+            Keycode::Y => Key::Y,
+            Keycode::Z => Key::Z,
+            Keycode::MINUS => Key::Minus,
+            Keycode::EQUALS => Key::Caret,
+            Keycode::BACKSLASH => Key::Yen,
+            Keycode::BACKQUOTE => Key::At,
+            Keycode::LEFTBRACKET => Key::LeftBracket,
+            Keycode::RIGHTBRACKET => Key::RightBracket,
+            Keycode::SEMICOLON => Key::Semicolon,
+            Keycode::QUOTE => Key::Colon,
+            Keycode::COMMA => Key::Comma,
+            Keycode::PERIOD => Key::Period,
+            Keycode::SLASH => Key::Slash,
+            Keycode::UNDERSCORE => Key::Underscore,
+            Keycode::RETURN => Key::Return,
+            Keycode::SPACE => Key::Space,
+            Keycode::ESCAPE => Key::Escape,
+            Keycode::LCTRL | Keycode::RCTRL => Key::Control,
+            Keycode::LSHIFT => Key::LeftShift,
+            Keycode::RSHIFT => Key::RightShift,
+            Keycode::LALT => Key::Graph,
+            Keycode::RALT => Key::Kana,
+            Keycode::END => Key::Stop,
+            Keycode::HOME => Key::ClearHome,
+            Keycode::INSERT => Key::Insert,
+            Keycode::BACKSPACE | Keycode::DELETE => Key::Delete,
+            Keycode::UP => Key::Up,
+            Keycode::DOWN => Key::Down,
+            Keycode::LEFT => Key::Left,
+            Keycode::RIGHT => Key::Right,
+            _ => return None,
+        };
+
+        Some(key)
+    }
+}
@@ -0,0 +1,566 @@
+use super::{
+    input::HeldKeys,
+    pacer::SyncMode,
+    scaling::{FullscreenMode, Scaling, Viewport},
+};
+use nes_sandbox::{
+    console::{console::Console, region::Region},
+    errors::{AppError, AppResult},
+    input::{keyboard::Key, ports::InputDevice},
+    ppu::frame::{FRAME_HEIGHT, FRAME_WIDTH, ProcessedFrame},
+};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use std::time::Duration;
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event_loop::EventLoop,
+    keyboard::{KeyCode, PhysicalKey},
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    window::{Fullscreen, Window, WindowBuilder},
+};
+
+pub const WINDOW_TITLE: &str = "nes-sandbox";
+
+/* Size of the window when opened, as a multiple of the PPU picture */
+pub const WINDOW_SCALE: u32 = 3;
+
+/* Key showing or hiding the buttons held on every controller */
+const INPUT_OVERLAY_KEY: KeyCode = KeyCode::F1;
+
+/* Key pausing and resuming the console */
+const PAUSE_KEY: KeyCode = KeyCode::F9;
+
+/* Key running a single frame, pausing the console first */
+const FRAME_ADVANCE_KEY: KeyCode = KeyCode::F12;
+
+/* Key switching between the window and fullscreen */
+const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+
+/* Keys fast-forwarding while held down, and until pressed again */
+const FAST_FORWARD_KEY: KeyCode = KeyCode::Tab;
+const FAST_FORWARD_TOGGLE_KEY: KeyCode = KeyCode::F10;
+
+/*
+ * A window showing the pictures of the console, drawn with
+ * wgpu through pixels. Unlike the SDL window it needs no
+ * library installed on the host.
+ *
+ * It behaves the same way: the keys held down drive the
+ * controllers as mapped by the bindings, keys being named
+ * the way SDL names them so both read the same file, the
+ * mouse aims and fires the Zapper, and keys are passed
+ * through to the Family BASIC keyboard when it is plugged in.
+ *
+ * Pictures are scaled on the CPU into a buffer the size of
+ * the window, as pixels only scales by whole factors.
+ */
+pub struct WinitWindow {
+    /* Draws on the window, so it has to be dropped first */
+    pixels: Pixels,
+    /* Size of the pixels buffer, always the one of the window */
+    buffer_size: (usize, usize),
+    window: Window,
+    event_loop: EventLoop<()>,
+    is_vsync_enabled: bool,
+    scaling: Scaling,
+    fullscreen_mode: FullscreenMode,
+    is_fast_forward_held: bool,
+    is_fast_forward_toggled: bool,
+    /* Position and size of the window to go back to, while in fullscreen */
+    windowed_geometry: Option<(Option<PhysicalPosition<i32>>, PhysicalSize<u32>)>,
+
+    /* Names of the host keys held down, shared with the input provider */
+    held_keys: HeldKeys,
+}
+
+impl WinitWindow {
+    /*
+     * Opens the window, sized for the pictures of the PPU. Vsync
+     * is only turned on if asked for and if the display refreshes
+     * at about the frame rate of the region.
+     */
+    pub fn new(sync: SyncMode, region: Region) -> AppResult<Self> {
+        let event_loop = EventLoop::new().map_err(|err| AppError::Video(err.to_string()))?;
+        let window = WindowBuilder::new()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(LogicalSize::new(
+                FRAME_WIDTH as u32 * WINDOW_SCALE,
+                FRAME_HEIGHT as u32 * WINDOW_SCALE,
+            ))
+            .with_min_inner_size(LogicalSize::new(FRAME_WIDTH as u32, FRAME_HEIGHT as u32))
+            .build(&event_loop)
+            .map_err(|err| AppError::Video(err.to_string()))?;
+
+        let window_size = window.inner_size();
+        let surface = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        let refresh_rate = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map(|rate| rate as f64 / 1000.0)
+            .unwrap_or(0.0);
+        let is_vsync_enabled =
+            sync == SyncMode::Vsync && SyncMode::is_vsync_usable(refresh_rate, region);
+
+        let pixels = PixelsBuilder::new(window_size.width, window_size.height, surface)
+            .enable_vsync(is_vsync_enabled)
+            .build()
+            .map_err(|err| AppError::Video(err.to_string()))?;
+
+        Ok(Self {
+            pixels,
+            buffer_size: (window_size.width as usize, window_size.height as usize),
+            window,
+            event_loop,
+            is_vsync_enabled,
+            scaling: Scaling::default(),
+            fullscreen_mode: FullscreenMode::default(),
+            windowed_geometry: None,
+            is_fast_forward_held: false,
+            is_fast_forward_toggled: false,
+            held_keys: HeldKeys::default(),
+        })
+    }
+
+    /*
+     * Handles the events received since the last call, feeding
+     * the Zapper and the Family BASIC keyboard of the console.
+     * Returns false once the window is closed.
+     */
+    pub fn handle_events(&mut self, console: &mut Console) -> bool {
+        let device = console.input().device();
+        let mut is_fullscreen_toggled = false;
+        let Self {
+            pixels,
+            buffer_size,
+            window,
+            event_loop,
+            scaling,
+            is_fast_forward_held,
+            is_fast_forward_toggled,
+            held_keys,
+            ..
+        } = self;
+
+        let status = event_loop.pump_events(Some(Duration::ZERO), |event, target| {
+            let Event::WindowEvent { window_id, event } = event else {
+                return;
+            };
+
+            if window_id != window.id() {
+                return;
+            }
+
+            match event {
+                WindowEvent::CloseRequested => target.exit(),
+                /* A minimized window has no size to draw at */
+                WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                    let result = pixels
+                        .resize_surface(size.width, size.height)
+                        .and_then(|_| pixels.resize_buffer(size.width, size.height));
+
+                    match result {
+                        Ok(()) => *buffer_size = (size.width as usize, size.height as usize),
+                        Err(err) => eprintln!("failed to resize the window: {}", err),
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(code),
+                            state,
+                            repeat,
+                            ..
+                        },
+                    ..
+                } => {
+                    let is_pressed = state == ElementState::Pressed;
+                    let family_key = Self::get_family_key(code);
+
+                    match code {
+                        KeyCode::Escape if is_pressed && device != InputDevice::FamilyKeyboard => {
+                            target.exit()
+                        }
+                        INPUT_OVERLAY_KEY if is_pressed && !repeat => {
+                            console.set_input_overlay(!console.is_input_overlay_enabled())
+                        }
+                        PAUSE_KEY if is_pressed && !repeat => {
+                            console.set_paused(!console.is_paused())
+                        }
+                        FRAME_ADVANCE_KEY if is_pressed && !repeat => console.advance_frame(),
+                        FULLSCREEN_KEY if is_pressed && !repeat => {
+                            is_fullscreen_toggled = !is_fullscreen_toggled
+                        }
+                        FAST_FORWARD_KEY => *is_fast_forward_held = is_pressed,
+                        FAST_FORWARD_TOGGLE_KEY if is_pressed && !repeat => {
+                            *is_fast_forward_toggled = !*is_fast_forward_toggled
+                        }
+                        _ if device == InputDevice::FamilyKeyboard && family_key.is_some() => {
+                            if let Some(key) = family_key {
+                                console.keyboard_mut().set_key(key, is_pressed);
+                            }
+                        }
+                        _ => {
+                            if let Some(name) = Self::get_key_name(code) {
+                                if is_pressed {
+                                    held_keys.borrow_mut().insert(name.to_string());
+                                } else {
+                                    held_keys.borrow_mut().remove(name);
+                                }
+                            }
+                        }
+                    }
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    let position = (position.x >= 0.0 && position.y >= 0.0)
+                        .then(|| {
+                            scaling.get_frame_position(
+                                *buffer_size,
+                                (position.x as usize, position.y as usize),
+                            )
+                        })
+                        .flatten();
+
+                    console.zapper_mut().set_position(position);
+                }
+                WindowEvent::CursorLeft { .. } => console.zapper_mut().set_position(None),
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => console
+                    .zapper_mut()
+                    .set_trigger(state == ElementState::Pressed),
+                WindowEvent::Focused(false) => {
+                    held_keys.borrow_mut().clear();
+                    *is_fast_forward_held = false;
+                    console.keyboard_mut().release_all();
+                }
+                _ => {}
+            }
+        });
+
+        if is_fullscreen_toggled && let Err(err) = self.set_fullscreen(!self.is_fullscreen()) {
+            eprintln!("failed to switch fullscreen: {}", err);
+        }
+
+        !matches!(status, PumpStatus::Exit(_))
+    }
+
+    /* Changes how pictures are fitted into the window */
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+    }
+
+    /* Changes how the window covers the screen once in fullscreen */
+    pub fn set_fullscreen_mode(&mut self, mode: FullscreenMode) {
+        self.fullscreen_mode = mode;
+    }
+
+    /*
+     * Switches between the window and fullscreen. The window
+     * gets back its position and size when leaving fullscreen.
+     */
+    pub fn set_fullscreen(&mut self, enabled: bool) -> AppResult<()> {
+        if enabled == self.is_fullscreen() {
+            return Ok(());
+        }
+
+        if !enabled {
+            self.window.set_fullscreen(None);
+
+            if let Some((position, size)) = self.windowed_geometry.take() {
+                let _ = self.window.request_inner_size(size);
+
+                if let Some(position) = position {
+                    self.window.set_outer_position(position);
+                }
+            }
+
+            return Ok(());
+        }
+
+        let fullscreen = match self.fullscreen_mode {
+            FullscreenMode::Borderless => Fullscreen::Borderless(None),
+            FullscreenMode::Exclusive => {
+                let monitor = self
+                    .window
+                    .current_monitor()
+                    .ok_or_else(|| AppError::Video("no monitor found".to_string()))?;
+                let refresh_rate = monitor.refresh_rate_millihertz().unwrap_or(0);
+
+                /* The desktop mode keeps vsync working as it was chosen for */
+                let mode = monitor
+                    .video_modes()
+                    .filter(|mode| mode.size() == monitor.size())
+                    .min_by_key(|mode| mode.refresh_rate_millihertz().abs_diff(refresh_rate))
+                    .ok_or_else(|| AppError::Video("no video mode found".to_string()))?;
+
+                Fullscreen::Exclusive(mode)
+            }
+        };
+
+        self.windowed_geometry =
+            Some((self.window.outer_position().ok(), self.window.inner_size()));
+        self.window.set_fullscreen(Some(fullscreen));
+
+        Ok(())
+    }
+
+    /* Specifies whether the window covers the screen */
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.fullscreen().is_some()
+    }
+
+    /* Specifies whether a fast-forward key is held down or was toggled on */
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.is_fast_forward_held || self.is_fast_forward_toggled
+    }
+
+    /* Whether presenting a picture waits for the refresh of the display */
+    pub fn is_vsync_enabled(&self) -> bool {
+        self.is_vsync_enabled
+    }
+
+    /* Names of the host keys held down, for the input provider to read */
+    pub fn held_keys(&self) -> HeldKeys {
+        self.held_keys.clone()
+    }
+
+    /* Shows the latest picture of the console */
+    pub fn present(&mut self, console: &mut Console) -> AppResult<()> {
+        let frame = console.output_frame();
+        let viewport = self
+            .scaling
+            .get_viewport((frame.width, frame.height), self.buffer_size);
+
+        Self::draw_frame(
+            &frame,
+            viewport,
+            self.pixels.frame_mut(),
+            self.buffer_size.0,
+        );
+        self.pixels
+            .render()
+            .map_err(|err| AppError::Video(err.to_string()))
+    }
+
+    /*
+     * Scales a picture into its area of a buffer of RGBA pixels
+     * the given number of pixels wide, with nearest neighbor
+     * scaling, and blacks out the rest of the buffer
+     */
+    fn draw_frame(frame: &ProcessedFrame, viewport: Viewport, buffer: &mut [u8], width: usize) {
+        let Viewport {
+            source,
+            destination,
+        } = viewport;
+
+        buffer.fill(0);
+
+        for pixel in buffer.chunks_exact_mut(4) {
+            pixel[3] = 0xFF;
+        }
+
+        if destination.width == 0 || destination.height == 0 {
+            return;
+        }
+
+        /* Column of the picture shown in each column of the area */
+        let columns: Vec<usize> = (0..destination.width)
+            .map(|x| source.x + x * source.width / destination.width)
+            .collect();
+
+        for y in 0..destination.height {
+            let source_y = source.y + y * source.height / destination.height;
+            let source_row = &frame.pixels[source_y * frame.width * 4..][..frame.width * 4];
+            let row_start = ((destination.y + y) * width + destination.x) * 4;
+            let row = &mut buffer[row_start..][..destination.width * 4];
+
+            for (pixel, column) in row.chunks_exact_mut(4).zip(&columns) {
+                pixel.copy_from_slice(&source_row[column * 4..][..4]);
+            }
+        }
+    }
+
+    /* Name SDL gives to a host key, which is what the bindings refer to keys by */
+    fn get_key_name(code: KeyCode) -> Option<&'static str> {
+        let name = match code {
+            KeyCode::KeyA => "A",
+            KeyCode::KeyB => "B",
+            KeyCode::KeyC => "C",
+            KeyCode::KeyD => "D",
+            KeyCode::KeyE => "E",
+            KeyCode::KeyF => "F",
+            KeyCode::KeyG => "G",
+            KeyCode::KeyH => "H",
+            KeyCode::KeyI => "I",
+            KeyCode::KeyJ => "J",
+            KeyCode::KeyK => "K",
+            KeyCode::KeyL => "L",
+            KeyCode::KeyM => "M",
+            KeyCode::KeyN => "N",
+            KeyCode::KeyO => "O",
+            KeyCode::KeyP => "P",
+            KeyCode::KeyQ => "Q",
+            KeyCode::KeyR => "R",
+            KeyCode::KeyS => "S",
+            KeyCode::KeyT => "T",
+            KeyCode::KeyU => "U",
+            KeyCode::KeyV => "V",
+            KeyCode::KeyW => "W",
+            KeyCode::KeyX => "X",
+            KeyCode::KeyY => "Y",
+            KeyCode::KeyZ => "Z",
+            KeyCode::Digit0 => "0",
+            KeyCode::Digit1 => "1",
+            KeyCode::Digit2 => "2",
+            KeyCode::Digit3 => "3",
+            KeyCode::Digit4 => "4",
+            KeyCode::Digit5 => "5",
+            KeyCode::Digit6 => "6",
+            KeyCode::Digit7 => "7",
+            KeyCode::Digit8 => "8",
+            KeyCode::Digit9 => "9",
+            KeyCode::Numpad0 => "Keypad 0",
+            KeyCode::Numpad1 => "Keypad 1",
+            KeyCode::Numpad2 => "Keypad 2",
+            KeyCode::Numpad3 => "Keypad 3",
+            KeyCode::Numpad4 => "Keypad 4",
+            KeyCode::Numpad5 => "Keypad 5",
+            KeyCode::Numpad6 => "Keypad 6",
+            KeyCode::Numpad7 => "Keypad 7",
+            KeyCode::Numpad8 => "Keypad 8",
+            KeyCode::Numpad9 => "Keypad 9",
+            KeyCode::NumpadEnter => "Keypad Enter",
+            KeyCode::F1 => "F1",
+            KeyCode::F2 => "F2",
+            KeyCode::F3 => "F3",
+            KeyCode::F4 => "F4",
+            KeyCode::F5 => "F5",
+            KeyCode::F6 => "F6",
+            KeyCode::F7 => "F7",
+            KeyCode::F8 => "F8",
+            KeyCode::F9 => "F9",
+            KeyCode::F10 => "F10",
+            KeyCode::F11 => "F11",
+            KeyCode::F12 => "F12",
+            KeyCode::Minus => "-",
+            KeyCode::Equal => "=",
+            KeyCode::BracketLeft => "[",
+            KeyCode::BracketRight => "]",
+            KeyCode::Backslash => "\\",
+            KeyCode::Semicolon => ";",
+            KeyCode::Quote => "'",
+            KeyCode::Backquote => "`",
+            KeyCode::Comma => ",",
+            KeyCode::Period => ".",
+            KeyCode::Slash => "/",
+            KeyCode::Enter => "Return",
+            KeyCode::Space => "Space",
+            KeyCode::Tab => "Tab",
+            KeyCode::Backspace => "Backspace",
+            KeyCode::Escape => "Escape",
+            KeyCode::Insert => "Insert",
+            KeyCode::Delete => "Delete",
+            KeyCode::Home => "Home",
+            KeyCode::End => "End",
+            KeyCode::PageUp => "PageUp",
+            KeyCode::PageDown => "PageDown",
+            KeyCode::ShiftLeft => "Left Shift",
+            KeyCode::ShiftRight => "Right Shift",
+            KeyCode::ControlLeft => "Left Ctrl",
+            KeyCode::ControlRight => "Right Ctrl",
+            KeyCode::AltLeft => "Left Alt",
+            KeyCode::AltRight => "Right Alt",
+            KeyCode::ArrowUp => "Up",
+            KeyCode::ArrowDown => "Down",
+            KeyCode::ArrowLeft => "Left",
+            KeyCode::ArrowRight => "Right",
+            _ => return None,
+        };
+
+        Some(name)
+    }
+
+    /* Key of the Family BASIC keyboard a host key stands for */
+    fn get_family_key(code: KeyCode) -> Option<Key> {
+        let key = match code {
+            KeyCode::F1 => Key::F1,
+            KeyCode::F2 => Key::F2,
+            KeyCode::F3 => Key::F3,
+            KeyCode::F4 => Key::F4,
+            KeyCode::F5 => Key::F5,
+            KeyCode::F6 => Key::F6,
+            KeyCode::F7 => Key::F7,
+            KeyCode::F8 => Key::F8,
+            KeyCode::Digit0 => Key::Num0,
+            KeyCode::Digit1 => Key::Num1,
+            KeyCode::Digit2 => Key::Num2,
+            KeyCode::Digit3 => Key::Num3,
+            KeyCode::Digit4 => Key::Num4,
+            KeyCode::Digit5 => Key::Num5,
+            KeyCode::Digit6 => Key::Num6,
+            KeyCode::Digit7 => Key::Num7,
+            KeyCode::Digit8 => Key::Num8,
+            KeyCode::Digit9 => Key::Num9,
+            KeyCode::KeyA => Key::A,
+            KeyCode::KeyB => Key::B,
+            KeyCode::KeyC => Key::C,
+            KeyCode::KeyD => Key::D,
+            KeyCode::KeyE => Key::E,
+            KeyCode::KeyF => Key::F,
+            KeyCode::KeyG => Key::G,
+            KeyCode::KeyH => Key::H,
+            KeyCode::KeyI => Key::I,
+            KeyCode::KeyJ => Key::J,
+            KeyCode::KeyK => Key::K,
+            KeyCode::KeyL => Key::L,
+            KeyCode::KeyM => Key::M,
+            KeyCode::KeyN => Key::N,
+            KeyCode::KeyO => Key::O,
+            KeyCode::KeyP => Key::P,
+            KeyCode::KeyQ => Key::Q,
+            KeyCode::KeyR => Key::R,
+            KeyCode::KeyS => Key::S,
+            KeyCode::KeyT => Key::T,
+            KeyCode::KeyU => Key::U,
+            KeyCode::KeyV => Key::V,
+            KeyCode::KeyW => Key::W,
+            KeyCode::KeyX => Key::X,
+            KeyCode::KeyY => Key::Y,
+            KeyCode::KeyZ => Key::Z,
+            KeyCode::Minus => Key::Minus,
+            KeyCode::Equal => Key::Caret,
+            KeyCode::Backslash | KeyCode::IntlYen => Key::Yen,
+            KeyCode::Backquote => Key::At,
+            KeyCode::BracketLeft => Key::LeftBracket,
+            KeyCode::BracketRight => Key::RightBracket,
+            KeyCode::Semicolon => Key::Semicolon,
+            KeyCode::Quote => Key::Colon,
+            KeyCode::Comma => Key::Comma,
+            KeyCode::Period => Key::Period,
+            KeyCode::Slash => Key::Slash,
+            KeyCode::IntlRo => Key::Underscore,
+            KeyCode::Enter => Key::Return,
+            KeyCode::Space => Key::Space,
+            KeyCode::Escape => Key::Escape,
+            KeyCode::ControlLeft | KeyCode::ControlRight => Key::Control,
+            KeyCode::ShiftLeft => Key::LeftShift,
+            KeyCode::ShiftRight => Key::RightShift,
+            KeyCode::AltLeft => Key::Graph,
+            KeyCode::AltRight => Key::Kana,
+            KeyCode::End => Key::Stop,
+            KeyCode::Home => Key::ClearHome,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::Backspace | KeyCode::Delete => Key::Delete,
+            KeyCode::ArrowUp => Key::Up,
+            KeyCode::ArrowDown => Key::Down,
+            KeyCode::ArrowLeft => Key::Left,
+            KeyCode::ArrowRight => Key::Right,
+            _ => return None,
+        };
+
+        Some(key)
+    }
+}
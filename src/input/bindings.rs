@@ -0,0 +1,253 @@
+use crate::{
+    errors::{AppError, AppResult},
+    input::{controller::Buttons, provider::Port},
+};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+/* File the bindings are read from, in the working directory */
+pub const BINDINGS_FILE_NAME: &str = "bindings.toml";
+
+/* Distance an analog stick has to be pushed to, between 0 and 1, to press the D-pad */
+pub const DEFAULT_DEADZONE: f32 = 0.5;
+
+/*
+ * Keys and gamepad buttons of the first controller when no
+ * file is found. Keys use the names SDL gives them and
+ * gamepad buttons the ones of gilrs.
+ */
+const DEFAULT_KEYBOARD: [(&str, Buttons); 8] = [
+    ("X", Buttons::A),
+    ("Z", Buttons::B),
+    ("Right Shift", Buttons::SELECT),
+    ("Return", Buttons::START),
+    ("Up", Buttons::UP),
+    ("Down", Buttons::DOWN),
+    ("Left", Buttons::LEFT),
+    ("Right", Buttons::RIGHT),
+];
+const DEFAULT_GAMEPAD: [(&str, Buttons); 8] = [
+    ("South", Buttons::A),
+    ("West", Buttons::B),
+    ("Select", Buttons::SELECT),
+    ("Start", Buttons::START),
+    ("DPadUp", Buttons::UP),
+    ("DPadDown", Buttons::DOWN),
+    ("DPadLeft", Buttons::LEFT),
+    ("DPadRight", Buttons::RIGHT),
+];
+
+/* Content of a bindings file, see Bindings */
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BindingsFile {
+    deadzone: f32,
+    profiles: HashMap<String, HashMap<String, String>>,
+    port1: PortFile,
+    port2: PortFile,
+    port3: PortFile,
+    port4: PortFile,
+}
+
+impl Default for BindingsFile {
+    fn default() -> Self {
+        Self {
+            deadzone: DEFAULT_DEADZONE,
+            profiles: HashMap::new(),
+            port1: PortFile::default(),
+            port2: PortFile::default(),
+            port3: PortFile::default(),
+            port4: PortFile::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PortFile {
+    keyboard: HashMap<String, String>,
+    gamepad: HashMap<String, String>,
+}
+
+/* Host inputs bound to the buttons of a single controller */
+#[derive(Debug, Clone, Default)]
+pub struct PortBindings {
+    pub keyboard: HashMap<String, Buttons>,
+    pub gamepad: HashMap<String, Buttons>,
+}
+
+/*
+ * Maps the keys and gamepad buttons of the host to the
+ * buttons of the four controllers.
+ *
+ * Bindings are read from a TOML file with a table for the
+ * keyboard and the gamepad of every port, each entry binding
+ * a host input to one or more NES buttons joined by +:
+ *
+ *   [port1.keyboard]
+ *   X = "A"
+ *   Return = "Start"
+ *   Q = "Up+Left"
+ *
+ *   [port1.gamepad]
+ *   South = "A"
+ *
+ * Gamepads whose buttons are laid out differently can get
+ * a profile of their own, keyed by the name of the device,
+ * which replaces the gamepad table of the port they drive:
+ *
+ *   deadzone = 0.4
+ *
+ *   [profiles."Retro Pad"]
+ *   East = "A"
+ *   South = "B"
+ */
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    ports: [PortBindings; 4],
+    /* Gamepad buttons bound by the profile of each device */
+    profiles: HashMap<String, HashMap<String, Buttons>>,
+    deadzone: f32,
+}
+
+impl Bindings {
+    /* Parses the content of a bindings file */
+    pub fn new(text: &str) -> AppResult<Self> {
+        let file: BindingsFile = toml::from_str(text)?;
+        let mut bindings = Self {
+            ports: Default::default(),
+            profiles: HashMap::new(),
+            deadzone: file.deadzone.clamp(0.0, 1.0),
+        };
+
+        for (device, table) in file.profiles {
+            bindings
+                .profiles
+                .insert(device, Self::get_buttons_map(table)?);
+        }
+
+        for (port, port_file) in [file.port1, file.port2, file.port3, file.port4]
+            .into_iter()
+            .enumerate()
+        {
+            bindings.ports[port] = PortBindings {
+                keyboard: Self::get_buttons_map(port_file.keyboard)?,
+                gamepad: Self::get_buttons_map(port_file.gamepad)?,
+            };
+        }
+
+        Ok(bindings)
+    }
+
+    /* Loads a bindings file from disk */
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let text = fs::read_to_string(path)?;
+
+        Self::new(&text)
+    }
+
+    /* Loads the bindings file if there is one, falling back to the defaults */
+    pub fn load<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        if path.as_ref().is_file() {
+            Self::from_file(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /* Bindings of the controller of a port */
+    pub fn port(&self, port: Port) -> &PortBindings {
+        &self.ports[port.index()]
+    }
+
+    /* Gives mutable access to the bindings of a port, to rebind its inputs */
+    pub fn port_mut(&mut self, port: Port) -> &mut PortBindings {
+        &mut self.ports[port.index()]
+    }
+
+    /* Buttons of a port held down by the given host keys */
+    pub fn get_keyboard_buttons<'a, I: IntoIterator<Item = &'a str>>(
+        &self,
+        port: Port,
+        keys: I,
+    ) -> Buttons {
+        Self::get_held_buttons(&self.port(port).keyboard, keys)
+    }
+
+    /*
+     * Buttons of a port held down by the given buttons of a
+     * gamepad, through the profile of the device if it has one
+     */
+    pub fn get_gamepad_buttons<'a, I: IntoIterator<Item = &'a str>>(
+        &self,
+        port: Port,
+        device: &str,
+        buttons: I,
+    ) -> Buttons {
+        let map = self
+            .profiles
+            .get(device)
+            .unwrap_or(&self.port(port).gamepad);
+
+        Self::get_held_buttons(map, buttons)
+    }
+
+    /* Distance analog sticks have to be pushed to, between 0 and 1, to press the D-pad */
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    /* Buttons bound to any of the given host inputs */
+    fn get_held_buttons<'a, I: IntoIterator<Item = &'a str>>(
+        map: &HashMap<String, Buttons>,
+        inputs: I,
+    ) -> Buttons {
+        inputs
+            .into_iter()
+            .filter_map(|input| map.get(input))
+            .fold(Buttons::empty(), |held, buttons| held | *buttons)
+    }
+
+    /* Parses the NES buttons every host input of a table is bound to */
+    fn get_buttons_map(table: HashMap<String, String>) -> AppResult<HashMap<String, Buttons>> {
+        table
+            .into_iter()
+            .map(|(input, names)| Ok((input, Self::get_buttons(&names)?)))
+            .collect()
+    }
+
+    /* Parses button names joined by +, such as Up+Left */
+    fn get_buttons(names: &str) -> AppResult<Buttons> {
+        names
+            .split('+')
+            .try_fold(Buttons::empty(), |buttons, name| {
+                Buttons::from_name(&name.trim().to_uppercase())
+                    .map(|button| buttons | button)
+                    .ok_or_else(|| AppError::InvalidBinding(names.to_string()))
+            })
+    }
+}
+
+impl Default for Bindings {
+    /* The first controller on the arrow keys, Z and X, and on the first gamepad */
+    fn default() -> Self {
+        let mut ports: [PortBindings; 4] = Default::default();
+
+        ports[0] = PortBindings {
+            keyboard: DEFAULT_KEYBOARD
+                .iter()
+                .map(|(key, buttons)| (key.to_string(), *buttons))
+                .collect(),
+            gamepad: DEFAULT_GAMEPAD
+                .iter()
+                .map(|(button, buttons)| (button.to_string(), *buttons))
+                .collect(),
+        };
+
+        Self {
+            ports,
+            profiles: HashMap::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
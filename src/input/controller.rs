@@ -0,0 +1,103 @@
+use bitflags::bitflags;
+
+/* Number of buttons reported by a standard controller */
+pub const BUTTON_COUNT: u8 = 8;
+
+/*
+ * Buttons of a standard controller, in the order they are
+ * shifted out to the CPU
+ */
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Buttons: u8 {
+        const A      = 0b0000_0001;
+        const B      = 0b0000_0010;
+        const SELECT = 0b0000_0100;
+        const START  = 0b0000_1000;
+        const UP     = 0b0001_0000;
+        const DOWN   = 0b0010_0000;
+        const LEFT   = 0b0100_0000;
+        const RIGHT  = 0b1000_0000;
+    }
+}
+
+/*
+ * A standard controller plugged into one of the two ports.
+ *
+ * Writing 1 to bit 0 of $4016 holds the strobe high, the
+ * buttons being continuously latched into an 8 bit shift
+ * register. Once it goes low, every read of the port returns
+ * the next button in bit 0, official controllers returning 1
+ * after the 8th read.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Controller {
+    /* Buttons currently held down, as set by the frontend */
+    buttons: Buttons,
+    shift_register: u8,
+    reads: u8,
+    is_strobe: bool,
+}
+
+impl Controller {
+    /* Initializes a new Controller with no button pressed */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Replaces the buttons held down, usually once per frame */
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        self.buttons = buttons;
+
+        if self.is_strobe {
+            self.latch();
+        }
+    }
+
+    /* Presses or releases some buttons, leaving the others as they are */
+    pub fn set_pressed(&mut self, buttons: Buttons, pressed: bool) {
+        self.set_buttons(if pressed {
+            self.buttons | buttons
+        } else {
+            self.buttons - buttons
+        });
+    }
+
+    /* Buttons currently held down */
+    pub fn buttons(&self) -> Buttons {
+        self.buttons
+    }
+
+    /* Handles a write to $4016, bit 0 being the strobe */
+    pub fn write(&mut self, value: u8) {
+        self.is_strobe = value & 0x01 != 0;
+
+        if self.is_strobe {
+            self.latch();
+        }
+    }
+
+    /* Shifts out the next button, in bit 0 */
+    pub fn read(&mut self) -> u8 {
+        /* While the strobe is held, the first button keeps being reported */
+        if self.is_strobe {
+            return self.buttons.contains(Buttons::A) as u8;
+        }
+
+        if self.reads >= BUTTON_COUNT {
+            return 0x01;
+        }
+
+        let bit = self.shift_register & 0x01;
+        self.shift_register >>= 1;
+        self.reads += 1;
+
+        bit
+    }
+
+    /* Copies the buttons into the shift register */
+    fn latch(&mut self) {
+        self.shift_register = self.buttons.bits();
+        self.reads = 0;
+    }
+}
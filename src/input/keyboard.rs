@@ -0,0 +1,216 @@
+/* Number of rows scanned by the keyboard matrix, each made of two columns */
+pub const KEYBOARD_ROWS: usize = 9;
+
+/* Keys of the Family BASIC keyboard */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Minus,
+    Caret,
+    Yen,
+    At,
+    LeftBracket,
+    RightBracket,
+    Semicolon,
+    Colon,
+    Comma,
+    Period,
+    Slash,
+    Underscore,
+    Return,
+    Space,
+    Escape,
+    Control,
+    LeftShift,
+    RightShift,
+    Graph,
+    Kana,
+    Stop,
+    ClearHome,
+    Insert,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/*
+ * Keys found at every row and column of the matrix, in the
+ * order of the data bits D1 to D4 they are reported on
+ */
+const KEY_MATRIX: [[[Key; 4]; 2]; KEYBOARD_ROWS] = [
+    [
+        [Key::RightBracket, Key::LeftBracket, Key::Return, Key::F8],
+        [Key::Stop, Key::Yen, Key::RightShift, Key::Kana],
+    ],
+    [
+        [Key::Semicolon, Key::Colon, Key::At, Key::F7],
+        [Key::Caret, Key::Minus, Key::Slash, Key::Underscore],
+    ],
+    [
+        [Key::K, Key::L, Key::O, Key::F6],
+        [Key::Num0, Key::P, Key::Comma, Key::Period],
+    ],
+    [
+        [Key::J, Key::U, Key::I, Key::F5],
+        [Key::Num8, Key::Num9, Key::N, Key::M],
+    ],
+    [
+        [Key::H, Key::G, Key::Y, Key::F4],
+        [Key::Num6, Key::Num7, Key::V, Key::B],
+    ],
+    [
+        [Key::D, Key::R, Key::T, Key::F3],
+        [Key::Num4, Key::Num5, Key::C, Key::F],
+    ],
+    [
+        [Key::A, Key::S, Key::W, Key::F2],
+        [Key::Num3, Key::E, Key::Z, Key::X],
+    ],
+    [
+        [Key::Control, Key::Q, Key::Escape, Key::F1],
+        [Key::Num2, Key::Num1, Key::Graph, Key::LeftShift],
+    ],
+    [
+        [Key::Left, Key::Right, Key::Up, Key::ClearHome],
+        [Key::Insert, Key::Delete, Key::Space, Key::Down],
+    ],
+];
+
+/*
+ * The Family BASIC keyboard, plugged into the expansion port
+ * of the Famicom.
+ *
+ * Games scan its matrix one half row at a time. Writes to
+ * $4016 enable it with bit 2, select the column with bit 1,
+ * and go back to the first row with bit 0, the next row
+ * being selected whenever the column goes from 1 back to 0.
+ * The 4 keys of the selected half row are then read from
+ * bits 1 to 4 of $4017, cleared while pressed.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct FamilyKeyboard {
+    /* Keys held down in every half row, bit 0 being the one read from D1 */
+    pressed: [[u8; 2]; KEYBOARD_ROWS],
+
+    row: usize,
+    column: usize,
+    is_enabled: bool,
+}
+
+impl FamilyKeyboard {
+    /* Initializes a new FamilyKeyboard with no key pressed */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Presses or releases a key */
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        let (row, column, bit) = Self::get_key_position(key);
+
+        if pressed {
+            self.pressed[row][column] |= 1 << bit;
+        } else {
+            self.pressed[row][column] &= !(1 << bit);
+        }
+    }
+
+    /* Specifies whether a key is held down */
+    pub fn is_pressed(&self, key: Key) -> bool {
+        let (row, column, bit) = Self::get_key_position(key);
+
+        self.pressed[row][column] & (1 << bit) != 0
+    }
+
+    /* Releases every key */
+    pub fn release_all(&mut self) {
+        self.pressed = [[0; 2]; KEYBOARD_ROWS];
+    }
+
+    /* Handles a write to $4016 */
+    pub fn write(&mut self, value: u8) {
+        let column = ((value >> 1) & 0x01) as usize;
+
+        self.is_enabled = value & 0x04 != 0;
+
+        if self.column == 1 && column == 0 {
+            self.row += 1;
+        }
+        self.column = column;
+
+        if value & 0x01 != 0 {
+            self.row = 0;
+        }
+    }
+
+    /* Reads the keys of the selected half row, in bits 1 to 4 of $4017 */
+    pub fn read(&self) -> u8 {
+        if !self.is_enabled {
+            return 0;
+        }
+
+        let pressed = self
+            .pressed
+            .get(self.row)
+            .map_or(0, |columns| columns[self.column]);
+
+        (!pressed & 0x0F) << 1
+    }
+
+    /* Row, column and data bit a key is reported at */
+    fn get_key_position(key: Key) -> (usize, usize, usize) {
+        for (row, columns) in KEY_MATRIX.iter().enumerate() {
+            for (column, keys) in columns.iter().enumerate() {
+                if let Some(bit) = keys.iter().position(|other| *other == key) {
+                    return (row, column, bit);
+                }
+            }
+        }
+
+        unreachable!("every key is part of the matrix")
+    }
+}
@@ -0,0 +1,7 @@
+pub mod bindings;
+pub mod controller;
+pub mod keyboard;
+pub mod overlay;
+pub mod ports;
+pub mod provider;
+pub mod zapper;
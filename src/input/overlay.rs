@@ -0,0 +1,81 @@
+use crate::{
+    input::controller::Buttons,
+    ppu::frame::{FRAME_HEIGHT, FRAME_WIDTH, ProcessedFrame},
+};
+
+/* Size of the drawing of a controller, in pixels of the PPU picture */
+const PAD_WIDTH: usize = 27;
+const PAD_HEIGHT: usize = 11;
+
+/* Space left around and between the drawings */
+const PAD_MARGIN: usize = 2;
+
+const BACKGROUND_COLOR: [u8; 3] = [0x20, 0x20, 0x20];
+const RELEASED_COLOR: [u8; 3] = [0x60, 0x60, 0x60];
+const PRESSED_COLOR: [u8; 3] = [0xF8, 0xF8, 0xF8];
+
+/* Spot of every button on the drawing, as x, y, width and height */
+const BUTTON_RECTS: [(Buttons, [usize; 4]); 8] = [
+    (Buttons::UP, [4, 1, 3, 3]),
+    (Buttons::DOWN, [4, 7, 3, 3]),
+    (Buttons::LEFT, [1, 4, 3, 3]),
+    (Buttons::RIGHT, [7, 4, 3, 3]),
+    (Buttons::SELECT, [11, 6, 3, 2]),
+    (Buttons::START, [15, 6, 3, 2]),
+    (Buttons::B, [19, 4, 3, 3]),
+    (Buttons::A, [23, 4, 3, 3]),
+];
+
+/*
+ * Draws the buttons held down on every controller in the
+ * bottom left corner of a picture, one small controller per
+ * port. Pictures larger than the one of the PPU, such as
+ * the NTSC filter output, get the drawing scaled along.
+ */
+pub fn draw_input_overlay(frame: &mut ProcessedFrame, controllers: &[Buttons]) {
+    let scale_x = (frame.width / FRAME_WIDTH).max(1);
+    let scale_y = (frame.height / FRAME_HEIGHT).max(1);
+    let y = (frame.height / scale_y).saturating_sub(PAD_HEIGHT + PAD_MARGIN);
+
+    for (index, buttons) in controllers.iter().enumerate() {
+        let x = PAD_MARGIN + index * (PAD_WIDTH + PAD_MARGIN);
+        let mut fill = |[left, top, width, height]: [usize; 4], color: [u8; 3]| {
+            fill_rect(
+                frame,
+                [(x + left) * scale_x, (y + top) * scale_y],
+                [width * scale_x, height * scale_y],
+                color,
+            )
+        };
+
+        fill([0, 0, PAD_WIDTH, PAD_HEIGHT], BACKGROUND_COLOR);
+
+        for (button, rect) in BUTTON_RECTS {
+            let color = if buttons.contains(button) {
+                PRESSED_COLOR
+            } else {
+                RELEASED_COLOR
+            };
+
+            fill(rect, color);
+        }
+    }
+}
+
+/* Fills a rectangle of an RGBA picture, clipped to its edges */
+fn fill_rect(
+    frame: &mut ProcessedFrame,
+    [x, y]: [usize; 2],
+    [width, height]: [usize; 2],
+    color: [u8; 3],
+) {
+    for row in y..(y + height).min(frame.height) {
+        for column in x..(x + width).min(frame.width) {
+            let offset = (row * frame.width + column) * 4;
+
+            if let Some(pixel) = frame.pixels.get_mut(offset..offset + 4) {
+                pixel.copy_from_slice(&[color[0], color[1], color[2], 0xFF]);
+            }
+        }
+    }
+}
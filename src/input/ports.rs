@@ -0,0 +1,200 @@
+use crate::{
+    input::{
+        controller::{Buttons, Controller},
+        keyboard::FamilyKeyboard,
+        provider::{InputProvider, Port},
+        zapper::Zapper,
+    },
+    ppu::ppu::PPU,
+};
+
+/* Number of controllers a Four Score connects */
+pub const CONTROLLER_COUNT: usize = 4;
+
+/*
+ * Signatures reported by the Four Score on $4016 and $4017
+ * after the buttons of its controllers
+ */
+pub const FOUR_SCORE_SIGNATURES: [u8; 2] = [0x08, 0x04];
+
+/* What is plugged into the controller ports */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputDevice {
+    /* A controller in each port */
+    #[default]
+    Controllers,
+    /* Four controllers through the Four Score multitap */
+    FourScore,
+    /* A controller in the first port and a Zapper in the second */
+    Zapper,
+    /* A controller in each port and the Family BASIC keyboard in the expansion port */
+    FamilyKeyboard,
+}
+
+/*
+ * The two controller ports of the console and the devices
+ * plugged into them. Writes to $4016 reach every device,
+ * while reads of $4016 and $4017 each reach a single port.
+ *
+ * The Four Score reports 24 bits on each port: the buttons
+ * of the controller plugged into it, those of the third or
+ * fourth controller, then a signature telling the ports
+ * apart.
+ *
+ * The buttons of the controllers are either set directly or
+ * polled from an input provider whenever the game latches
+ * them. Buttons queued by the console take priority over the
+ * provider.
+ */
+#[derive(Default)]
+pub struct InputPorts {
+    controllers: [Controller; CONTROLLER_COUNT],
+    zapper: Zapper,
+    keyboard: FamilyKeyboard,
+    device: InputDevice,
+    provider: Option<Box<dyn InputProvider>>,
+    /* Buttons queued for each controller, latched instead of polling the provider */
+    queued_buttons: [Option<Buttons>; CONTROLLER_COUNT],
+
+    is_strobe: bool,
+    /* Bits read from each port of the Four Score since the strobe */
+    four_score_reads: [u8; 2],
+}
+
+impl InputPorts {
+    /* Initializes new ports with a controller in each */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Handles a write to $4016 */
+    pub fn write(&mut self, value: u8) {
+        self.is_strobe = value & 0x01 != 0;
+
+        if self.is_strobe {
+            self.four_score_reads = [0; 2];
+            self.poll_provider();
+        }
+
+        for controller in self.controllers.iter_mut() {
+            controller.write(value);
+        }
+
+        if self.device == InputDevice::FamilyKeyboard {
+            self.keyboard.write(value);
+        }
+    }
+
+    /* Reads a port, 0 for $4016 and 1 for $4017, the PPU being seen by light guns */
+    pub fn read(&mut self, port: usize, ppu: &PPU) -> u8 {
+        match (port, self.device) {
+            (_, InputDevice::FourScore) => self.read_four_score(port),
+            (1, InputDevice::Zapper) => self.zapper.read(ppu),
+            (1, InputDevice::FamilyKeyboard) => self.controllers[1].read() | self.keyboard.read(),
+            _ => self.controllers[port].read(),
+        }
+    }
+
+    /* Updates the buttons of every controller from the input provider, if there is one */
+    fn poll_provider(&mut self) {
+        if let Some(provider) = self.provider.as_mut() {
+            for port in Port::ALL {
+                let buttons = match self.queued_buttons[port.index()] {
+                    Some(buttons) => buttons,
+                    None => provider.poll(port),
+                };
+                self.controllers[port.index()].set_buttons(buttons);
+            }
+        }
+    }
+
+    /* Reads the next of the 24 bits the Four Score reports on a port */
+    fn read_four_score(&mut self, port: usize) -> u8 {
+        if self.is_strobe {
+            return self.controllers[port].read();
+        }
+
+        let reads = self.four_score_reads[port];
+        self.four_score_reads[port] = reads.saturating_add(1);
+
+        match reads {
+            0..8 => self.controllers[port].read(),
+            8..16 => self.controllers[port + 2].read(),
+            16..24 => (FOUR_SCORE_SIGNATURES[port] >> (reads - 16)) & 0x01,
+            _ => 0x01,
+        }
+    }
+
+    /* Registers the source the buttons are polled from when the game latches them */
+    pub fn set_provider<P: InputProvider + 'static>(&mut self, provider: P) {
+        self.provider = Some(Box::new(provider));
+    }
+
+    /* Removes the input provider, the buttons being set directly again */
+    pub fn clear_provider(&mut self) {
+        self.provider = None;
+    }
+
+    /*
+     * Holds buttons on a controller until the queued buttons are
+     * cleared, the input provider not being polled for it
+     */
+    pub fn set_queued_buttons(&mut self, index: usize, buttons: Buttons) {
+        self.queued_buttons[index] = Some(buttons);
+        self.controllers[index].set_buttons(buttons);
+    }
+
+    /* Hands every controller back to the input provider */
+    pub fn clear_queued_buttons(&mut self) {
+        self.queued_buttons = [None; CONTROLLER_COUNT];
+    }
+
+    /* Changes what is plugged into the ports */
+    pub fn set_device(&mut self, device: InputDevice) {
+        self.device = device;
+    }
+
+    /* What is plugged into the ports */
+    pub fn device(&self) -> InputDevice {
+        self.device
+    }
+
+    /* Number of controllers plugged in, directly or through the Four Score */
+    pub fn controller_count(&self) -> usize {
+        match self.device {
+            InputDevice::FourScore => CONTROLLER_COUNT,
+            InputDevice::Zapper => 1,
+            _ => 2,
+        }
+    }
+
+    /* Gives access to a controller, the last two being reached through the Four Score */
+    pub fn controller(&self, index: usize) -> &Controller {
+        &self.controllers[index]
+    }
+
+    /* Gives mutable access to a controller, between 0 and 3 */
+    pub fn controller_mut(&mut self, index: usize) -> &mut Controller {
+        &mut self.controllers[index]
+    }
+
+    /* Gives access to the Zapper, used when plugged into the second port */
+    pub fn zapper(&self) -> &Zapper {
+        &self.zapper
+    }
+
+    /* Gives mutable access to the Zapper, for the frontend to aim and shoot */
+    pub fn zapper_mut(&mut self) -> &mut Zapper {
+        &mut self.zapper
+    }
+
+    /* Gives access to the Family BASIC keyboard, used when on the expansion port */
+    pub fn keyboard(&self) -> &FamilyKeyboard {
+        &self.keyboard
+    }
+
+    /* Gives mutable access to the Family BASIC keyboard, for the frontend to type on it */
+    pub fn keyboard_mut(&mut self) -> &mut FamilyKeyboard {
+        &mut self.keyboard
+    }
+}
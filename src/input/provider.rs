@@ -0,0 +1,38 @@
+use crate::input::controller::Buttons;
+
+/* Controller a provider is polled for, the last two being reached through the Four Score */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Port {
+    One,
+    Two,
+    Three,
+    Four,
+}
+
+impl Port {
+    /* Every port, in the order of the controllers */
+    pub const ALL: [Port; 4] = [Port::One, Port::Two, Port::Three, Port::Four];
+
+    /* Index of the controller plugged into the port */
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/*
+ * A source of button states, such as a keyboard, a gamepad,
+ * a movie file or the network. The console polls it for
+ * every controller when the game latches the buttons, so
+ * sources can be swapped without touching the bus.
+ */
+pub trait InputProvider {
+    /* Buttons held down on the controller of a port */
+    fn poll(&mut self, port: Port) -> Buttons;
+}
+
+/* Closures make for quick scripted providers */
+impl<F: FnMut(Port) -> Buttons> InputProvider for F {
+    fn poll(&mut self, port: Port) -> Buttons {
+        self(port)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::ppu::{
+    frame::{FRAME_HEIGHT, FRAME_WIDTH},
+    ppu::PPU,
+};
+
+/*
+ * Number of scanlines the light sensor keeps reporting light
+ * after the beam drew a bright pixel in front of it
+ */
+pub const LIGHT_SCANLINES: u16 = 20;
+
+/* Distance in pixels around the aimed point the sensor sees */
+pub const LIGHT_RADIUS: isize = 2;
+
+/* Lowest brightness, as the sum of the RGB components, detected as light */
+pub const LIGHT_THRESHOLD: u16 = 0x55 * 3;
+
+/*
+ * The Zapper light gun, plugged into the second port.
+ *
+ * Reads return the trigger in bit 4 and the light sensor in
+ * bit 3, which is cleared while light is seen. The sensor
+ * only sees the pixels around where the gun points, and only
+ * for a short while after the beam drew them, so the picture
+ * being drawn by the PPU is sampled at the time of the read.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Zapper {
+    /* Pixel the gun points at, None when aimed off the screen */
+    position: Option<(usize, usize)>,
+    is_trigger_pulled: bool,
+}
+
+impl Zapper {
+    /* Initializes a new Zapper aimed off the screen */
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Aims the gun at a pixel of the picture, or off the screen */
+    pub fn set_position(&mut self, position: Option<(usize, usize)>) {
+        self.position = position.filter(|(x, y)| *x < FRAME_WIDTH && *y < FRAME_HEIGHT);
+    }
+
+    /* Pixel the gun points at */
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.position
+    }
+
+    /* Pulls or releases the trigger */
+    pub fn set_trigger(&mut self, pulled: bool) {
+        self.is_trigger_pulled = pulled;
+    }
+
+    /* Specifies whether the trigger is pulled */
+    pub fn is_trigger_pulled(&self) -> bool {
+        self.is_trigger_pulled
+    }
+
+    /* Reads the trigger and the light sensor, looking at the picture being drawn */
+    pub fn read(&self, ppu: &PPU) -> u8 {
+        let trigger = (self.is_trigger_pulled as u8) << 4;
+        let light = (!self.is_light_detected(ppu) as u8) << 3;
+
+        trigger | light
+    }
+
+    /*
+     * Whether a bright pixel around the aimed point was drawn
+     * during the last few scanlines
+     */
+    fn is_light_detected(&self, ppu: &PPU) -> bool {
+        let Some((x, y)) = self.position else {
+            return false;
+        };
+
+        let scanline = ppu.scanline() as isize;
+        let dot = ppu.dot() as isize;
+
+        for offset_y in -LIGHT_RADIUS..=LIGHT_RADIUS {
+            let pixel_y = y as isize + offset_y;
+
+            if pixel_y < 0 || pixel_y >= FRAME_HEIGHT as isize {
+                continue;
+            }
+
+            /* The pixels of the line have to be drawn, and not be faded away yet */
+            let elapsed = scanline - pixel_y;
+
+            if elapsed < 0 || elapsed > LIGHT_SCANLINES as isize {
+                continue;
+            }
+
+            for offset_x in -LIGHT_RADIUS..=LIGHT_RADIUS {
+                let pixel_x = x as isize + offset_x;
+
+                if pixel_x < 0 || pixel_x >= FRAME_WIDTH as isize {
+                    continue;
+                }
+
+                /* Dot 1 draws the first pixel of the line */
+                if elapsed == 0 && pixel_x >= dot - 1 {
+                    continue;
+                }
+
+                if Self::is_bright(ppu, pixel_x as usize, pixel_y as usize) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /* Whether a pixel of the picture is bright enough to be seen by the sensor */
+    fn is_bright(ppu: &PPU, x: usize, y: usize) -> bool {
+        let color = ppu.palette().get_color(ppu.frame().get_pixel(x, y));
+        let brightness: u16 = color.iter().map(|component| *component as u16).sum();
+
+        brightness >= LIGHT_THRESHOLD
+    }
+}
@@ -0,0 +1,15 @@
+#![allow(clippy::upper_case_acronyms, clippy::module_inception)]
+
+pub mod apu;
+pub mod bus;
+pub mod cartridge;
+pub mod cheats;
+pub mod console;
+pub mod cpu;
+pub mod errors;
+pub mod input;
+pub mod memory;
+pub mod nsf;
+pub mod patch;
+pub mod ppu;
+pub mod state;
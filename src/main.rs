@@ -1,33 +1,256 @@
-use crate::{
-    bus::{cpu_bus::CpuBus, ppu_bus::PpuBus},
-    cartridge::cartridge::Cartridge,
-    cpu::cpu::CPU,
-    memory::memory::Memory,
-    ppu::ppu::PPU,
-};
-use std::{rc::Rc, thread, time::Duration};
+mod frontend;
 
-mod bus;
-mod cartridge;
-mod cpu;
-mod errors;
-mod memory;
-mod ppu;
+use frontend::pacer::{FastForwardSpeed, FramePacer, SyncMode};
+use nes_sandbox::{
+    cartridge::{battery::BatterySave, cartridge::Cartridge},
+    cheats::cheats::CheatList,
+    console::console::Console,
+    errors::AppResult,
+};
+use std::{env, process};
 
 fn main() {
-    let ram = Memory::new(65536);
-    let cartridge = Rc::new(Cartridge::new(&[]).unwrap());
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: nes-sandbox <rom> [patch]");
+        process::exit(1);
+    };
+
+    /* Patches next to the ROM are found on their own, others can be given */
+    let cartridge = match env::args().nth(2) {
+        Some(patch_path) => Cartridge::from_file_with_patch(&path, patch_path),
+        None => Cartridge::from_file(&path),
+    };
+    let cartridge = cartridge.unwrap_or_else(|err| {
+        eprintln!("failed to load {}: {}", path, err);
+        process::exit(1);
+    });
+    let mut console = Console::new(cartridge);
+
+    /* Battery backed games keep their saves next to the ROM */
+    if let Err(err) = console.set_battery_save_path(BatterySave::path_for_rom(&path)) {
+        eprintln!("failed to load the save file: {}", err);
+    }
+
+    /* So are their cheats */
+    let cheats_path = CheatList::path_for_rom(&path);
+
+    if cheats_path.is_file() {
+        match CheatList::from_file(&cheats_path) {
+            Ok(cheats) => *console.cheats_mut() = cheats,
+            Err(err) => eprintln!("failed to load the cheats: {}", err),
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    let mut fast_forward_audio = frontend::audio::FastForwardAudio::default();
+    #[cfg(feature = "audio")]
+    let mut audio = {
+        /* Channels can be spread apart with a fake stereo effect */
+        if std::env::var("NES_FAKE_STEREO").is_ok() {
+            console.set_stereo(true);
+            console.set_fake_stereo_pans();
+        }
+
+        /* Games keep running without sound when there is no audio device */
+        match frontend::audio::AudioOutput::new(console.audio_channel_count()) {
+            Ok(mut audio) => {
+                console.set_sample_rate(audio.sample_rate());
+
+                /* Dynamic rate control can be tuned, or disabled with 0 */
+                if let Some(delta) = std::env::var("NES_MAX_RATE_DELTA")
+                    .ok()
+                    .and_then(|delta| delta.parse().ok())
+                {
+                    audio.set_max_rate_delta(delta);
+                }
 
-    let ppu_bus = PpuBus::new(cartridge.clone());
-    let ppu = PPU::new(ppu_bus);
+                /* Fast-forwarding skips audio it has no room for, or mutes it with NES_FAST_FORWARD_AUDIO */
+                if let Ok(name) = std::env::var("NES_FAST_FORWARD_AUDIO") {
+                    match frontend::audio::FastForwardAudio::from_name(&name) {
+                        Some(mode) => fast_forward_audio = mode,
+                        None => eprintln!("unknown fast-forward audio {}", name),
+                    }
+                }
+                Some(audio)
+            }
+            Err(err) => {
+                eprintln!(
+                    "failed to open the audio device, running without sound: {}",
+                    err
+                );
+                None
+            }
+        }
+    };
+    #[cfg(feature = "audio")]
+    let mut samples = Vec::new();
+
+    /*
+     * Frames are paced by the refresh of the display when close
+     * enough to the console, by the audio device otherwise. The
+     * clock can be chosen with NES_SYNC=vsync|audio|timer.
+     */
+    let sync = match std::env::var("NES_SYNC") {
+        Ok(name) => SyncMode::from_name(&name).unwrap_or_else(|| {
+            eprintln!("unknown sync mode {}, using vsync", name);
+            SyncMode::default()
+        }),
+        Err(_) => SyncMode::default(),
+    };
 
-    let cpu_bus = CpuBus::new(ram, ppu, cartridge.clone());
-    let mut cpu = CPU::new(cpu_bus);
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    let mut window = frontend::Window::new(sync, console.region()).unwrap_or_else(|err| {
+        eprintln!("failed to open the window: {}", err);
+        process::exit(1);
+    });
+    /* Dots can be widened to their shape on a television, and the overscan cropped */
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    window.set_scaling(frontend::scaling::Scaling {
+        is_aspect_corrected: std::env::var("NES_PIXEL_ASPECT").is_ok(),
+        is_overscan_cropped: std::env::var("NES_CROP_OVERSCAN").is_ok(),
+    });
 
-    loop {
-        thread::sleep(Duration::from_secs(1));
-        if let Err(err) = cpu.clock() {
-            panic!("{}", err);
+    /* Fullscreen can be borderless or exclusive, starting in it with NES_FULLSCREEN */
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    if let Ok(name) = std::env::var("NES_FULLSCREEN") {
+        match frontend::scaling::FullscreenMode::from_name(&name) {
+            Some(mode) => {
+                window.set_fullscreen_mode(mode);
+
+                if let Err(err) = window.set_fullscreen(true) {
+                    eprintln!("failed to switch fullscreen: {}", err);
+                }
+            }
+            None => eprintln!("unknown fullscreen mode {}", name),
         }
     }
+
+    /*
+     * Keys and gamepads drive the controllers as mapped by the
+     * bindings file, the console polling them whenever the game
+     * latches the buttons
+     */
+    #[cfg(any(feature = "sdl", feature = "winit", feature = "gamepad"))]
+    {
+        use nes_sandbox::input::bindings::{BINDINGS_FILE_NAME, Bindings};
+
+        let bindings = Bindings::load(BINDINGS_FILE_NAME).unwrap_or_else(|err| {
+            eprintln!("failed to load the bindings: {}", err);
+            Bindings::default()
+        });
+        let mut input = frontend::input::HostInput::new(bindings);
+
+        #[cfg(any(feature = "sdl", feature = "winit"))]
+        input.set_held_keys(window.held_keys());
+
+        /* Keys still work when gamepads cannot be reached */
+        #[cfg(feature = "gamepad")]
+        match frontend::gamepad::GamepadInput::new() {
+            Ok(gamepads) => input.set_gamepads(gamepads),
+            Err(err) => eprintln!("failed to open the gamepads: {}", err),
+        }
+
+        console.set_input_provider(input);
+    }
+
+    #[cfg(any(feature = "sdl", feature = "winit"))]
+    let is_vsync_enabled = window.is_vsync_enabled();
+    #[cfg(not(any(feature = "sdl", feature = "winit")))]
+    let is_vsync_enabled = false;
+
+    #[cfg(feature = "audio")]
+    let has_audio = audio.is_some();
+    #[cfg(not(feature = "audio"))]
+    let has_audio = false;
+
+    let sync = match sync {
+        SyncMode::Vsync if !is_vsync_enabled => SyncMode::Audio,
+        sync => sync,
+    };
+
+    /* Without an audio device to follow, the host clock is used */
+    let sync = match sync {
+        SyncMode::Audio if !has_audio => SyncMode::Timer,
+        sync => sync,
+    };
+    let mut pacer = FramePacer::new(console.region(), sync);
+
+    /* Fast-forwarding can be capped with NES_FAST_FORWARD_SPEED=2x|4x|unlimited */
+    let fast_forward_speed = match std::env::var("NES_FAST_FORWARD_SPEED") {
+        Ok(name) => FastForwardSpeed::from_name(&name).unwrap_or_else(|| {
+            eprintln!("unknown fast-forward speed {}, using unlimited", name);
+            FastForwardSpeed::default()
+        }),
+        Err(_) => FastForwardSpeed::default(),
+    };
+
+    /* Errors stop the emulation, the save file is still written before exiting */
+    let result: AppResult<()> = loop {
+        #[cfg(any(feature = "sdl", feature = "winit"))]
+        if !window.handle_events(&mut console) {
+            break Ok(());
+        }
+
+        #[cfg(any(feature = "sdl", feature = "winit"))]
+        let is_fast_forwarding = window.is_fast_forwarding();
+        #[cfg(not(any(feature = "sdl", feature = "winit")))]
+        let is_fast_forwarding = false;
+
+        if console.is_paused() != pacer.is_paused() {
+            pacer.set_paused(console.is_paused(), console.cycles());
+        }
+
+        if is_fast_forwarding != pacer.is_fast_forwarding() {
+            let speed = is_fast_forwarding.then_some(fast_forward_speed);
+            pacer.set_fast_forward(speed, console.cycles());
+
+            #[cfg(feature = "audio")]
+            if let Some(audio) = audio.as_mut() {
+                audio.set_fast_forward(is_fast_forwarding.then_some(fast_forward_audio));
+            }
+        }
+
+        if let Err(err) = console.step_frame() {
+            break Err(err);
+        }
+
+        if let Some(err) = console.take_save_error() {
+            eprintln!("failed to write the save file: {}", err);
+        }
+
+        #[cfg(feature = "audio")]
+        {
+            console.take_samples(&mut samples);
+
+            if let Some(audio) = audio.as_mut() {
+                audio.queue(&samples);
+                console.set_sample_rate_adjustment(audio.rate_adjustment());
+            }
+            samples.clear();
+        }
+
+        /* Frames the display has no time to show are skipped while fast-forwarding */
+        if pacer.should_present() {
+            #[cfg(any(feature = "sdl", feature = "winit"))]
+            if let Err(err) = window.present(&mut console) {
+                break Err(err);
+            }
+        }
+
+        #[cfg(feature = "audio")]
+        let audio_ahead = audio.as_ref().map(|audio| audio.queued_ahead());
+        #[cfg(not(feature = "audio"))]
+        let audio_ahead = None;
+
+        pacer.wait(console.cycles(), audio_ahead);
+    };
+
+    if let Err(err) = console.flush_battery_save() {
+        eprintln!("failed to write the save file: {}", err);
+    }
+
+    if let Err(err) = result {
+        eprintln!("emulation stopped: {}", err);
+        process::exit(1);
+    }
 }
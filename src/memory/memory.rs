@@ -1,3 +1,7 @@
+use crate::{
+    errors::AppResult,
+    state::state::{StateReader, StateWriter},
+};
 use std::cell::RefCell;
 
 /*
@@ -18,20 +22,45 @@ impl Memory {
     }
 
     /* Reading from specific address */
-    pub fn read(&self, address: u16) -> u8 {
-        self.cells.borrow()[address as usize]
+    pub fn read(&self, address: usize) -> u8 {
+        self.cells.borrow()[address]
     }
 
     /* Writing to a specific address */
-    pub fn write(&self, address: u16, value: u8) {
-        self.cells.borrow_mut()[address as usize] = value;
+    pub fn write(&self, address: usize, value: u8) {
+        self.cells.borrow_mut()[address] = value;
     }
 
     /* Writing vector of data starting from a specific address */
-    pub fn write_chunk(&self, address: u16, value: &[u8]) {
-        let start = address as usize;
+    pub fn write_chunk(&self, address: usize, value: &[u8]) {
+        let start = address;
         let end = start + value.len();
 
         self.cells.borrow_mut()[start..end].copy_from_slice(value);
     }
+
+    /* Copies the whole content of the memory */
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.cells.borrow().clone()
+    }
+
+    /* Number of bytes the memory holds */
+    pub fn len(&self) -> usize {
+        self.cells.borrow().len()
+    }
+
+    /* Specifies whether the memory holds no bytes at all */
+    pub fn is_empty(&self) -> bool {
+        self.cells.borrow().is_empty()
+    }
+
+    /* Saves the whole content of the memory */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.cells.borrow());
+    }
+
+    /* Restores the content of the memory, which must have the same size */
+    pub fn load_state(&self, state: &mut StateReader) -> AppResult<()> {
+        state.read_bytes(&mut self.cells.borrow_mut())
+    }
 }
@@ -0,0 +1,106 @@
+use crate::{memory::memory::Memory, nsf::nsf::Nsf};
+use std::cell::Cell;
+
+/* Registers selecting the 4KB bank mapped to each slot of $8000-$FFFF */
+pub const BANK_REGISTERS_ADDRESS_LO: u16 = 0x5FF8;
+pub const BANK_REGISTERS_ADDRESS_HI: u16 = 0x5FFF;
+pub const WORK_RAM_ADDRESS_LO: u16 = 0x6000;
+pub const WORK_RAM_ADDRESS_HI: u16 = 0x7FFF;
+pub const PRG_ADDRESS_LO: u16 = 0x8000;
+
+/*
+ * Address of an endless loop the CPU is parked in between
+ * calls to the NSF routines, which return to it
+ */
+pub const IDLE_LOOP_ADDRESS: u16 = 0x5FF0;
+
+/* Size of a bank of music data */
+pub const BANK_SIZE: usize = 0x1000;
+
+/*
+ * Stands in for a cartridge while playing an NSF file. It
+ * maps the music data through eight 4KB banks, provides 8KB
+ * of work RAM and the idle loop used by the player.
+ */
+pub struct NsfBoard {
+    prg: Memory,
+    work_ram: Memory,
+    banks: Cell<[u8; 8]>,
+    is_bank_switched: bool,
+}
+
+impl NsfBoard {
+    /* Initializes a new board holding the music data of the given file */
+    pub fn new(nsf: &Nsf) -> Self {
+        let is_bank_switched = nsf.is_bank_switched();
+
+        /* Without banking, the data is simply placed at its load address */
+        let padding = if is_bank_switched {
+            nsf.load_address() as usize & (BANK_SIZE - 1)
+        } else {
+            (nsf.load_address() - PRG_ADDRESS_LO) as usize
+        };
+
+        let size = (padding + nsf.data().len()).div_ceil(BANK_SIZE) * BANK_SIZE;
+        let prg = Memory::new(size.max(8 * BANK_SIZE));
+        let data_size = nsf.data().len().min(prg.len() - padding);
+        prg.write_chunk(padding, &nsf.data()[..data_size]);
+
+        let board = Self {
+            prg,
+            work_ram: Memory::new(0x2000),
+            banks: Cell::new([0, 1, 2, 3, 4, 5, 6, 7]),
+            is_bank_switched,
+        };
+        board.reset(nsf);
+
+        board
+    }
+
+    /* Maps the initial banks of the file back, as done before starting a song */
+    pub fn reset(&self, nsf: &Nsf) {
+        if self.is_bank_switched {
+            self.banks.set(nsf.bank_init());
+        }
+
+        for address in 0..self.work_ram.len() {
+            self.work_ram.write(address, 0);
+        }
+    }
+
+    /* Reads a CPU address between $4020 and $FFFF */
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            IDLE_LOOP_ADDRESS => 0x4C,
+            0x5FF1 => IDLE_LOOP_ADDRESS as u8,
+            0x5FF2 => (IDLE_LOOP_ADDRESS >> 8) as u8,
+            WORK_RAM_ADDRESS_LO..=WORK_RAM_ADDRESS_HI => {
+                self.work_ram.read((address - WORK_RAM_ADDRESS_LO) as usize)
+            }
+            PRG_ADDRESS_LO..=0xFFFF => {
+                let slot = (address - PRG_ADDRESS_LO) as usize / BANK_SIZE;
+                let bank = self.banks.get()[slot] as usize;
+                let address =
+                    (bank * BANK_SIZE + (address as usize & (BANK_SIZE - 1))) % self.prg.len();
+
+                self.prg.read(address)
+            }
+            _ => 0,
+        }
+    }
+
+    /* Writes a CPU address between $4020 and $FFFF */
+    pub fn write(&self, address: u16, value: u8) {
+        match address {
+            BANK_REGISTERS_ADDRESS_LO..=BANK_REGISTERS_ADDRESS_HI if self.is_bank_switched => {
+                let mut banks = self.banks.get();
+                banks[(address - BANK_REGISTERS_ADDRESS_LO) as usize] = value;
+                self.banks.set(banks);
+            }
+            WORK_RAM_ADDRESS_LO..=WORK_RAM_ADDRESS_HI => self
+                .work_ram
+                .write((address - WORK_RAM_ADDRESS_LO) as usize, value),
+            _ => {}
+        }
+    }
+}
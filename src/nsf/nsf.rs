@@ -0,0 +1,170 @@
+use crate::{
+    apu::apu::ExpansionChips,
+    console::region::Region,
+    errors::{AppError, AppResult},
+};
+use std::{fs, path::Path};
+
+/* Size of the NSF header, the music data follows right after it */
+pub const NSF_HEADER_SIZE: usize = 0x80;
+
+/* Play routine periods used when the header leaves them empty, in microseconds */
+pub const NTSC_DEFAULT_PLAY_SPEED: u16 = 16639;
+pub const PAL_DEFAULT_PLAY_SPEED: u16 = 19997;
+
+/*
+ * Represents an NSF file, holding the music of a game along
+ * with the addresses of the routines playing it, stripped
+ * of everything else.
+ */
+#[derive(Debug, Clone)]
+pub struct Nsf {
+    song_count: u8,
+    starting_song: u8,
+    load_address: u16,
+    init_address: u16,
+    play_address: u16,
+    name: String,
+    artist: String,
+    copyright: String,
+    ntsc_play_speed: u16,
+    pal_play_speed: u16,
+    /* Banks initially mapped to $8000-$FFFF, all 0 if banking is unused */
+    bank_init: [u8; 8],
+    /* Region bits, bit 0 for PAL and bit 1 for both regions */
+    region_flags: u8,
+    expansion_chips: ExpansionChips,
+    data: Vec<u8>,
+}
+
+impl Nsf {
+    /* Parses the content of an NSF file */
+    pub fn new(bytes: &[u8]) -> AppResult<Self> {
+        if bytes.len() < NSF_HEADER_SIZE || &bytes[0..5] != b"NESM\x1A" {
+            return Err(AppError::InvalidNsfFile);
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+
+        let nsf = Self {
+            song_count: bytes[0x06],
+            starting_song: bytes[0x07],
+            load_address: read_u16(0x08),
+            init_address: read_u16(0x0A),
+            play_address: read_u16(0x0C),
+            name: Self::get_text(&bytes[0x0E..0x2E]),
+            artist: Self::get_text(&bytes[0x2E..0x4E]),
+            copyright: Self::get_text(&bytes[0x4E..0x6E]),
+            ntsc_play_speed: read_u16(0x6E),
+            pal_play_speed: read_u16(0x78),
+            bank_init: bytes[0x70..0x78].try_into().unwrap(),
+            region_flags: bytes[0x7A],
+            expansion_chips: ExpansionChips::from_bits_truncate(bytes[0x7B]),
+            data: bytes[NSF_HEADER_SIZE..].to_vec(),
+        };
+
+        if nsf.song_count == 0 || (!nsf.is_bank_switched() && nsf.load_address < 0x8000) {
+            return Err(AppError::InvalidNsfFile);
+        }
+
+        Ok(nsf)
+    }
+
+    /* Loads an NSF file from disk */
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let bytes = fs::read(path)?;
+
+        Self::new(&bytes)
+    }
+
+    /* Number of songs in the file */
+    pub fn song_count(&self) -> u8 {
+        self.song_count
+    }
+
+    /* Index of the song to play first, starting from 0 */
+    pub fn starting_song(&self) -> u8 {
+        self.starting_song
+            .saturating_sub(1)
+            .min(self.song_count - 1)
+    }
+
+    /* Address the music data is loaded at */
+    pub fn load_address(&self) -> u16 {
+        self.load_address
+    }
+
+    /* Address of the routine setting up a song */
+    pub fn init_address(&self) -> u16 {
+        self.init_address
+    }
+
+    /* Address of the routine called at a fixed rate while a song plays */
+    pub fn play_address(&self) -> u16 {
+        self.play_address
+    }
+
+    /* Title of the game or album */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /* Composer of the music */
+    pub fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    /* Copyright holder and year of the music */
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+
+    /* Period of the play routine for the given region, in microseconds */
+    pub fn play_speed(&self, region: Region) -> u16 {
+        match region {
+            Region::Ntsc if self.ntsc_play_speed != 0 => self.ntsc_play_speed,
+            Region::Ntsc => NTSC_DEFAULT_PLAY_SPEED,
+            Region::Pal | Region::Dendy if self.pal_play_speed != 0 => self.pal_play_speed,
+            Region::Pal | Region::Dendy => PAL_DEFAULT_PLAY_SPEED,
+        }
+    }
+
+    /* The region the music was written for, NTSC when it supports both */
+    pub fn region(&self) -> Region {
+        if self.region_flags & 0x03 == 0x01 {
+            Region::Pal
+        } else {
+            Region::Ntsc
+        }
+    }
+
+    /* Specifies whether the music data is mapped through 4KB banks */
+    pub fn is_bank_switched(&self) -> bool {
+        self.bank_init.iter().any(|bank| *bank != 0)
+    }
+
+    /* Banks mapped to $8000-$FFFF when a song starts */
+    pub fn bank_init(&self) -> [u8; 8] {
+        self.bank_init
+    }
+
+    /* Expansion sound chips used by the music */
+    pub fn expansion_chips(&self) -> ExpansionChips {
+        self.expansion_chips
+    }
+
+    /* The music data, loaded at load_address */
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /* Reads a null terminated string field of the header */
+    fn get_text(bytes: &[u8]) -> String {
+        let length = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+
+        String::from_utf8_lossy(&bytes[..length]).into_owned()
+    }
+}
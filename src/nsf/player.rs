@@ -0,0 +1,163 @@
+use crate::{
+    apu::apu::{FRAME_COUNTER_ADDRESS, STATUS_ADDRESS},
+    bus::cpu_bus::APU_REGISTERS_ADDRESS_LO,
+    cartridge::cartridge::Cartridge,
+    console::{console::Console, region::Region},
+    errors::AppResult,
+    nsf::{board::IDLE_LOOP_ADDRESS, nsf::Nsf},
+};
+
+/*
+ * Plays the songs of an NSF file on an emulated console.
+ *
+ * There is no game to drive the music, so the player does
+ * it itself: the INIT routine is called when a song starts,
+ * then the PLAY routine at the rate given by the file. The
+ * CPU waits in an endless loop in between, the routines
+ * returning to it.
+ */
+pub struct NsfPlayer {
+    console: Console,
+    nsf: Nsf,
+    song: u8,
+
+    /* PPU dots between two calls of the play routine */
+    play_period: f64,
+    play_counter: f64,
+    is_play_pending: bool,
+}
+
+impl NsfPlayer {
+    /* Initializes a new player, starting the first song of the file */
+    pub fn new(nsf: Nsf) -> Self {
+        let mut console = Console::new(Cartridge::from_nsf(&nsf));
+        console.set_region(nsf.region());
+
+        let song = nsf.starting_song();
+
+        let mut player = Self {
+            console,
+            nsf,
+            song,
+            play_period: 0.0,
+            play_counter: 0.0,
+            is_play_pending: false,
+        };
+        player.play_song(song);
+
+        player
+    }
+
+    /* The file being played */
+    pub fn nsf(&self) -> &Nsf {
+        &self.nsf
+    }
+
+    /* Index of the song being played, starting from 0 */
+    pub fn song(&self) -> u8 {
+        self.song
+    }
+
+    /* Number of songs in the file */
+    pub fn song_count(&self) -> u8 {
+        self.nsf.song_count()
+    }
+
+    /*
+     * Starts playing a song from the beginning, resetting the
+     * console and calling the INIT routine of the file
+     */
+    pub fn play_song(&mut self, song: u8) {
+        self.song = song.min(self.nsf.song_count() - 1);
+
+        let region = self.console.region();
+        self.console.reset();
+
+        let cpu = self.console.cpu_mut();
+        let bus = cpu.bus_mut();
+
+        for address in 0x0000..0x0800 {
+            bus.write(address, 0);
+        }
+
+        if let Some(board) = bus.cartridge().nsf_board() {
+            board.reset(&self.nsf);
+        }
+
+        for address in APU_REGISTERS_ADDRESS_LO..=APU_REGISTERS_ADDRESS_LO + 0x13 {
+            bus.write(address, 0);
+        }
+
+        bus.write(APU_REGISTERS_ADDRESS_LO + STATUS_ADDRESS, 0x00);
+        bus.write(APU_REGISTERS_ADDRESS_LO + STATUS_ADDRESS, 0x0F);
+        bus.write(APU_REGISTERS_ADDRESS_LO + FRAME_COUNTER_ADDRESS, 0x40);
+
+        let x = if region == Region::Pal { 1 } else { 0 };
+        cpu.call_subroutine(self.nsf.init_address(), IDLE_LOOP_ADDRESS, self.song, x);
+
+        let (dots, cycles) = region.dots_per_cpu_cycle();
+        let play_speed = self.nsf.play_speed(region) as f64 / 1_000_000.0;
+
+        self.play_period = play_speed * region.cpu_clock_rate() * dots as f64 / cycles as f64;
+        self.play_counter = 0.0;
+        self.is_play_pending = false;
+    }
+
+    /* Starts the next song, wrapping around after the last one */
+    pub fn next_song(&mut self) {
+        self.play_song((self.song + 1) % self.nsf.song_count());
+    }
+
+    /* Starts the previous song, wrapping around before the first one */
+    pub fn previous_song(&mut self) {
+        let song_count = self.nsf.song_count();
+        self.play_song((self.song + song_count - 1) % song_count);
+    }
+
+    /*
+     * Advances the console by a single PPU dot, calling the play
+     * routine once it is due and the CPU is done with the
+     * previous routine
+     */
+    pub fn clock(&mut self) -> AppResult<()> {
+        self.console.clock()?;
+
+        self.play_counter += 1.0;
+
+        if self.play_counter >= self.play_period {
+            self.play_counter -= self.play_period;
+            self.is_play_pending = true;
+        }
+
+        let cpu = self.console.cpu_mut();
+
+        if self.is_play_pending && cpu.pc() == IDLE_LOOP_ADDRESS && cpu.is_at_instruction_boundary()
+        {
+            cpu.call_subroutine(self.nsf.play_address(), IDLE_LOOP_ADDRESS, 0, 0);
+            self.is_play_pending = false;
+        }
+
+        Ok(())
+    }
+
+    /* Runs the player for the duration of a video frame */
+    pub fn step_frame(&mut self) -> AppResult<()> {
+        let frame = self.console.frame_number();
+
+        while self.console.frame_number() == frame {
+            self.clock()?;
+        }
+
+        Ok(())
+    }
+
+    /* Gives access to the console, mainly to collect its audio samples */
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+
+    /* Gives mutable access to the console */
+    pub fn console_mut(&mut self) -> &mut Console {
+        &mut self.console
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    errors::{AppError, AppResult},
+    patch::patch::{self, CHECKSUMS_SIZE, PatchReader},
+};
+
+pub const BPS_MAGIC: &[u8] = b"BPS1";
+
+/* Actions building the target ROM, told by the lower 2 bits of each command */
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+/*
+ * Applies a BPS patch. The target ROM is built from start to
+ * end by commands copying bytes either from the patch, from
+ * the source ROM or from the target already built, which lets
+ * data move around. The CRC32 of both ROMs are checked.
+ */
+pub fn apply(rom: &[u8], patch: &[u8]) -> AppResult<Vec<u8>> {
+    let (source_crc, target_crc) = patch::get_checksums(patch)?;
+    patch::check_source(rom, source_crc)?;
+
+    let mut reader = PatchReader::new(patch, BPS_MAGIC.len());
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+    let metadata_size = reader.read_number()?;
+    reader.read(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(AppError::InvalidPatch);
+    }
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    let end = patch.len() - CHECKSUMS_SIZE;
+
+    while reader.offset() < end {
+        let command = reader.read_number()?;
+        let length = (command >> 2) + 1;
+
+        match command & 0x03 {
+            SOURCE_READ => {
+                let start = target.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or(AppError::InvalidPatch)?;
+                target.extend_from_slice(bytes);
+            }
+            TARGET_READ => target.extend_from_slice(reader.read(length)?),
+            SOURCE_COPY => {
+                source_offset = get_relative_offset(source_offset, reader.read_number()?)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or(AppError::InvalidPatch)?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            TARGET_COPY => {
+                target_offset = get_relative_offset(target_offset, reader.read_number()?)?;
+
+                /* Copied one byte at a time, as the copy may overlap what it produces */
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or(AppError::InvalidPatch)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(AppError::InvalidPatch);
+    }
+
+    patch::check_target(&target, target_crc)?;
+
+    Ok(target)
+}
+
+/* Moves an offset by a signed amount, stored with its sign in the lowest bit */
+fn get_relative_offset(offset: usize, value: usize) -> AppResult<usize> {
+    let distance = value >> 1;
+
+    if value & 0x01 != 0 {
+        offset.checked_sub(distance).ok_or(AppError::InvalidPatch)
+    } else {
+        Ok(offset + distance)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    errors::{AppError, AppResult},
+    patch::patch::PatchReader,
+};
+
+pub const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+
+/*
+ * Applies an IPS patch. It is a list of records, each one
+ * overwriting the bytes at a 24 bit offset, either with the
+ * data it holds or with a single repeated byte. The ROM grows
+ * when a record writes past its end, and may be truncated to
+ * a size given after the end marker.
+ */
+pub fn apply(rom: &[u8], patch: &[u8]) -> AppResult<Vec<u8>> {
+    if !patch.starts_with(IPS_MAGIC) {
+        return Err(AppError::InvalidPatch);
+    }
+
+    let mut rom = rom.to_vec();
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+
+    loop {
+        let offset = reader.read(3)?;
+
+        if offset == IPS_EOF {
+            break;
+        }
+
+        let offset = get_number(offset);
+        let size = get_number(reader.read(2)?);
+
+        let (data, length) = if size == 0 {
+            let length = get_number(reader.read(2)?);
+            (None, length)
+        } else {
+            (Some(reader.read(size)?), size)
+        };
+
+        if rom.len() < offset + length {
+            rom.resize(offset + length, 0);
+        }
+
+        match data {
+            Some(data) => rom[offset..offset + length].copy_from_slice(data),
+            None => rom[offset..offset + length].fill(reader.read(1)?[0]),
+        }
+    }
+
+    /* Some patches cut the ROM down to a given size */
+    if let Ok(size) = reader.read(3) {
+        rom.truncate(get_number(size));
+    }
+
+    Ok(rom)
+}
+
+/* Big endian number of up to 3 bytes */
+fn get_number(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |number, byte| (number << 8) | *byte as usize)
+}
@@ -0,0 +1,4 @@
+pub mod bps;
+pub mod ips;
+pub mod patch;
+pub mod ups;
@@ -0,0 +1,135 @@
+use crate::{
+    cartridge::crc32::crc32,
+    errors::{AppError, AppResult},
+    patch::{
+        bps::{self, BPS_MAGIC},
+        ips::{self, IPS_MAGIC},
+        ups::{self, UPS_MAGIC},
+    },
+};
+use std::path::{Path, PathBuf};
+
+/* Size of the footer of BPS and UPS patches, holding three CRC32 */
+pub const CHECKSUMS_SIZE: usize = 12;
+
+/* Extensions of the patch files looked for next to a ROM */
+const PATCH_EXTENSIONS: [&str; 3] = ["bps", "ups", "ips"];
+
+/* Applies a patch to a ROM image, its format being told by its first bytes */
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> AppResult<Vec<u8>> {
+    if patch.starts_with(IPS_MAGIC) {
+        ips::apply(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        bps::apply(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        ups::apply(rom, patch)
+    } else {
+        Err(AppError::InvalidPatch)
+    }
+}
+
+/* Patch file sitting next to a ROM with the same name, if there is one */
+pub fn find_patch<P: AsRef<Path>>(rom_path: P) -> Option<PathBuf> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|extension| rom_path.as_ref().with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/*
+ * CRC32 of the source and the target ROMs found at the end of
+ * BPS and UPS patches, once the patch itself was checked
+ */
+pub fn get_checksums(patch: &[u8]) -> AppResult<(u32, u32)> {
+    if patch.len() < CHECKSUMS_SIZE {
+        return Err(AppError::InvalidPatch);
+    }
+
+    let footer = &patch[patch.len() - CHECKSUMS_SIZE..];
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(footer[offset..offset + 4].try_into().unwrap());
+
+    if crc32(&patch[..patch.len() - 4]) != read_u32(8) {
+        return Err(AppError::InvalidPatch);
+    }
+
+    Ok((read_u32(0), read_u32(4)))
+}
+
+/* Checks the ROM a patch is applied to is the one it was made for */
+pub fn check_source(rom: &[u8], expected: u32) -> AppResult<()> {
+    let actual = crc32(rom);
+
+    if actual != expected {
+        return Err(AppError::PatchSourceMismatch { expected, actual });
+    }
+
+    Ok(())
+}
+
+/* Checks the patched ROM is the one the patch was made to produce */
+pub fn check_target(rom: &[u8], expected: u32) -> AppResult<()> {
+    if crc32(rom) != expected {
+        return Err(AppError::InvalidPatch);
+    }
+
+    Ok(())
+}
+
+/* Walks over the bytes of a patch, failing on truncated ones */
+pub struct PatchReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    /* Initializes a new reader starting at the given offset */
+    pub fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Self { bytes, offset }
+    }
+
+    /* Offset of the next byte to be read */
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /* Reads the given number of bytes */
+    pub fn read(&mut self, length: usize) -> AppResult<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + length)
+            .ok_or(AppError::InvalidPatch)?;
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    /* Reads a single byte */
+    pub fn read_byte(&mut self) -> AppResult<u8> {
+        Ok(self.read(1)?[0])
+    }
+
+    /*
+     * Reads a number encoded the way BPS and UPS do, 7 bits per
+     * byte with the last byte flagged by bit 7. Every byte but
+     * the last also adds one, so each number has a single form.
+     */
+    pub fn read_number(&mut self) -> AppResult<usize> {
+        let mut number = 0usize;
+        let mut shift = 1usize;
+
+        loop {
+            let byte = self.read_byte()?;
+            number = number
+                .checked_add((byte & 0x7F) as usize * shift)
+                .ok_or(AppError::InvalidPatch)?;
+
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+
+            shift = shift.checked_shl(7).ok_or(AppError::InvalidPatch)?;
+            number += shift;
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{
+    errors::{AppError, AppResult},
+    patch::patch::{self, CHECKSUMS_SIZE, PatchReader},
+};
+
+pub const UPS_MAGIC: &[u8] = b"UPS1";
+
+/*
+ * Applies a UPS patch. Its records skip over a number of
+ * bytes, then XOR the ROM with the bytes they hold up to a
+ * terminating zero. The CRC32 of both ROMs are checked.
+ */
+pub fn apply(rom: &[u8], patch: &[u8]) -> AppResult<Vec<u8>> {
+    let (source_crc, target_crc) = patch::get_checksums(patch)?;
+    patch::check_source(rom, source_crc)?;
+
+    let mut reader = PatchReader::new(patch, UPS_MAGIC.len());
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+
+    if source_size != rom.len() {
+        return Err(AppError::InvalidPatch);
+    }
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+
+    let end = patch.len() - CHECKSUMS_SIZE;
+    let mut position = 0;
+
+    while reader.offset() < end {
+        position += reader.read_number()?;
+
+        loop {
+            let byte = reader.read_byte()?;
+
+            /* The terminating zero also stands for an unchanged byte */
+            if byte == 0 {
+                position += 1;
+                break;
+            }
+
+            if let Some(target) = target.get_mut(position) {
+                *target ^= byte;
+            }
+            position += 1;
+        }
+    }
+
+    patch::check_target(&target, target_crc)?;
+
+    Ok(target)
+}
@@ -0,0 +1,278 @@
+use crate::{
+    bus::ppu_bus::{NAMETABLE_SIZE, NAMETABLES_ADDRESS_LO, PALETTE_ADDRESS_LO},
+    ppu::{
+        frame::{FRAME_HEIGHT, FRAME_WIDTH},
+        ppu::{Control, PPU},
+        sprite::{OAM_SPRITES, Sprite},
+        tile::{TILE_SIZE, decode_tile},
+    },
+};
+
+/* Size of a pattern table laid out as a 16x16 grid of tiles */
+pub const PATTERN_TABLE_SIZE: usize = 128;
+
+/* Size of the four nametables laid out as a 2x2 grid */
+pub const NAMETABLES_WIDTH: usize = FRAME_WIDTH * 2;
+pub const NAMETABLES_HEIGHT: usize = FRAME_HEIGHT * 2;
+
+/* The sprite sheet lays out all OAM sprites as an 8x8 grid */
+pub const SPRITE_SHEET_COLUMNS: usize = 8;
+
+/* Number of entries of the palette memory */
+pub const PALETTE_ENTRIES: usize = 32;
+
+/* Color of the rectangle showing the visible part of the nametables */
+pub const VIEWPORT_COLOR: [u8; 3] = [255, 0, 255];
+
+/*
+ * A plain RGBA picture produced by the debugging views,
+ * separate from the frames generated by the PPU
+ */
+#[derive(Debug, Clone)]
+pub struct DebugImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl DebugImage {
+    /* Initializes a new black image */
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    /* Writes the RGB color of a specific pixel */
+    pub fn set_pixel(&mut self, x: usize, y: usize, [r, g, b]: [u8; 3]) {
+        let offset = (y * self.width + x) * 4;
+
+        self.pixels[offset..offset + 4].copy_from_slice(&[r, g, b, 0xFF]);
+    }
+}
+
+impl PPU {
+    /*
+     * Decodes both pattern tables into 128x128 images, coloring
+     * them with one of the 8 palettes currently in palette memory
+     */
+    pub fn pattern_tables(&self, palette: u8) -> [DebugImage; 2] {
+        [
+            self.pattern_table(0, palette),
+            self.pattern_table(1, palette),
+        ]
+    }
+
+    /* Decodes a single pattern table ($0000 or $1000) into a 128x128 image */
+    pub fn pattern_table(&self, table: u16, palette: u8) -> DebugImage {
+        let mut image = DebugImage::new(PATTERN_TABLE_SIZE, PATTERN_TABLE_SIZE);
+
+        for tile in 0..256 {
+            let address = (table << 12) | (tile << 4);
+            let pixels = self.get_debug_tile(address);
+
+            let tile_x = (tile as usize % 16) * 8;
+            let tile_y = (tile as usize / 16) * 8;
+
+            for (y, row) in pixels.iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    let color = self.get_debug_color(palette, pixel);
+                    image.set_pixel(tile_x + x, tile_y + y, color);
+                }
+            }
+        }
+
+        image
+    }
+
+    /*
+     * Renders the four logical nametables, as seen through the
+     * current mirroring, into a single 512x480 image. The scroll
+     * viewport can be outlined on top of it.
+     */
+    pub fn nametables(&self, show_viewport: bool) -> DebugImage {
+        let mut image = DebugImage::new(NAMETABLES_WIDTH, NAMETABLES_HEIGHT);
+
+        let pattern_table = if self.control().contains(Control::BACKGROUND_PATTERN) {
+            0x1000
+        } else {
+            0x0000
+        };
+
+        for nametable in 0..4 {
+            let base = NAMETABLES_ADDRESS_LO + nametable * NAMETABLE_SIZE;
+            let origin_x = (nametable as usize & 0x01) * FRAME_WIDTH;
+            let origin_y = (nametable as usize >> 1) * FRAME_HEIGHT;
+
+            for row in 0..30 {
+                for column in 0..32 {
+                    let tile = self.bus().peek(base + row * 32 + column) as u16;
+                    let palette = self.get_debug_attribute(base, row, column);
+
+                    let pixels = self.get_debug_tile(pattern_table | (tile << 4));
+
+                    for (y, pixel_row) in pixels.iter().enumerate() {
+                        for (x, &pixel) in pixel_row.iter().enumerate() {
+                            image.set_pixel(
+                                origin_x + column as usize * 8 + x,
+                                origin_y + row as usize * 8 + y,
+                                self.get_debug_color(palette, pixel),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if show_viewport {
+            self.draw_viewport(&mut image);
+        }
+
+        image
+    }
+
+    /*
+     * Resolves the 32 palette memory entries to the RGB colors
+     * they are currently displayed with, grayscale and emphasis
+     * bits of PPUMASK applied
+     */
+    pub fn palette_colors(&self) -> [[u8; 3]; PALETTE_ENTRIES] {
+        let mut colors = [[0; 3]; PALETTE_ENTRIES];
+
+        for (entry, color) in colors.iter_mut().enumerate() {
+            let value = self.bus().peek(PALETTE_ADDRESS_LO + entry as u16);
+            *color = self.palette().get_color(self.get_output_color(value));
+        }
+
+        colors
+    }
+
+    /* Decodes all 64 sprites currently in OAM */
+    pub fn sprites(&self) -> Vec<Sprite> {
+        self.oam()
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(index, bytes)| Sprite::new(index as u8, bytes))
+            .collect()
+    }
+
+    /*
+     * Draws every OAM sprite, flips applied, into a sheet of 8x8
+     * cells. Cells are 16 pixels tall when 8x16 sprites are used.
+     */
+    pub fn sprite_sheet(&self) -> DebugImage {
+        let height = if self.control().contains(Control::SPRITE_SIZE) {
+            16
+        } else {
+            8
+        };
+        let rows = OAM_SPRITES / SPRITE_SHEET_COLUMNS;
+        let mut image = DebugImage::new(SPRITE_SHEET_COLUMNS * 8, rows * height);
+
+        for sprite in self.sprites() {
+            let cell_x = (sprite.index as usize % SPRITE_SHEET_COLUMNS) * 8;
+            let cell_y = (sprite.index as usize / SPRITE_SHEET_COLUMNS) * height;
+
+            for y in 0..height {
+                let row = if sprite.flip_vertical {
+                    height - 1 - y
+                } else {
+                    y
+                };
+                let pixels = self.get_debug_tile(self.get_sprite_tile_address(&sprite, row));
+
+                for x in 0..8 {
+                    let column = if sprite.flip_horizontal { 7 - x } else { x };
+                    let pixel = pixels[row % 8][column];
+
+                    image.set_pixel(
+                        cell_x + x,
+                        cell_y + y,
+                        self.get_debug_color(sprite.palette + 4, pixel),
+                    );
+                }
+            }
+        }
+
+        image
+    }
+
+    /* Pattern address of the tile holding the given row of a sprite */
+    fn get_sprite_tile_address(&self, sprite: &Sprite, row: usize) -> u16 {
+        let tile = sprite.tile as u16;
+
+        if self.control().contains(Control::SPRITE_SIZE) {
+            ((tile & 0x01) << 12) | (((tile & 0xFE) + (row as u16 >> 3)) << 4)
+        } else if self.control().contains(Control::SPRITE_PATTERN) {
+            0x1000 | (tile << 4)
+        } else {
+            tile << 4
+        }
+    }
+
+    /* Palette of a nametable tile, taken from its attribute byte */
+    fn get_debug_attribute(&self, base: u16, row: u16, column: u16) -> u8 {
+        let attribute = self.bus().peek(base + 0x03C0 + (row / 4) * 8 + column / 4);
+        let shift = ((row & 0x02) << 1) | (column & 0x02);
+
+        (attribute >> shift) & 0x03
+    }
+
+    /*
+     * Outlines the 256x240 area visible on screen, wrapping
+     * around the edges like the scrolling itself does
+     */
+    fn draw_viewport(&self, image: &mut DebugImage) {
+        let (scroll_x, scroll_y) = self.get_scroll_origin();
+
+        for offset in 0..FRAME_WIDTH {
+            let x = (scroll_x + offset) % NAMETABLES_WIDTH;
+
+            image.set_pixel(x, scroll_y % NAMETABLES_HEIGHT, VIEWPORT_COLOR);
+            image.set_pixel(
+                x,
+                (scroll_y + FRAME_HEIGHT - 1) % NAMETABLES_HEIGHT,
+                VIEWPORT_COLOR,
+            );
+        }
+
+        for offset in 0..FRAME_HEIGHT {
+            let y = (scroll_y + offset) % NAMETABLES_HEIGHT;
+
+            image.set_pixel(scroll_x % NAMETABLES_WIDTH, y, VIEWPORT_COLOR);
+            image.set_pixel(
+                (scroll_x + FRAME_WIDTH - 1) % NAMETABLES_WIDTH,
+                y,
+                VIEWPORT_COLOR,
+            );
+        }
+    }
+
+    /* Reads and decodes the tile stored at the given pattern address */
+    pub(crate) fn get_debug_tile(&self, address: u16) -> [[u8; 8]; 8] {
+        let mut bytes = [0; TILE_SIZE];
+
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.bus().peek(address + offset as u16);
+        }
+
+        decode_tile(&bytes)
+    }
+
+    /*
+     * Resolves a pixel value of one of the 8 palettes to its RGB
+     * color, transparent pixels showing the backdrop color
+     */
+    pub(crate) fn get_debug_color(&self, palette: u8, pixel: u8) -> [u8; 3] {
+        let address = if pixel == 0 {
+            PALETTE_ADDRESS_LO
+        } else {
+            PALETTE_ADDRESS_LO + ((palette as u16 & 0x07) << 2) + pixel as u16
+        };
+
+        self.palette()
+            .get_color((self.bus().peek(address) & 0x3F) as u16)
+    }
+}
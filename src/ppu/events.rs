@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+/* Number of events kept by default before the oldest ones are dropped */
+pub const EVENT_LOG_CAPACITY: usize = 65536;
+
+/* Something noteworthy the PPU did or went through */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpuEventKind {
+    RegisterRead { register: u16, value: u8 },
+    RegisterWrite { register: u16, value: u8 },
+    Nmi,
+    SpriteZeroHit,
+}
+
+/* A single event tagged with the position of the beam it happened at */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpuEvent {
+    pub frame: u64,
+    pub scanline: u16,
+    pub dot: u16,
+    pub kind: PpuEventKind,
+}
+
+/*
+ * A bounded record of the PPU activity, feeding the event
+ * views of a debugger. Logging costs time on every register
+ * access so it is disabled until requested.
+ */
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    events: VecDeque<PpuEvent>,
+    capacity: usize,
+    enabled: bool,
+}
+
+impl EventLog {
+    /* Initializes a new disabled EventLog keeping up to capacity events */
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+            enabled: false,
+        }
+    }
+
+    /* Starts or stops recording events */
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /* Specifies whether events are being recorded */
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /* Records an event, dropping the oldest one when full */
+    pub fn push(&mut self, event: PpuEvent) {
+        if !self.enabled || self.capacity == 0 {
+            return;
+        }
+
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+
+        self.events.push_back(event);
+    }
+
+    /* All recorded events, oldest first */
+    pub fn events(&self) -> impl Iterator<Item = &PpuEvent> {
+        self.events.iter()
+    }
+
+    /* The recorded events that happened during a specific frame */
+    pub fn events_in_frame(&self, frame: u64) -> impl Iterator<Item = &PpuEvent> {
+        self.events.iter().filter(move |event| event.frame == frame)
+    }
+
+    /* Number of recorded events */
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /* Specifies whether no event was recorded */
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /* Forgets every recorded event */
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_CAPACITY)
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    errors::AppResult,
+    ppu::palette::Palette,
+    state::state::{StateReader, StateWriter},
+};
+
+/* Visible picture dimensions produced by the PPU */
+pub const FRAME_WIDTH: usize = 256;
+pub const FRAME_HEIGHT: usize = 240;
+
+/*
+ * Represents a single picture produced by the PPU.
+ *
+ * Each pixel is stored as a 9-bit color, an index into the
+ * NES master palette along with the emphasis bits active when
+ * it was drawn, rather than a final RGB color, so the frontend
+ * can decide how to convert it for display.
+ */
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pixels: Vec<u16>,
+}
+
+impl Frame {
+    /* Initializes a new blank Frame */
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; FRAME_WIDTH * FRAME_HEIGHT],
+        }
+    }
+
+    /* Width of the frame in pixels */
+    pub fn width(&self) -> usize {
+        FRAME_WIDTH
+    }
+
+    /* Height of the frame in pixels */
+    pub fn height(&self) -> usize {
+        FRAME_HEIGHT
+    }
+
+    /* Reads the color of a specific pixel */
+    pub fn get_pixel(&self, x: usize, y: usize) -> u16 {
+        self.pixels[y * FRAME_WIDTH + x]
+    }
+
+    /* Writes the color of a specific pixel */
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: u16) {
+        self.pixels[y * FRAME_WIDTH + x] = value;
+    }
+
+    /* All pixels of the frame in row-major order */
+    pub fn pixels(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    /* Converts the frame to RGBA bytes using the given palette */
+    pub fn to_rgba(&self, palette: &Palette) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+
+        for &color in &self.pixels {
+            let [r, g, b] = palette.get_color(color);
+            rgba.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+
+        rgba
+    }
+
+    /* Saves every pixel of the frame */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        for &pixel in &self.pixels {
+            state.write_u16(pixel);
+        }
+    }
+
+    /* Restores the pixels saved by save_state */
+    pub fn load_state(&mut self, state: &mut StateReader) -> AppResult<()> {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = state.read_u16()?;
+        }
+
+        Ok(())
+    }
+}
+
+/*
+ * A picture ready for display, as RGBA bytes. Its size is up
+ * to the stage producing it, upscalers and filters are free
+ * to change it.
+ */
+#[derive(Debug, Clone)]
+pub struct ProcessedFrame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/*
+ * A stage of the video output path turning the frames of the
+ * PPU into pictures for display, in place of the built-in
+ * palette conversion and filters
+ */
+pub type PostProcessor = Box<dyn FnMut(&Frame) -> ProcessedFrame>;
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
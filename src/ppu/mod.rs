@@ -1 +1,9 @@
+pub mod debug;
+pub mod events;
+pub mod frame;
+pub mod ntsc;
+pub mod palette;
 pub mod ppu;
+pub mod scroll;
+pub mod sprite;
+pub mod tile;
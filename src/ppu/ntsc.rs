@@ -0,0 +1,233 @@
+use crate::ppu::frame::{FRAME_HEIGHT, FRAME_WIDTH, Frame};
+use std::f32::consts::PI;
+
+/*
+ * The PPU outputs 8 samples of composite signal per pixel,
+ * while a cycle of the color subcarrier lasts 12 of them
+ */
+pub const SAMPLES_PER_PIXEL: usize = 8;
+pub const SAMPLES_PER_SUBCARRIER_CYCLE: usize = 12;
+
+/*
+ * Size of the picture produced by the NTSC filter. It doubles
+ * the horizontal resolution so the artifacts stay visible.
+ */
+pub const NTSC_OUTPUT_WIDTH: usize = FRAME_WIDTH * 2;
+pub const NTSC_OUTPUT_HEIGHT: usize = FRAME_HEIGHT;
+
+/*
+ * Voltage levels of the signal relative to sync, the low and
+ * high halves of the square wave for each of the 4 luminances
+ */
+const SIGNAL_LEVELS_LOW: [f32; 4] = [0.350, 0.518, 0.962, 1.550];
+const SIGNAL_LEVELS_HIGH: [f32; 4] = [1.094, 1.506, 1.962, 1.962];
+const SIGNAL_BLACK: f32 = 0.518;
+const SIGNAL_WHITE: f32 = 1.962;
+
+/* Ratio the signal is scaled by while an emphasis bit is active on it */
+const SIGNAL_EMPHASIS_ATTENUATION: f32 = 0.746;
+
+/*
+ * Phase shift, in samples, between the subcarrier of the PPU
+ * and the one a TV decodes with, giving the usual NTSC hues
+ */
+const DECODER_PHASE_OFFSET: f32 = 3.5;
+
+/* A TV expects a 2.2 gamma signal while the PPU is closer to 1.8 */
+const GAMMA_CORRECTION: f32 = 2.2 / 1.8;
+
+/* Selects how frames are converted to RGB for display */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VideoFilter {
+    #[default]
+    Rgb,
+    Ntsc,
+}
+
+/*
+ * Composite signal level produced for a sample of a 9-bit
+ * color, normalized so that black is 0 and white is 1.
+ *
+ * The chroma is a square wave alternating between a low and
+ * a high level, the hue picking when it happens within the
+ * subcarrier cycle. Emphasis bits attenuate the part of the
+ * wave matching the phase of their color.
+ */
+pub(crate) fn get_signal_level(color: u16, phase: usize) -> f32 {
+    let hue = (color & 0x0F) as usize;
+    let luminance = if hue > 13 {
+        1
+    } else {
+        ((color >> 4) & 0x03) as usize
+    };
+    let emphasis = (color >> 6) & 0x07;
+
+    let mut low = SIGNAL_LEVELS_LOW[luminance];
+    let mut high = SIGNAL_LEVELS_HIGH[luminance];
+
+    if hue == 0 {
+        low = high;
+    } else if hue > 12 {
+        high = low;
+    }
+
+    let is_in_phase = |hue: usize| (hue + phase) % SAMPLES_PER_SUBCARRIER_CYCLE < 6;
+
+    let mut signal = if is_in_phase(hue) { high } else { low };
+
+    if (emphasis & 0x01 != 0 && is_in_phase(0))
+        || (emphasis & 0x02 != 0 && is_in_phase(4))
+        || (emphasis & 0x04 != 0 && is_in_phase(8))
+    {
+        signal *= SIGNAL_EMPHASIS_ATTENUATION;
+    }
+
+    (signal - SIGNAL_BLACK) / (SIGNAL_WHITE - SIGNAL_BLACK)
+}
+
+/*
+ * Decodes a whole subcarrier cycle of a single 9-bit color
+ * into its YIQ components, like a TV does on a flat area
+ */
+pub(crate) fn get_color_yiq(color: u16) -> (f32, f32, f32) {
+    let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+
+    for phase in 0..SAMPLES_PER_SUBCARRIER_CYCLE {
+        let level = get_signal_level(color, phase);
+        let angle = PI * (phase as f32 + DECODER_PHASE_OFFSET) / 6.0;
+
+        y += level;
+        i += level * angle.cos();
+        q += level * angle.sin();
+    }
+
+    let samples = SAMPLES_PER_SUBCARRIER_CYCLE as f32;
+
+    (y / samples, i * 2.0 / samples, q * 2.0 / samples)
+}
+
+/* Converts an RGB color back to YIQ components, undoing the gamma correction */
+pub(crate) fn get_yiq_from_rgb(color: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = color.map(|value| (value as f32 / 255.0).powf(1.0 / GAMMA_CORRECTION));
+
+    (
+        0.299 * r + 0.587 * g + 0.114 * b,
+        0.596 * r - 0.274 * g - 0.322 * b,
+        0.211 * r - 0.523 * g + 0.312 * b,
+    )
+}
+
+/*
+ * Converts YIQ components decoded from the signal to RGB,
+ * applying the gamma correction expected by the display
+ */
+pub(crate) fn get_rgb_from_yiq(y: f32, i: f32, q: f32) -> [u8; 3] {
+    let to_byte = |value: f32| {
+        let value = value.max(0.0).powf(GAMMA_CORRECTION);
+        (value * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    [
+        to_byte(y + 0.946882 * i + 0.623557 * q),
+        to_byte(y - 0.274788 * i - 0.635691 * q),
+        to_byte(y - 1.108545 * i + 1.709007 * q),
+    ]
+}
+
+/*
+ * Simulates the composite video path between the PPU and a
+ * TV. The 9-bit colors of a frame are turned back into the
+ * signal the PPU generates, which is then decoded the way a
+ * TV would, bringing chroma fringing and dot crawl along.
+ */
+#[derive(Debug, Clone)]
+pub struct NtscFilter {
+    /* Hue rotation, in degrees */
+    pub hue: f32,
+    /* Multiplier applied to the decoded chroma */
+    pub saturation: f32,
+}
+
+impl NtscFilter {
+    /* Initializes a new filter with neutral settings */
+    pub fn new() -> Self {
+        Self {
+            hue: 0.0,
+            saturation: 1.0,
+        }
+    }
+
+    /*
+     * Converts a frame to RGBA bytes of NTSC_OUTPUT_WIDTH by
+     * NTSC_OUTPUT_HEIGHT pixels. The phase is the position of
+     * the subcarrier, in samples, at the start of the frame.
+     */
+    pub fn apply(&self, frame: &Frame, phase: usize) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(NTSC_OUTPUT_WIDTH * NTSC_OUTPUT_HEIGHT * 4);
+        let mut signal = vec![0.0f32; FRAME_WIDTH * SAMPLES_PER_PIXEL];
+
+        let (cos, sin) = self.get_decoder_carrier();
+
+        for y in 0..FRAME_HEIGHT {
+            /* Each scanline lasts 341 * 8 samples, shifting the phase by 4 */
+            let line_phase = (phase + y * 4) % SAMPLES_PER_SUBCARRIER_CYCLE;
+
+            for x in 0..FRAME_WIDTH {
+                let color = frame.get_pixel(x, y);
+
+                for sample in 0..SAMPLES_PER_PIXEL {
+                    let index = x * SAMPLES_PER_PIXEL + sample;
+                    signal[index] = get_signal_level(color, line_phase + index);
+                }
+            }
+
+            for x in 0..NTSC_OUTPUT_WIDTH {
+                let center = x * SAMPLES_PER_PIXEL / 2 + SAMPLES_PER_PIXEL / 4;
+                let start = center.saturating_sub(SAMPLES_PER_SUBCARRIER_CYCLE / 2);
+                let end = (start + SAMPLES_PER_SUBCARRIER_CYCLE).min(signal.len());
+
+                let (mut luma, mut i, mut q) = (0.0, 0.0, 0.0);
+
+                for (index, level) in signal.iter().enumerate().take(end).skip(start) {
+                    let carrier = (line_phase + index) % SAMPLES_PER_SUBCARRIER_CYCLE;
+
+                    luma += level;
+                    i += level * cos[carrier];
+                    q += level * sin[carrier];
+                }
+
+                let samples = SAMPLES_PER_SUBCARRIER_CYCLE as f32;
+                let chroma_gain = 2.0 * self.saturation / samples;
+
+                let [r, g, b] = get_rgb_from_yiq(luma / samples, i * chroma_gain, q * chroma_gain);
+                rgba.extend_from_slice(&[r, g, b, 0xFF]);
+            }
+        }
+
+        rgba
+    }
+
+    /*
+     * Sine and cosine of the subcarrier the TV decodes the
+     * chroma with, for each sample of its cycle
+     */
+    fn get_decoder_carrier(&self) -> ([f32; 12], [f32; 12]) {
+        let mut cos = [0.0; SAMPLES_PER_SUBCARRIER_CYCLE];
+        let mut sin = [0.0; SAMPLES_PER_SUBCARRIER_CYCLE];
+
+        for phase in 0..SAMPLES_PER_SUBCARRIER_CYCLE {
+            let angle = PI * (phase as f32 + DECODER_PHASE_OFFSET) / 6.0 + self.hue.to_radians();
+
+            cos[phase] = angle.cos();
+            sin[phase] = angle.sin();
+        }
+
+        (cos, sin)
+    }
+}
+
+impl Default for NtscFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    console::region::Region,
+    errors::{AppError, AppResult},
+    ppu::ntsc::{get_color_yiq, get_rgb_from_yiq, get_yiq_from_rgb},
+};
+use std::{fs, path::Path};
+
+/* Number of colors the PPU is able to generate */
+pub const PALETTE_COLORS: usize = 64;
+
+/*
+ * Number of colors in an extended palette, holding a copy of
+ * the 64 base colors for each of the 8 emphasis combinations
+ */
+pub const EXTENDED_PALETTE_COLORS: usize = PALETTE_COLORS * 8;
+
+/* A single color, as red, green and blue components */
+pub type Rgb = [u8; 3];
+
+/*
+ * The master palette of the NTSC 2C02 PPU. The PPU never
+ * outputs RGB values, so these are approximations of the
+ * composite signal as seen on a typical TV set.
+ */
+pub const NTSC_PALETTE: [Rgb; PALETTE_COLORS] = [
+    [84, 84, 84],
+    [0, 30, 116],
+    [8, 16, 144],
+    [48, 0, 136],
+    [68, 0, 100],
+    [92, 0, 48],
+    [84, 4, 0],
+    [60, 24, 0],
+    [32, 42, 0],
+    [8, 58, 0],
+    [0, 64, 0],
+    [0, 60, 0],
+    [0, 50, 60],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [152, 150, 152],
+    [8, 76, 196],
+    [48, 50, 236],
+    [92, 30, 228],
+    [136, 20, 176],
+    [160, 20, 100],
+    [152, 34, 32],
+    [120, 60, 0],
+    [84, 90, 0],
+    [40, 114, 0],
+    [8, 124, 0],
+    [0, 118, 40],
+    [0, 102, 120],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [236, 238, 236],
+    [76, 154, 236],
+    [120, 124, 236],
+    [176, 98, 236],
+    [228, 84, 236],
+    [236, 88, 180],
+    [236, 106, 100],
+    [212, 136, 32],
+    [160, 170, 0],
+    [116, 196, 0],
+    [76, 208, 32],
+    [56, 204, 108],
+    [56, 180, 204],
+    [60, 60, 60],
+    [0, 0, 0],
+    [0, 0, 0],
+    [236, 238, 236],
+    [168, 204, 236],
+    [188, 188, 236],
+    [212, 178, 236],
+    [236, 174, 236],
+    [236, 174, 212],
+    [236, 180, 176],
+    [228, 196, 144],
+    [204, 210, 120],
+    [180, 222, 120],
+    [168, 226, 144],
+    [152, 226, 180],
+    [160, 214, 228],
+    [160, 162, 160],
+    [0, 0, 0],
+    [0, 0, 0],
+];
+
+/*
+ * The master palette of the PAL 2C07 PPU. Its color
+ * generation is shifted in phase compared to the NTSC one,
+ * giving slightly different hues.
+ */
+pub const PAL_PALETTE: [Rgb; PALETTE_COLORS] = [
+    [101, 101, 101],
+    [16, 14, 204],
+    [66, 0, 199],
+    [107, 0, 151],
+    [129, 0, 72],
+    [127, 0, 0],
+    [99, 27, 0],
+    [55, 57, 0],
+    [6, 82, 0],
+    [0, 94, 0],
+    [0, 90, 0],
+    [0, 72, 89],
+    [0, 44, 163],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [174, 174, 174],
+    [61, 58, 255],
+    [127, 26, 255],
+    [182, 10, 239],
+    [211, 15, 134],
+    [207, 39, 17],
+    [171, 76, 0],
+    [112, 115, 0],
+    [47, 148, 0],
+    [0, 163, 0],
+    [0, 159, 39],
+    [0, 135, 157],
+    [3, 98, 255],
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+    [255, 255, 255],
+    [141, 138, 255],
+    [207, 105, 255],
+    [255, 89, 255],
+    [255, 94, 215],
+    [255, 118, 95],
+    [252, 155, 0],
+    [193, 196, 0],
+    [126, 228, 0],
+    [70, 245, 12],
+    [41, 240, 119],
+    [45, 216, 238],
+    [81, 178, 255],
+    [79, 79, 79],
+    [0, 0, 0],
+    [0, 0, 0],
+    [255, 255, 255],
+    [208, 207, 255],
+    [235, 193, 255],
+    [255, 187, 255],
+    [255, 189, 239],
+    [255, 199, 189],
+    [254, 214, 148],
+    [229, 231, 125],
+    [202, 244, 128],
+    [179, 251, 155],
+    [167, 249, 199],
+    [168, 239, 248],
+    [183, 223, 255],
+    [182, 182, 182],
+    [0, 0, 0],
+    [0, 0, 0],
+];
+
+/*
+ * Represents the table used to convert the palette indices
+ * produced by the PPU into displayable RGB colors.
+ *
+ * It always holds the extended set of 512 colors, so that the
+ * 3 emphasis bits of PPUMASK can be used as the upper bits
+ * of a 9-bit color index.
+ */
+#[derive(Debug, Clone)]
+pub struct Palette {
+    colors: Vec<Rgb>,
+}
+
+impl Palette {
+    /* Initializes the built-in NTSC palette */
+    pub fn new() -> Self {
+        Self {
+            colors: Self::extend_with_emphasis(&NTSC_PALETTE),
+        }
+    }
+
+    /*
+     * Generates all 512 colors straight from the composite signal
+     * of the NTSC PPU, as a TV would decode them
+     */
+    pub fn from_ntsc_signal() -> Self {
+        let colors = (0..EXTENDED_PALETTE_COLORS as u16)
+            .map(|color| {
+                let (y, i, q) = get_color_yiq(color);
+                get_rgb_from_yiq(y, i, q)
+            })
+            .collect();
+
+        Self { colors }
+    }
+
+    /* Initializes the built-in palette matching the console region */
+    pub fn for_region(region: Region) -> Self {
+        let base = match region {
+            Region::Ntsc => &NTSC_PALETTE,
+            Region::Pal | Region::Dendy => &PAL_PALETTE,
+        };
+
+        Self {
+            colors: Self::extend_with_emphasis(base),
+        }
+    }
+
+    /*
+     * Parses the content of a .pal file, which is either 192
+     * bytes (64 colors) or 1536 bytes (512 colors) of RGB triplets
+     */
+    pub fn from_bytes(bytes: &[u8]) -> AppResult<Self> {
+        if bytes.len() != PALETTE_COLORS * 3 && bytes.len() != EXTENDED_PALETTE_COLORS * 3 {
+            return Err(AppError::InvalidPaletteFile);
+        }
+
+        let colors: Vec<Rgb> = bytes
+            .chunks_exact(3)
+            .map(|color| [color[0], color[1], color[2]])
+            .collect();
+
+        if colors.len() == EXTENDED_PALETTE_COLORS {
+            return Ok(Self { colors });
+        }
+
+        Ok(Self {
+            colors: Self::extend_with_emphasis(&colors),
+        })
+    }
+
+    /* Loads a palette from a .pal file on disk */
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let bytes = fs::read(path)?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    /*
+     * Converts a 9-bit color produced by the PPU to its RGB value.
+     * The lower 6 bits select the base color and the upper 3
+     * bits hold the red, green and blue emphasis bits.
+     */
+    pub fn get_color(&self, color: u16) -> Rgb {
+        self.colors[color as usize % EXTENDED_PALETTE_COLORS]
+    }
+
+    /*
+     * Builds the 512 colors table out of the 64 base colors. The
+     * emphasis bits attenuate parts of the composite signal, so
+     * the change they bring to each color is computed from the
+     * NTSC signal and applied to the base color in YIQ space.
+     */
+    fn extend_with_emphasis(base: &[Rgb]) -> Vec<Rgb> {
+        let mut colors = base.to_vec();
+
+        for emphasis in 1..8u16 {
+            for (index, color) in base.iter().enumerate() {
+                let plain = get_color_yiq(index as u16);
+                let emphasized = get_color_yiq(emphasis << 6 | index as u16);
+                let (y, i, q) = get_yiq_from_rgb(*color);
+
+                colors.push(get_rgb_from_yiq(
+                    y + emphasized.0 - plain.0,
+                    i + emphasized.1 - plain.1,
+                    q + emphasized.2 - plain.2,
+                ));
+            }
+        }
+
+        colors
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
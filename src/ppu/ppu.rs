@@ -1,17 +1,1856 @@
-use crate::bus::ppu_bus::PpuBus;
+use crate::{
+    bus::ppu_bus::{PALETTE_ADDRESS_LO, PpuBus},
+    console::region::Region,
+    errors::AppResult,
+    ppu::{
+        events::{EventLog, PpuEvent, PpuEventKind},
+        frame::{FRAME_HEIGHT, FRAME_WIDTH, Frame, PostProcessor, ProcessedFrame},
+        ntsc::{
+            NTSC_OUTPUT_HEIGHT, NTSC_OUTPUT_WIDTH, NtscFilter, SAMPLES_PER_PIXEL,
+            SAMPLES_PER_SUBCARRIER_CYCLE, VideoFilter,
+        },
+        palette::Palette,
+        scroll::ScrollState,
+        sprite::OAM_SPRITES,
+        tile::get_row_pixel,
+    },
+    state::state::{StateReader, StateWriter},
+};
+use bitflags::bitflags;
 
+/* PPU registers exposed to the CPU, mirrored every 8 bytes */
+pub const PPUCTRL: u16 = 0x0000;
+pub const PPUMASK: u16 = 0x0001;
+pub const PPUSTATUS: u16 = 0x0002;
+pub const OAMADDR: u16 = 0x0003;
+pub const OAMDATA: u16 = 0x0004;
+pub const PPUSCROLL: u16 = 0x0005;
+pub const PPUADDR: u16 = 0x0006;
+pub const PPUDATA: u16 = 0x0007;
+
+/*
+ * Every scanline lasts 341 dots, the number of scanlines
+ * in a frame depends on the console region.
+ */
+pub const DOTS_PER_SCANLINE: u16 = 341;
+
+/*
+ * Number of frames after which a bit of the I/O latch that
+ * was not refreshed decays back to 0, roughly 600ms
+ */
+pub const IO_LATCH_DECAY_FRAMES: u64 = 36;
+
+/*
+ * Number of dots between the second PPUADDR write and the
+ * moment the temporary address is actually copied to the
+ * current VRAM address
+ */
+pub const ADDRESS_UPDATE_DELAY: u8 = 3;
+
+/* Sprite related limits of the PPU */
+pub const OAM_SIZE: usize = 256;
+pub const SPRITES_PER_SCANLINE: usize = 8;
+
+/*
+ * OAM is dynamic memory refreshed by the sprite evaluation.
+ * Each row of 8 bytes left alone for about 3000 CPU cycles
+ * loses its content, which settles on this value here.
+ */
+pub const OAM_ROW_SIZE: usize = 8;
+pub const OAM_DECAY_DOTS: u64 = 9000;
+pub const OAM_DECAYED_VALUE: u8 = 0x10;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    pub struct Control: u8 {
+        const NAMETABLE_X        = 0b0000_0001;
+        const NAMETABLE_Y        = 0b0000_0010;
+        const INCREMENT_MODE     = 0b0000_0100;
+        const SPRITE_PATTERN     = 0b0000_1000;
+        const BACKGROUND_PATTERN = 0b0001_0000;
+        const SPRITE_SIZE        = 0b0010_0000;
+        const MASTER_SLAVE       = 0b0100_0000;
+        const NMI_ENABLE         = 0b1000_0000;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    pub struct Mask: u8 {
+        const GRAYSCALE            = 0b0000_0001;
+        const SHOW_BACKGROUND_LEFT = 0b0000_0010;
+        const SHOW_SPRITES_LEFT    = 0b0000_0100;
+        const SHOW_BACKGROUND      = 0b0000_1000;
+        const SHOW_SPRITES         = 0b0001_0000;
+        const EMPHASIZE_RED        = 0b0010_0000;
+        const EMPHASIZE_GREEN      = 0b0100_0000;
+        const EMPHASIZE_BLUE       = 0b1000_0000;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    pub struct Status: u8 {
+        const SPRITE_OVERFLOW = 0b0010_0000;
+        const SPRITE_ZERO_HIT = 0b0100_0000;
+        const VERTICAL_BLANK  = 0b1000_0000;
+    }
+}
+
+/*
+ * Selects how the picture is generated. The dot renderer runs
+ * every fetch on the dot it happens on, while the scanline one
+ * draws each line at once, trading mid-scanline effects for speed.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Renderer {
+    #[default]
+    Dot,
+    Scanline,
+}
+
+/*
+ * Function called when the beam reaches a specific scanline,
+ * receiving the PPU in its current state
+ */
+pub type ScanlineCallback = Box<dyn FnMut(&PPU)>;
+
+/* Function called with every frame once it is complete */
+pub type FrameCallback = Box<dyn FnMut(&Frame)>;
+
+/*
+ * The Picture Processing Unit, responsible for generating
+ * the video signal of the console.
+ *
+ * It is clocked once per dot and walks over every dot of
+ * every scanline. Memory fetches, scroll increments and flag
+ * updates all happen on the dot they happen on real hardware,
+ * and the visible pixels are written into its framebuffer.
+ */
 pub struct PPU {
     bus: PpuBus,
+    region: Region,
+
+    control: Control,
+    mask: Mask,
+    status: Status,
+
+    /* Object attribute memory, holding 64 sprites of 4 bytes each */
+    oam: [u8; OAM_SIZE],
+    oam_address: u8,
+
+    /* Whether OAM rows decay when not refreshed, off by default */
+    oam_decay: bool,
+    oam_rows_refreshed_at: [u64; OAM_SIZE / OAM_ROW_SIZE],
+
+    /*
+     * Rows of OAM, one per bit, that were being accessed when
+     * rendering got disabled mid-scanline. They are overwritten
+     * with the first row once sprite evaluation runs again.
+     */
+    oam_corrupted_rows: u32,
+
+    /*
+     * Sprites selected during evaluation for the next scanline.
+     * Only the first 8 entries exist on hardware, the others are
+     * used when the sprite limit is lifted.
+     */
+    secondary_oam: [u8; OAM_SIZE],
+    secondary_sprite_count: usize,
+    secondary_has_sprite_zero: bool,
+
+    /*
+     * Internal "loopy" registers: the current VRAM address,
+     * the temporary VRAM address, the fine X scroll and the
+     * shared first/second write toggle
+     */
+    v: u16,
+    t: u16,
+    fine_x: u8,
+    write_toggle: bool,
+
+    /* Dots left before a pending PPUADDR update reaches the VRAM address */
+    address_update_delay: u8,
+
+    /* Delayed result of PPUDATA reads outside palette memory */
+    data_buffer: u8,
+
+    /*
+     * Capacitance of the PPU data bus, keeping the last value
+     * driven on it. Each bit slowly decays to 0 unless refreshed,
+     * so the frame each bit was last refreshed on is kept.
+     */
+    io_latch: u8,
+    io_latch_refreshed_at: [u64; 8],
+
+    /* Background tile data fetched ahead of the beam */
+    next_tile_id: u8,
+    next_tile_attribute: u8,
+    next_tile_lo: u8,
+    next_tile_hi: u8,
+
+    /*
+     * Background shift registers, holding pattern and palette
+     * bits of the current and the next tile
+     */
+    pattern_shifter_lo: u16,
+    pattern_shifter_hi: u16,
+    attribute_shifter_lo: u16,
+    attribute_shifter_hi: u16,
+
+    /* Sprites being drawn on the current scanline */
+    sprite_count: usize,
+    sprite_zero_on_line: bool,
+    sprite_patterns_lo: [u8; OAM_SPRITES],
+    sprite_patterns_hi: [u8; OAM_SPRITES],
+    sprite_attributes: [u8; OAM_SPRITES],
+    sprite_positions: [u8; OAM_SPRITES],
+
+    /* Whether only 8 sprites per scanline are drawn, like on hardware */
+    sprite_limit: bool,
+
+    renderer: Renderer,
+
+    /*
+     * After power or reset the PPU ignores writes to some of its
+     * registers until the end of the first vertical blank, about
+     * 29658 CPU cycles. Emulating it can be turned off.
+     */
+    warm_up: bool,
+    is_warming_up: bool,
+
+    /* Scroll position each visible scanline was drawn with */
+    scanline_scroll: [ScrollState; FRAME_HEIGHT],
+
+    scanline: u16,
+    dot: u16,
+    odd_frame: bool,
+    frame_number: u64,
+
+    nmi_pending: bool,
+
+    /*
+     * Set when PPUSTATUS is read right before the vertical blank
+     * flag is raised, so neither the flag nor the NMI happen
+     */
+    is_vblank_suppressed: bool,
+
+    frame: Frame,
+    frame_ready: bool,
+
+    /* Table used for the final conversion of the frame to RGB */
+    palette: Palette,
+
+    /* Conversion applied to frames before display */
+    video_filter: VideoFilter,
+    ntsc_filter: NtscFilter,
+    /* External stage replacing the built-in conversion when set */
+    post_processor: Option<PostProcessor>,
+    /* Position of the color subcarrier at the start of the frame */
+    frame_phase: usize,
+
+    /* Callbacks registered for specific scanlines */
+    scanline_callbacks: Vec<(u16, ScanlineCallback)>,
+
+    /* Callbacks fired every time a frame is complete */
+    frame_callbacks: Vec<FrameCallback>,
+
+    /* Register accesses and interrupts recorded for debugging */
+    event_log: EventLog,
 }
 
 impl PPU {
+    /* Initializes a new PPU */
     pub fn new(bus: PpuBus) -> Self {
-        Self { bus }
+        Self {
+            bus,
+            region: Region::Ntsc,
+            control: Control::empty(),
+            mask: Mask::empty(),
+            status: Status::empty(),
+            oam: [0; OAM_SIZE],
+            oam_address: 0,
+            oam_decay: false,
+            oam_rows_refreshed_at: [0; OAM_SIZE / OAM_ROW_SIZE],
+            oam_corrupted_rows: 0,
+            secondary_oam: [0xFF; OAM_SIZE],
+            secondary_sprite_count: 0,
+            secondary_has_sprite_zero: false,
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            write_toggle: false,
+            address_update_delay: 0,
+            data_buffer: 0,
+            io_latch: 0,
+            io_latch_refreshed_at: [0; 8],
+            next_tile_id: 0,
+            next_tile_attribute: 0,
+            next_tile_lo: 0,
+            next_tile_hi: 0,
+            pattern_shifter_lo: 0,
+            pattern_shifter_hi: 0,
+            attribute_shifter_lo: 0,
+            attribute_shifter_hi: 0,
+            sprite_count: 0,
+            sprite_zero_on_line: false,
+            sprite_patterns_lo: [0; OAM_SPRITES],
+            sprite_patterns_hi: [0; OAM_SPRITES],
+            sprite_attributes: [0; OAM_SPRITES],
+            sprite_positions: [0; OAM_SPRITES],
+            sprite_limit: true,
+            renderer: Renderer::Dot,
+            warm_up: true,
+            is_warming_up: true,
+            scanline_scroll: [ScrollState::default(); FRAME_HEIGHT],
+            scanline: 0,
+            dot: 0,
+            odd_frame: false,
+            frame_number: 0,
+            nmi_pending: false,
+            is_vblank_suppressed: false,
+            frame: Frame::new(),
+            frame_ready: false,
+            palette: Palette::new(),
+            video_filter: VideoFilter::Rgb,
+            ntsc_filter: NtscFilter::new(),
+            post_processor: None,
+            frame_phase: 0,
+            scanline_callbacks: Vec::new(),
+            frame_callbacks: Vec::new(),
+            event_log: EventLog::default(),
+        }
+    }
+
+    /*
+     * Advances the PPU by a single dot, performing the memory
+     * fetches scheduled for it, drawing the pixel under the beam
+     * and raising the vertical blank at the end of the picture.
+     */
+    pub fn clock(&mut self) {
+        self.bus.clock();
+
+        if self.dot == 0 && !self.scanline_callbacks.is_empty() {
+            self.run_scanline_callbacks();
+        }
+
+        if self.address_update_delay > 0 {
+            self.address_update_delay -= 1;
+
+            if self.address_update_delay == 0 {
+                self.v = self.t;
+            }
+        }
+
+        let is_visible_scanline = self.scanline < FRAME_HEIGHT as u16;
+        let is_pre_render_scanline = self.scanline == self.region.pre_render_scanline();
+
+        if is_visible_scanline && self.dot == 1 {
+            self.scanline_scroll[self.scanline as usize] = self.get_line_scroll_state();
+        }
+
+        match self.renderer {
+            Renderer::Dot => {
+                if (is_visible_scanline || is_pre_render_scanline) && self.is_rendering_enabled() {
+                    self.clock_background();
+                    self.clock_sprites();
+                }
+
+                if is_visible_scanline && (1..=FRAME_WIDTH as u16).contains(&self.dot) {
+                    self.render_pixel();
+                }
+            }
+            Renderer::Scanline => {
+                if is_visible_scanline && self.dot == 1 {
+                    self.render_scanline();
+                }
+
+                if (is_visible_scanline || is_pre_render_scanline) && self.is_rendering_enabled() {
+                    self.clock_scanline();
+                }
+            }
+        }
+
+        /* OAMADDR is cleared while the sprite patterns are being fetched */
+        if (is_visible_scanline || is_pre_render_scanline)
+            && self.is_rendering_enabled()
+            && (257..=320).contains(&self.dot)
+        {
+            self.oam_address = 0;
+        }
+
+        if self.scanline == self.region.vblank_start_scanline() && self.dot == 1 {
+            if !self.is_vblank_suppressed {
+                self.status.insert(Status::VERTICAL_BLANK);
+
+                if self.control.contains(Control::NMI_ENABLE) {
+                    self.raise_nmi();
+                }
+            }
+
+            self.is_vblank_suppressed = false;
+            self.frame_ready = true;
+            self.frame_number += 1;
+            self.decay_io_latch();
+
+            for callback in self.frame_callbacks.iter_mut() {
+                callback(&self.frame);
+            }
+        }
+
+        if is_pre_render_scanline && self.dot == 1 {
+            self.is_warming_up = false;
+            self.status.remove(Status::VERTICAL_BLANK);
+            self.status.remove(Status::SPRITE_ZERO_HIT);
+            self.status.remove(Status::SPRITE_OVERFLOW);
+        }
+
+        self.increment_dot();
+    }
+
+    /*
+     * Reads one of the PPU registers. Bits not driven by the
+     * register come from the I/O latch, as do whole reads of
+     * write-only registers.
+     */
+    pub fn read(&mut self, address: u16) -> u8 {
+        let value = self.read_register(address);
+
+        self.log_event(PpuEventKind::RegisterRead {
+            register: address,
+            value,
+        });
+
+        value
+    }
+
+    /* Performs the register read itself, leaving the logging to read */
+    fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            PPUSTATUS => {
+                self.handle_vblank_race();
+
+                let value = self.status.bits() | (self.io_latch & 0x1F);
+                self.refresh_io_latch(value, 0xE0);
+
+                self.status.remove(Status::VERTICAL_BLANK);
+                self.write_toggle = false;
+
+                value
+            }
+            OAMDATA => {
+                self.refresh_oam_row(self.oam_address);
+
+                let value = self.oam[self.oam_address as usize];
+                self.refresh_io_latch(value, 0xFF);
+
+                value
+            }
+            PPUDATA => {
+                let address = self.v & 0x3FFF;
+
+                /*
+                 * Palette memory sits on the PPU itself so it is
+                 * returned right away, its 2 upper bits coming from
+                 * the latch. The buffer gets the nametable byte
+                 * hidden underneath instead.
+                 */
+                let value = if address >= PALETTE_ADDRESS_LO {
+                    self.data_buffer = self.bus.read(address & 0x2FFF);
+
+                    let value = (self.io_latch & 0xC0) | (self.bus.peek(address) & 0x3F);
+                    self.refresh_io_latch(value, 0x3F);
+
+                    value
+                } else {
+                    let value = self.data_buffer;
+                    self.data_buffer = self.bus.read(address);
+                    self.refresh_io_latch(value, 0xFF);
+
+                    value
+                };
+
+                self.increment_vram_address();
+                value
+            }
+            _ => self.io_latch,
+        }
+    }
+
+    /* Writes to one of the PPU registers, every write filling the I/O latch */
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.log_event(PpuEventKind::RegisterWrite {
+            register: address,
+            value,
+        });
+        self.refresh_io_latch(value, 0xFF);
+
+        if self.is_warming_up
+            && self.warm_up
+            && matches!(address, PPUCTRL | PPUMASK | PPUSCROLL | PPUADDR)
+        {
+            return;
+        }
+
+        match address {
+            PPUCTRL => {
+                let was_nmi_enabled = self.control.contains(Control::NMI_ENABLE);
+                self.control = Control::from_bits_truncate(value);
+
+                self.t = (self.t & 0xF3FF) | ((value as u16 & 0x03) << 10);
+
+                /* Enabling NMI in the middle of vertical blank fires it immediately */
+                if !was_nmi_enabled
+                    && self.control.contains(Control::NMI_ENABLE)
+                    && self.status.contains(Status::VERTICAL_BLANK)
+                {
+                    self.raise_nmi();
+                }
+            }
+            PPUMASK => {
+                let was_rendering_enabled = self.is_rendering_enabled();
+                self.mask = Mask::from_bits_truncate(value);
+
+                if was_rendering_enabled && !self.is_rendering_enabled() && self.is_rendering_line()
+                {
+                    self.corrupt_oam_row();
+                }
+            }
+            OAMADDR => self.oam_address = value,
+            OAMDATA => {
+                self.refresh_oam_row(self.oam_address);
+
+                /* Bits 2-4 of the sprite attributes do not exist in OAM */
+                let value = if self.oam_address & 0x03 == 0x02 {
+                    value & 0xE3
+                } else {
+                    value
+                };
+
+                self.oam[self.oam_address as usize] = value;
+                self.oam_address = self.oam_address.wrapping_add(1);
+            }
+            PPUSCROLL => {
+                if !self.write_toggle {
+                    self.t = (self.t & 0xFFE0) | (value as u16 >> 3);
+                    self.fine_x = value & 0x07;
+                } else {
+                    self.t = (self.t & 0x8C1F)
+                        | ((value as u16 & 0x07) << 12)
+                        | ((value as u16 & 0xF8) << 2);
+                }
+
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUADDR => {
+                if !self.write_toggle {
+                    self.t = (self.t & 0x00FF) | ((value as u16 & 0x3F) << 8);
+                } else {
+                    self.t = (self.t & 0xFF00) | value as u16;
+                    self.address_update_delay = ADDRESS_UPDATE_DELAY;
+                }
+
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUDATA => {
+                self.bus.write(self.v, value);
+                self.increment_vram_address();
+            }
+            _ => {}
+        }
+    }
+
+    /*
+     * Resets the PPU like the console reset button does. Only a
+     * few registers are cleared, memory is left untouched, and
+     * the warm-up period starts over.
+     */
+    pub fn reset(&mut self) {
+        self.control = Control::empty();
+        self.mask = Mask::empty();
+        self.write_toggle = false;
+        self.data_buffer = 0;
+        self.scanline = 0;
+        self.dot = 0;
+        self.odd_frame = false;
+        self.is_warming_up = true;
+    }
+
+    /*
+     * Returns whether a non-maskable interrupt was raised since
+     * the last call, acknowledging it at the same time.
+     */
+    pub fn poll_nmi(&mut self) -> bool {
+        let pending = self.nmi_pending;
+        self.nmi_pending = false;
+
+        pending
+    }
+
+    /* The most recent picture produced by the PPU */
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /*
+     * The most recent picture converted to RGBA bytes through the
+     * selected video filter, output_size giving its dimensions
+     */
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        match self.video_filter {
+            VideoFilter::Rgb => self.frame.to_rgba(&self.palette),
+            VideoFilter::Ntsc => self.ntsc_filter.apply(&self.frame, self.frame_phase),
+        }
+    }
+
+    /* Width and height of the pictures produced by frame_rgba */
+    pub fn output_size(&self) -> (usize, usize) {
+        match self.video_filter {
+            VideoFilter::Rgb => (FRAME_WIDTH, FRAME_HEIGHT),
+            VideoFilter::Ntsc => (NTSC_OUTPUT_WIDTH, NTSC_OUTPUT_HEIGHT),
+        }
+    }
+
+    /*
+     * The most recent picture ready for display. It goes through
+     * the registered post-processor when there is one, otherwise
+     * through the selected video filter.
+     */
+    pub fn output_frame(&mut self) -> ProcessedFrame {
+        if let Some(processor) = self.post_processor.as_mut() {
+            return processor(&self.frame);
+        }
+
+        let (width, height) = self.output_size();
+
+        ProcessedFrame {
+            width,
+            height,
+            pixels: self.frame_rgba(),
+        }
+    }
+
+    /*
+     * Registers the stage turning frames into pictures for
+     * display, such as an external upscaler or shader
+     */
+    pub fn set_post_processor<F: FnMut(&Frame) -> ProcessedFrame + 'static>(
+        &mut self,
+        processor: F,
+    ) {
+        self.post_processor = Some(Box::new(processor));
+    }
+
+    /* Goes back to the built-in conversion of frames */
+    pub fn clear_post_processor(&mut self) {
+        self.post_processor = None;
+    }
+
+    /* Selects the conversion applied by frame_rgba */
+    pub fn set_video_filter(&mut self, filter: VideoFilter) {
+        self.video_filter = filter;
+    }
+
+    /* The conversion applied by frame_rgba */
+    pub fn video_filter(&self) -> VideoFilter {
+        self.video_filter
+    }
+
+    /* Replaces the settings of the NTSC filter */
+    pub fn set_ntsc_filter(&mut self, filter: NtscFilter) {
+        self.ntsc_filter = filter;
+    }
+
+    /* Current value of PPUCTRL */
+    pub fn control(&self) -> Control {
+        self.control
+    }
+
+    /* Current value of PPUMASK */
+    pub fn mask(&self) -> Mask {
+        self.mask
+    }
+
+    /*
+     * Enables or lifts the hardware limit of 8 sprites per
+     * scanline. Lifting it removes the flicker games use to work
+     * around it, at the cost of accuracy.
+     */
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.sprite_limit = enabled;
+    }
+
+    /* Specifies whether the limit of 8 sprites per scanline is enforced */
+    pub fn sprite_limit(&self) -> bool {
+        self.sprite_limit
+    }
+
+    /*
+     * Enables or disables the emulation of the warm-up period,
+     * during which writes to PPUCTRL, PPUMASK, PPUSCROLL and
+     * PPUADDR are ignored
+     */
+    pub fn set_warm_up(&mut self, enabled: bool) {
+        self.warm_up = enabled;
+    }
+
+    /* Specifies whether the warm-up period is emulated */
+    pub fn warm_up(&self) -> bool {
+        self.warm_up
+    }
+
+    /*
+     * Enables or disables the decay of OAM rows that are not
+     * refreshed, which happens when rendering stays disabled.
+     * Some homebrew detects it, it is off by default.
+     */
+    pub fn set_oam_decay(&mut self, enabled: bool) {
+        if enabled && !self.oam_decay {
+            self.oam_rows_refreshed_at = [self.bus.dots(); OAM_SIZE / OAM_ROW_SIZE];
+        }
+
+        self.oam_decay = enabled;
+    }
+
+    /* Specifies whether OAM decay is emulated */
+    pub fn oam_decay(&self) -> bool {
+        self.oam_decay
+    }
+
+    /*
+     * Switches between the dot accurate renderer and the faster
+     * scanline based one, which is accurate enough for most games
+     */
+    pub(crate) fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    /* The renderer generating the picture */
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    /* Raw content of the object attribute memory */
+    pub fn oam(&self) -> &[u8; OAM_SIZE] {
+        &self.oam
+    }
+
+    /* Scroll position decoded from the current VRAM address */
+    pub fn scroll_state(&self) -> ScrollState {
+        ScrollState::new(self.v, self.fine_x)
+    }
+
+    /*
+     * Scroll position a visible scanline was drawn with, during
+     * the current frame or the previous one when the beam has
+     * not reached it yet
+     */
+    pub fn scanline_scroll(&self, scanline: usize) -> ScrollState {
+        self.scanline_scroll[scanline]
+    }
+
+    /* Scroll positions of all visible scanlines, top to bottom */
+    pub fn scanline_scrolls(&self) -> &[ScrollState; FRAME_HEIGHT] {
+        &self.scanline_scroll
+    }
+
+    /*
+     * Scroll position the current scanline starts from. The dot
+     * renderer has already prefetched two tiles at that point, so
+     * the VRAM address is moved back by as much.
+     */
+    fn get_line_scroll_state(&self) -> ScrollState {
+        let mut address = self.v;
+
+        if self.renderer == Renderer::Dot && self.is_rendering_enabled() {
+            for _ in 0..2 {
+                if address & 0x001F == 0 {
+                    address = (address | 0x001F) ^ 0x0400;
+                } else {
+                    address -= 1;
+                }
+            }
+        }
+
+        ScrollState::new(address, self.fine_x)
+    }
+
+    /*
+     * Position of the top-left corner of the screen inside the
+     * 512x480 area covered by the four nametables, as set by
+     * the scroll registers
+     */
+    pub(crate) fn get_scroll_origin(&self) -> (usize, usize) {
+        let t = self.t as usize;
+
+        let x = ((t & 0x001F) << 3 | self.fine_x as usize) + ((t >> 10) & 0x01) * 256;
+        let y = (((t >> 5) & 0x001F) << 3 | (t >> 12) & 0x07) + ((t >> 11) & 0x01) * 240;
+
+        (x, y)
+    }
+
+    /* Scanline the beam is currently on, the pre-render one being the last */
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    /* Dot of the current scanline the beam is on */
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    /*
+     * Registers a callback fired every frame when the beam
+     * enters the given scanline
+     */
+    pub fn on_scanline<F: FnMut(&PPU) + 'static>(&mut self, scanline: u16, callback: F) {
+        self.scanline_callbacks.push((scanline, Box::new(callback)));
+    }
+
+    /* Removes all registered scanline callbacks */
+    pub fn clear_scanline_callbacks(&mut self) {
+        self.scanline_callbacks.clear();
+    }
+
+    /*
+     * Registers a callback fired with every frame as soon as it
+     * is complete, when the vertical blank starts
+     */
+    pub fn on_frame<F: FnMut(&Frame) + 'static>(&mut self, callback: F) {
+        self.frame_callbacks.push(Box::new(callback));
+    }
+
+    /* Removes all registered frame callbacks */
+    pub fn clear_frame_callbacks(&mut self) {
+        self.frame_callbacks.clear();
+    }
+
+    /*
+     * Number of frames completed since power on. It only ever
+     * increases, a reset does not bring it back to 0.
+     */
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /*
+     * Switches the PPU to the timing of another console region,
+     * along with the matching built-in palette
+     */
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.palette = Palette::for_region(region);
+    }
+
+    /* The console region the PPU timing follows */
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /* Replaces the palette used for RGB conversion */
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /* The palette used for RGB conversion */
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /* Specifies whether a whole frame was drawn since the flag was cleared */
+    pub fn is_frame_ready(&self) -> bool {
+        self.frame_ready
+    }
+
+    /* Returns whether a new frame is ready, clearing the flag */
+    pub fn take_frame_ready(&mut self) -> bool {
+        let ready = self.frame_ready;
+        self.frame_ready = false;
+
+        ready
+    }
+
+    /*
+     * Saves the complete internal state of the PPU, its memory
+     * included. Settings such as the region, the palette or the
+     * sprite limit belong to the frontend and are left out.
+     */
+    pub fn save_state(&self, state: &mut StateWriter) {
+        self.bus.save_state(state);
+
+        state.write_u8(self.control.bits());
+        state.write_u8(self.mask.bits());
+        state.write_u8(self.status.bits());
+        state.write_bytes(&self.oam);
+        state.write_u8(self.oam_address);
+        for refreshed_at in self.oam_rows_refreshed_at {
+            state.write_u64(refreshed_at);
+        }
+        state.write_u64(self.oam_corrupted_rows as u64);
+        state.write_bytes(&self.secondary_oam);
+        state.write_u64(self.secondary_sprite_count as u64);
+        state.write_bool(self.secondary_has_sprite_zero);
+
+        state.write_u16(self.v);
+        state.write_u16(self.t);
+        state.write_u8(self.fine_x);
+        state.write_bool(self.write_toggle);
+        state.write_u8(self.address_update_delay);
+        state.write_u8(self.data_buffer);
+        state.write_u8(self.io_latch);
+        for refreshed_at in self.io_latch_refreshed_at {
+            state.write_u64(refreshed_at);
+        }
+
+        state.write_u8(self.next_tile_id);
+        state.write_u8(self.next_tile_attribute);
+        state.write_u8(self.next_tile_lo);
+        state.write_u8(self.next_tile_hi);
+        state.write_u16(self.pattern_shifter_lo);
+        state.write_u16(self.pattern_shifter_hi);
+        state.write_u16(self.attribute_shifter_lo);
+        state.write_u16(self.attribute_shifter_hi);
+
+        state.write_u64(self.sprite_count as u64);
+        state.write_bool(self.sprite_zero_on_line);
+        state.write_bytes(&self.sprite_patterns_lo);
+        state.write_bytes(&self.sprite_patterns_hi);
+        state.write_bytes(&self.sprite_attributes);
+        state.write_bytes(&self.sprite_positions);
+
+        state.write_u16(self.scanline);
+        state.write_u16(self.dot);
+        state.write_bool(self.odd_frame);
+        state.write_u64(self.frame_number);
+        state.write_bool(self.nmi_pending);
+        state.write_bool(self.is_warming_up);
+        state.write_bool(self.is_vblank_suppressed);
+        state.write_u8(self.frame_phase as u8);
+
+        self.frame.save_state(state);
+    }
+
+    /* Restores a state produced by save_state */
+    pub fn load_state(&mut self, state: &mut StateReader) -> AppResult<()> {
+        self.bus.load_state(state)?;
+
+        self.control = Control::from_bits_truncate(state.read_u8()?);
+        self.mask = Mask::from_bits_truncate(state.read_u8()?);
+        self.status = Status::from_bits_truncate(state.read_u8()?);
+        state.read_bytes(&mut self.oam)?;
+        self.oam_address = state.read_u8()?;
+        for refreshed_at in self.oam_rows_refreshed_at.iter_mut() {
+            *refreshed_at = state.read_u64()?;
+        }
+        self.oam_corrupted_rows = state.read_u64()? as u32;
+        state.read_bytes(&mut self.secondary_oam)?;
+        self.secondary_sprite_count = (state.read_u64()? as usize).min(OAM_SPRITES);
+        self.secondary_has_sprite_zero = state.read_bool()?;
+
+        self.v = state.read_u16()?;
+        self.t = state.read_u16()?;
+        self.fine_x = state.read_u8()?;
+        self.write_toggle = state.read_bool()?;
+        self.address_update_delay = state.read_u8()?;
+        self.data_buffer = state.read_u8()?;
+        self.io_latch = state.read_u8()?;
+        for refreshed_at in self.io_latch_refreshed_at.iter_mut() {
+            *refreshed_at = state.read_u64()?;
+        }
+
+        self.next_tile_id = state.read_u8()?;
+        self.next_tile_attribute = state.read_u8()?;
+        self.next_tile_lo = state.read_u8()?;
+        self.next_tile_hi = state.read_u8()?;
+        self.pattern_shifter_lo = state.read_u16()?;
+        self.pattern_shifter_hi = state.read_u16()?;
+        self.attribute_shifter_lo = state.read_u16()?;
+        self.attribute_shifter_hi = state.read_u16()?;
+
+        self.sprite_count = (state.read_u64()? as usize).min(OAM_SPRITES);
+        self.sprite_zero_on_line = state.read_bool()?;
+        state.read_bytes(&mut self.sprite_patterns_lo)?;
+        state.read_bytes(&mut self.sprite_patterns_hi)?;
+        state.read_bytes(&mut self.sprite_attributes)?;
+        state.read_bytes(&mut self.sprite_positions)?;
+
+        self.scanline = state.read_u16()?;
+        self.dot = state.read_u16()?;
+        self.odd_frame = state.read_bool()?;
+        self.frame_number = state.read_u64()?;
+        self.nmi_pending = state.read_bool()?;
+        self.is_warming_up = state.read_bool()?;
+        self.is_vblank_suppressed = state.read_bool()?;
+        self.frame_phase = state.read_u8()? as usize % SAMPLES_PER_SUBCARRIER_CYCLE;
+
+        self.frame.load_state(state)
     }
 
-    pub fn read(&self, address: u16) -> u8 {
-        return 0;
+    /* The record of PPU events, disabled until logging is turned on */
+    pub fn event_log(&self) -> &EventLog {
+        &self.event_log
     }
 
-    pub fn write(&self, address: u16, value: u8) {}
+    /* Gives access to the event log to enable or clear it */
+    pub fn event_log_mut(&mut self) -> &mut EventLog {
+        &mut self.event_log
+    }
+
+    /*
+     * Reads the PPU address space without going through PPUADDR
+     * and PPUDATA, leaving the VRAM address, the read buffer and
+     * the cartridge untouched
+     */
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    /* Writes the PPU address space without any side effect */
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.bus.poke(address, value);
+    }
+
+    /* Gives direct access to the PPU bus */
+    pub fn bus(&self) -> &PpuBus {
+        &self.bus
+    }
+
+    /*
+     * Calls the callbacks registered for the current scanline.
+     * They are moved out while running so they can look at the PPU.
+     */
+    fn run_scanline_callbacks(&mut self) {
+        let mut callbacks = std::mem::take(&mut self.scanline_callbacks);
+
+        for (scanline, callback) in callbacks.iter_mut() {
+            if *scanline == self.scanline {
+                callback(self);
+            }
+        }
+
+        self.scanline_callbacks = callbacks;
+    }
+
+    /* Asks the CPU for a non-maskable interrupt */
+    fn raise_nmi(&mut self) {
+        self.nmi_pending = true;
+        self.log_event(PpuEventKind::Nmi);
+    }
+
+    /* Records an event at the current position of the beam */
+    fn log_event(&mut self, kind: PpuEventKind) {
+        if !self.event_log.is_enabled() {
+            return;
+        }
+
+        self.event_log.push(PpuEvent {
+            frame: self.frame_number,
+            scanline: self.scanline,
+            dot: self.dot,
+            kind,
+        });
+    }
+
+    /*
+     * Reading PPUSTATUS while the vertical blank flag is being
+     * raised races with it. Reading on the dot before the flag is
+     * set sees it clear and prevents it for the whole frame, while
+     * reading on the dot it is set or the next one sees it set but
+     * still cancels the NMI of that frame.
+     */
+    fn handle_vblank_race(&mut self) {
+        if self.scanline != self.region.vblank_start_scanline() {
+            return;
+        }
+
+        match self.dot {
+            1 => self.is_vblank_suppressed = true,
+            2 | 3 => self.nmi_pending = false,
+            _ => {}
+        }
+    }
+
+    /*
+     * Accessing an OAM row refreshes it. When decay is emulated
+     * and the row was left alone for too long, its content is
+     * lost before the access happens.
+     */
+    fn refresh_oam_row(&mut self, address: u8) {
+        if !self.oam_decay {
+            return;
+        }
+
+        let row = address as usize / OAM_ROW_SIZE;
+        let dots = self.bus.dots();
+
+        if dots - self.oam_rows_refreshed_at[row] >= OAM_DECAY_DOTS {
+            let start = row * OAM_ROW_SIZE;
+            self.oam[start..start + OAM_ROW_SIZE].fill(OAM_DECAYED_VALUE);
+        }
+
+        self.oam_rows_refreshed_at[row] = dots;
+    }
+
+    /* Drives the given bits of the I/O latch with a new value */
+    fn refresh_io_latch(&mut self, value: u8, mask: u8) {
+        self.io_latch = (self.io_latch & !mask) | (value & mask);
+
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.io_latch_refreshed_at[bit] = self.frame_number;
+            }
+        }
+    }
+
+    /* Clears the bits of the I/O latch that were not refreshed for too long */
+    fn decay_io_latch(&mut self) {
+        for bit in 0..8 {
+            if self.frame_number - self.io_latch_refreshed_at[bit] >= IO_LATCH_DECAY_FRAMES {
+                self.io_latch &= !(1 << bit);
+            }
+        }
+    }
+
+    /* Specifies whether the beam is on a scanline the PPU renders or prefetches */
+    fn is_rendering_line(&self) -> bool {
+        self.scanline < FRAME_HEIGHT as u16 || self.scanline == self.region.pre_render_scanline()
+    }
+
+    /*
+     * Disabling rendering in the middle of a scanline interrupts
+     * the OAM access in progress, corrupting the row it was on:
+     * secondary OAM clearing during the first 64 dots, or the
+     * sprite fetches during dots 256 to 320.
+     */
+    fn corrupt_oam_row(&mut self) {
+        let dot = self.dot as usize;
+
+        let row = if dot < 64 {
+            dot >> 1
+        } else if (256..320).contains(&dot) {
+            ((dot - 256) >> 3) * 4 + ((dot - 256) & 0x07).min(3)
+        } else {
+            return;
+        };
+
+        self.oam_corrupted_rows |= 1 << row;
+    }
+
+    /* Overwrites the corrupted OAM rows with a copy of the first one */
+    fn apply_oam_corruption(&mut self) {
+        for row in 1..OAM_SIZE / OAM_ROW_SIZE {
+            if self.oam_corrupted_rows & (1 << row) != 0 {
+                self.oam.copy_within(0..OAM_ROW_SIZE, row * OAM_ROW_SIZE);
+            }
+        }
+
+        self.oam_corrupted_rows = 0;
+    }
+
+    /* Specifies whether either background or sprite rendering is enabled */
+    fn is_rendering_enabled(&self) -> bool {
+        self.mask
+            .intersects(Mask::SHOW_BACKGROUND | Mask::SHOW_SPRITES)
+    }
+
+    /*
+     * Moves the beam to the next dot. On odd NTSC frames the
+     * idle dot at the end of the pre-render scanline is skipped
+     * while rendering is enabled.
+     */
+    fn increment_dot(&mut self) {
+        if self.scanline == self.region.pre_render_scanline()
+            && self.dot == DOTS_PER_SCANLINE - 2
+            && self.odd_frame
+            && self.region.skips_odd_frame_dot()
+            && self.is_rendering_enabled()
+        {
+            self.dot += 1;
+            self.frame_phase = (self.frame_phase + SAMPLES_PER_SUBCARRIER_CYCLE
+                - SAMPLES_PER_PIXEL)
+                % SAMPLES_PER_SUBCARRIER_CYCLE;
+        }
+
+        self.dot += 1;
+
+        if self.dot >= DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline >= self.region.scanlines_per_frame() {
+                let samples =
+                    self.scanline as usize * DOTS_PER_SCANLINE as usize * SAMPLES_PER_PIXEL;
+
+                self.frame_phase = (self.frame_phase + samples) % SAMPLES_PER_SUBCARRIER_CYCLE;
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+    }
+
+    /*
+     * Background part of the rendering pipeline. Each tile takes
+     * 8 dots to fetch: nametable byte, attribute byte and the two
+     * pattern bytes, after which the coarse X scroll is incremented.
+     * The first two tiles of the next scanline are prefetched at
+     * the end of the current one.
+     */
+    fn clock_background(&mut self) {
+        let dot = self.dot;
+
+        if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+            self.update_background_shifters();
+
+            match (dot - 1) % 8 {
+                0 => {
+                    self.load_background_shifters();
+                    self.fetch_nametable_byte();
+                }
+                2 => self.fetch_attribute_byte(),
+                4 => self.next_tile_lo = self.fetch_background_pattern(0),
+                6 => self.next_tile_hi = self.fetch_background_pattern(8),
+                7 => self.increment_scroll_x(),
+                _ => {}
+            }
+        }
+
+        match dot {
+            256 => self.increment_scroll_y(),
+            257 => {
+                self.load_background_shifters();
+                self.transfer_address_x();
+            }
+            338 | 340 => self.fetch_nametable_byte(),
+            280..=304 if self.scanline == self.region.pre_render_scanline() => {
+                self.transfer_address_y()
+            }
+            _ => {}
+        }
+    }
+
+    /*
+     * Sprite part of the rendering pipeline. Sprites for the next
+     * scanline are evaluated once the visible part of the current
+     * one is over, then their pattern bytes are fetched during
+     * the 8 slots of dots 257 to 320.
+     */
+    fn clock_sprites(&mut self) {
+        let dot = self.dot;
+
+        if dot == 257 {
+            self.prepare_sprites();
+        }
+
+        if (257..=320).contains(&dot) {
+            let slot = ((dot - 257) / 8) as usize;
+
+            match (dot - 257) % 8 {
+                4 => self.sprite_patterns_lo[slot] = self.fetch_sprite_pattern(slot, 0),
+                6 => self.sprite_patterns_hi[slot] = self.fetch_sprite_pattern(slot, 8),
+                _ => {}
+            }
+        }
+
+        /*
+         * Sprites past the 8th only exist when the limit is lifted,
+         * there is no room for them in the hardware fetch slots
+         */
+        if dot == 320 {
+            for slot in SPRITES_PER_SCANLINE..self.secondary_sprite_count {
+                self.sprite_patterns_lo[slot] = self.fetch_sprite_pattern(slot, 0);
+                self.sprite_patterns_hi[slot] = self.fetch_sprite_pattern(slot, 8);
+            }
+        }
+    }
+
+    /*
+     * Scanline renderer counterpart of the background and sprite
+     * pipelines. The scroll updates happen on their usual dots but
+     * all sprites of the next scanline are fetched at once.
+     */
+    fn clock_scanline(&mut self) {
+        match self.dot {
+            256 => self.increment_scroll_y(),
+            257 => {
+                self.transfer_address_x();
+                self.prepare_sprites();
+
+                let slots = self.secondary_sprite_count.max(SPRITES_PER_SCANLINE);
+
+                for slot in 0..slots {
+                    self.sprite_patterns_lo[slot] = self.fetch_sprite_pattern(slot, 0);
+                    self.sprite_patterns_hi[slot] = self.fetch_sprite_pattern(slot, 8);
+                }
+            }
+            280 if self.scanline == self.region.pre_render_scanline() => self.transfer_address_y(),
+            _ => {}
+        }
+    }
+
+    /*
+     * Selects the sprites of the next scanline, which is how the
+     * OAM gets refreshed. The pre-render scanline finds none.
+     */
+    fn prepare_sprites(&mut self) {
+        self.apply_oam_corruption();
+
+        for row in 0..OAM_SIZE / OAM_ROW_SIZE {
+            self.refresh_oam_row((row * OAM_ROW_SIZE) as u8);
+        }
+
+        if self.scanline == self.region.pre_render_scanline() {
+            self.secondary_oam = [0xFF; OAM_SIZE];
+            self.secondary_sprite_count = 0;
+            self.secondary_has_sprite_zero = false;
+        } else {
+            self.evaluate_sprites();
+        }
+
+        self.sprite_count = self.secondary_sprite_count;
+        self.sprite_zero_on_line = self.secondary_has_sprite_zero;
+    }
+
+    /*
+     * Scans the primary OAM for sprites overlapping the next
+     * scanline and copies up to 8 of them to the secondary OAM.
+     * Once it is full, the overflow check suffers from the
+     * hardware bug of also moving to the next byte of the sprite
+     * on every miss, so the flag is both missed and falsely set.
+     */
+    fn evaluate_sprites(&mut self) {
+        let height = self.get_sprite_height();
+
+        self.secondary_oam = [0xFF; OAM_SIZE];
+        self.secondary_sprite_count = 0;
+        self.secondary_has_sprite_zero = false;
+
+        let mut sprite = 0;
+        let mut is_overflow_checked = false;
+
+        while sprite < OAM_SPRITES {
+            if self.secondary_sprite_count == SPRITES_PER_SCANLINE && !is_overflow_checked {
+                self.evaluate_sprite_overflow(sprite, height);
+                is_overflow_checked = true;
+
+                if self.sprite_limit {
+                    break;
+                }
+            }
+
+            if self.is_sprite_in_range(self.oam[sprite * 4], height) {
+                let offset = self.secondary_sprite_count * 4;
+                self.secondary_oam[offset..offset + 4]
+                    .copy_from_slice(&self.oam[sprite * 4..sprite * 4 + 4]);
+                self.secondary_sprite_count += 1;
+
+                if sprite == 0 {
+                    self.secondary_has_sprite_zero = true;
+                }
+            }
+
+            sprite += 1;
+        }
+    }
+
+    /* Emulates the buggy search of a 9th sprite on the scanline */
+    fn evaluate_sprite_overflow(&mut self, first_sprite: usize, height: u16) {
+        let mut sprite = first_sprite;
+        let mut byte = 0;
+
+        while sprite < OAM_SPRITES {
+            if self.is_sprite_in_range(self.oam[sprite * 4 + byte], height) {
+                self.status.insert(Status::SPRITE_OVERFLOW);
+                return;
+            }
+
+            sprite += 1;
+            byte = (byte + 1) & 0x03;
+        }
+    }
+
+    /* Specifies whether a sprite at the given Y position covers the next scanline */
+    fn is_sprite_in_range(&self, y: u8, height: u16) -> bool {
+        self.scanline.wrapping_sub(y as u16) < height
+    }
+
+    /*
+     * Fetches one of the pattern bytes of a sprite in the
+     * secondary OAM. Unused slots still perform a fetch of
+     * tile $FF like the hardware does.
+     */
+    fn fetch_sprite_pattern(&mut self, slot: usize, plane: u16) -> u8 {
+        let height = self.get_sprite_height();
+        let [y, tile, attribute, x] = [0, 1, 2, 3].map(|i| self.secondary_oam[slot * 4 + i]);
+
+        let mut row = if slot < self.secondary_sprite_count {
+            self.scanline.wrapping_sub(y as u16) & (height - 1)
+        } else {
+            0
+        };
+
+        if attribute & 0x80 != 0 {
+            row = height - 1 - row;
+        }
+
+        let address = if height == 16 {
+            let table = (tile as u16 & 0x01) << 12;
+            let tile = (tile as u16 & 0xFE) + (row >> 3);
+
+            table | (tile << 4) | (row & 0x07)
+        } else {
+            let table = if self.control.contains(Control::SPRITE_PATTERN) {
+                0x1000
+            } else {
+                0x0000
+            };
+
+            table | ((tile as u16) << 4) | row
+        };
+
+        let mut pattern = self.bus.read(address + plane);
+
+        if slot < self.secondary_sprite_count {
+            if attribute & 0x40 != 0 {
+                pattern = pattern.reverse_bits();
+            }
+        } else {
+            pattern = 0;
+        }
+
+        self.sprite_attributes[slot] = attribute;
+        self.sprite_positions[slot] = x;
+
+        pattern
+    }
+
+    /* Sprites are either 8x8 or 8x16 pixels depending on PPUCTRL */
+    fn get_sprite_height(&self) -> u16 {
+        if self.control.contains(Control::SPRITE_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+
+    /* Fetches the tile index under the current VRAM address */
+    fn fetch_nametable_byte(&mut self) {
+        self.next_tile_id = self.bus.read(0x2000 | (self.v & 0x0FFF));
+    }
+
+    /*
+     * Fetches the attribute byte of the current tile and keeps
+     * only the 2 palette bits of the 16x16 area it belongs to
+     */
+    fn fetch_attribute_byte(&mut self) {
+        let v = self.v;
+        let address = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+        let shift = ((v >> 4) & 0x04) | (v & 0x02);
+
+        self.next_tile_attribute = (self.bus.read(address) >> shift) & 0x03;
+    }
+
+    /* Fetches one of the bitplanes of the current background tile */
+    fn fetch_background_pattern(&mut self, plane: u16) -> u8 {
+        let table = if self.control.contains(Control::BACKGROUND_PATTERN) {
+            0x1000
+        } else {
+            0x0000
+        };
+        let fine_y = (self.v >> 12) & 0x07;
+
+        self.bus
+            .read(table + ((self.next_tile_id as u16) << 4) + fine_y + plane)
+    }
+
+    /* Moves the freshly fetched tile into the low byte of the shifters */
+    fn load_background_shifters(&mut self) {
+        self.pattern_shifter_lo = (self.pattern_shifter_lo & 0xFF00) | self.next_tile_lo as u16;
+        self.pattern_shifter_hi = (self.pattern_shifter_hi & 0xFF00) | self.next_tile_hi as u16;
+
+        let attribute_lo = if self.next_tile_attribute & 0x01 != 0 {
+            0xFF
+        } else {
+            0x00
+        };
+        let attribute_hi = if self.next_tile_attribute & 0x02 != 0 {
+            0xFF
+        } else {
+            0x00
+        };
+
+        self.attribute_shifter_lo = (self.attribute_shifter_lo & 0xFF00) | attribute_lo;
+        self.attribute_shifter_hi = (self.attribute_shifter_hi & 0xFF00) | attribute_hi;
+    }
+
+    /* Shifts the background registers by one pixel */
+    fn update_background_shifters(&mut self) {
+        if self.mask.contains(Mask::SHOW_BACKGROUND) {
+            self.pattern_shifter_lo <<= 1;
+            self.pattern_shifter_hi <<= 1;
+            self.attribute_shifter_lo <<= 1;
+            self.attribute_shifter_hi <<= 1;
+        }
+    }
+
+    /* Moves the VRAM address to the next tile, wrapping to the next nametable */
+    fn increment_scroll_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /* Moves the VRAM address to the next pixel row, wrapping to the next nametable */
+    fn increment_scroll_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+        let mut coarse_y = (self.v & 0x03E0) >> 5;
+
+        if coarse_y == 29 {
+            coarse_y = 0;
+            self.v ^= 0x0800;
+        } else if coarse_y == 31 {
+            coarse_y = 0;
+        } else {
+            coarse_y += 1;
+        }
+
+        self.v = (self.v & !0x03E0) | (coarse_y << 5);
+    }
+
+    /* Restores the horizontal scroll bits from the temporary address */
+    fn transfer_address_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    /* Restores the vertical scroll bits from the temporary address */
+    fn transfer_address_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    /*
+     * Background pixel under the beam as a (pixel, palette) pair,
+     * hidden in the leftmost 8 pixels when PPUMASK asks for it
+     */
+    fn get_background_pixel(&self, x: u16) -> (u8, u8) {
+        if !self.is_background_visible(x) {
+            return (0, 0);
+        }
+
+        let mux = 0x8000 >> self.fine_x;
+
+        let pixel = ((self.pattern_shifter_hi & mux != 0) as u8) << 1
+            | (self.pattern_shifter_lo & mux != 0) as u8;
+        let palette = ((self.attribute_shifter_hi & mux != 0) as u8) << 1
+            | (self.attribute_shifter_lo & mux != 0) as u8;
+
+        (pixel, palette)
+    }
+
+    /* Specifies whether the background is shown at the given column */
+    fn is_background_visible(&self, x: u16) -> bool {
+        self.mask.contains(Mask::SHOW_BACKGROUND)
+            && (x >= 8 || self.mask.contains(Mask::SHOW_BACKGROUND_LEFT))
+    }
+
+    /*
+     * First opaque sprite pixel under the beam as a (pixel,
+     * palette, is behind background, is sprite zero) tuple,
+     * sprites with a lower OAM index being drawn on top
+     */
+    fn get_sprite_pixel(&self, x: u16) -> (u8, u8, bool, bool) {
+        if !self.mask.contains(Mask::SHOW_SPRITES) {
+            return (0, 0, false, false);
+        }
+
+        if x < 8 && !self.mask.contains(Mask::SHOW_SPRITES_LEFT) {
+            return (0, 0, false, false);
+        }
+
+        for slot in 0..self.sprite_count {
+            let offset = x.wrapping_sub(self.sprite_positions[slot] as u16);
+
+            if offset >= 8 {
+                continue;
+            }
+
+            let pixel = get_row_pixel(
+                self.sprite_patterns_lo[slot],
+                self.sprite_patterns_hi[slot],
+                offset,
+            );
+
+            if pixel != 0 {
+                let attribute = self.sprite_attributes[slot];
+                let palette = (attribute & 0x03) + 4;
+                let is_behind_background = attribute & 0x20 != 0;
+
+                return (
+                    pixel,
+                    palette,
+                    is_behind_background,
+                    slot == 0 && self.sprite_zero_on_line,
+                );
+            }
+        }
+
+        (0, 0, false, false)
+    }
+
+    /*
+     * Draws the pixel under the beam for the dot renderer, the
+     * background coming out of the shift registers
+     */
+    fn render_pixel(&mut self) {
+        let x = self.dot - 1;
+        let y = self.scanline as usize;
+
+        if !self.is_rendering_enabled() {
+            let color = self.get_backdrop_color();
+
+            self.frame
+                .set_pixel(x as usize, y, self.get_output_color(color));
+            return;
+        }
+
+        let (background_pixel, background_palette) = self.get_background_pixel(x);
+        self.draw_pixel(x, background_pixel, background_palette);
+    }
+
+    /*
+     * Draws a whole visible scanline at once for the scanline
+     * renderer. Background tiles are fetched one after the other
+     * starting from the current VRAM address, which ends up
+     * moved past the line like the dot renderer leaves it.
+     */
+    fn render_scanline(&mut self) {
+        let y = self.scanline as usize;
+
+        if !self.is_rendering_enabled() {
+            let color = self.get_output_color(self.get_backdrop_color());
+
+            for x in 0..FRAME_WIDTH {
+                self.frame.set_pixel(x, y, color);
+            }
+            return;
+        }
+
+        let fine_x = self.fine_x as u16;
+
+        /* A 33rd tile is needed when the line is not aligned on tiles */
+        for tile in 0..=FRAME_WIDTH as u16 / 8 {
+            self.fetch_nametable_byte();
+            self.fetch_attribute_byte();
+            self.next_tile_lo = self.fetch_background_pattern(0);
+            self.next_tile_hi = self.fetch_background_pattern(8);
+            self.increment_scroll_x();
+
+            for column in 0..8 {
+                let x = (tile * 8 + column).wrapping_sub(fine_x);
+
+                if x >= FRAME_WIDTH as u16 {
+                    continue;
+                }
+
+                let (pixel, palette) = if self.is_background_visible(x) {
+                    let pixel = get_row_pixel(self.next_tile_lo, self.next_tile_hi, column);
+                    (pixel, self.next_tile_attribute)
+                } else {
+                    (0, 0)
+                };
+
+                self.draw_pixel(x, pixel, palette);
+            }
+        }
+    }
+
+    /*
+     * Combines a background pixel with the sprites at the same
+     * column and writes the resulting color to the framebuffer
+     */
+    fn draw_pixel(&mut self, x: u16, background_pixel: u8, background_palette: u8) {
+        let y = self.scanline as usize;
+        let (sprite_pixel, sprite_palette, is_behind_background, is_sprite_zero) =
+            self.get_sprite_pixel(x);
+
+        /*
+         * Pixels hidden by the left-edge masking are transparent,
+         * so they never trigger a sprite zero hit
+         */
+        if is_sprite_zero
+            && background_pixel != 0
+            && x != 255
+            && !self.status.contains(Status::SPRITE_ZERO_HIT)
+        {
+            self.status.insert(Status::SPRITE_ZERO_HIT);
+            self.log_event(PpuEventKind::SpriteZeroHit);
+        }
+
+        /*
+         * Only the frontmost opaque sprite competes with the
+         * background, so a sprite behind it hides any sprite with a
+         * higher index even where the background wins
+         */
+        let is_sprite_shown = sprite_pixel != 0 && (background_pixel == 0 || !is_behind_background);
+
+        let (pixel, palette) = if is_sprite_shown {
+            (sprite_pixel, sprite_palette)
+        } else {
+            (background_pixel, background_palette)
+        };
+
+        let address = if pixel == 0 {
+            PALETTE_ADDRESS_LO
+        } else {
+            PALETTE_ADDRESS_LO + ((palette as u16) << 2) + pixel as u16
+        };
+
+        /* Palette memory is internal to the PPU, it never reaches the bus */
+        let color = self.bus.peek(address);
+
+        self.frame
+            .set_pixel(x as usize, y, self.get_output_color(color));
+    }
+
+    /*
+     * Color output while rendering is disabled. It is normally
+     * the backdrop color at $3F00, but when the VRAM address
+     * points inside palette memory the entry it designates is
+     * shown instead, which games use for full-screen effects.
+     */
+    fn get_backdrop_color(&self) -> u8 {
+        let address = self.v & 0x3FFF;
+
+        if address >= PALETTE_ADDRESS_LO {
+            self.bus.peek(address)
+        } else {
+            self.bus.peek(PALETTE_ADDRESS_LO)
+        }
+    }
+
+    /*
+     * Applies the grayscale and emphasis bits of PPUMASK to a
+     * palette entry, producing the 9-bit color sent to the output.
+     * Grayscale keeps only the luminance column of the palette and
+     * PAL emphasis bits are reordered to red, green, blue.
+     */
+    pub(crate) fn get_output_color(&self, color: u8) -> u16 {
+        let color = if self.mask.contains(Mask::GRAYSCALE) {
+            color & 0x30
+        } else {
+            color & 0x3F
+        };
+        let mut emphasis = (self.mask.bits() >> 5) as u16;
+
+        if self.region.swaps_emphasis_bits() {
+            emphasis = (emphasis & 0x04) | ((emphasis & 0x01) << 1) | ((emphasis & 0x02) >> 1);
+        }
+
+        (emphasis << 6) | color as u16
+    }
+
+    /*
+     * Moves the VRAM address forward after a PPUDATA access.
+     * While rendering, the access collides with the rendering
+     * increments and both coarse X and Y get incremented instead.
+     */
+    fn increment_vram_address(&mut self) {
+        if self.is_rendering_in_progress() {
+            self.increment_scroll_x();
+            self.increment_scroll_y();
+            return;
+        }
+
+        let step = if self.control.contains(Control::INCREMENT_MODE) {
+            32
+        } else {
+            1
+        };
+
+        self.v = self.v.wrapping_add(step) & 0x7FFF;
+    }
+
+    /* Specifies whether the beam is on a scanline being rendered */
+    fn is_rendering_in_progress(&self) -> bool {
+        let is_rendered_scanline = self.scanline < FRAME_HEIGHT as u16
+            || self.scanline == self.region.pre_render_scanline();
+
+        is_rendered_scanline && self.is_rendering_enabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{
+        cartridge::Cartridge,
+        mapper::{CHR_BANK_SIZE, PRG_BANK_SIZE},
+    };
+    use std::rc::Rc;
+
+    /* PPU of an NROM cartridge, with the NMI enabled */
+    fn get_ppu() -> PPU {
+        let mut bytes = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        bytes.resize(16 + PRG_BANK_SIZE + CHR_BANK_SIZE, 0);
+        let cartridge = Rc::new(Cartridge::new(&bytes).unwrap());
+
+        let mut ppu = PPU::new(PpuBus::new(cartridge));
+        ppu.set_warm_up(false);
+        ppu.write(PPUCTRL, Control::NMI_ENABLE.bits());
+        ppu
+    }
+
+    /* Runs the PPU until it is about to render the given dot of the first vertical blank scanline */
+    fn clock_until_vblank_dot(ppu: &mut PPU, dot: u16) {
+        while ppu.scanline() != Region::Ntsc.vblank_start_scanline() || ppu.dot() != dot {
+            ppu.clock();
+        }
+    }
+
+    /* Runs the PPU through the rest of the vertical blank scanline */
+    fn clock_through_vblank_scanline(ppu: &mut PPU) {
+        while ppu.scanline() == Region::Ntsc.vblank_start_scanline() {
+            ppu.clock();
+        }
+    }
+
+    #[test]
+    fn vblank_raises_the_nmi_without_a_racing_read() {
+        let mut ppu = get_ppu();
+        clock_until_vblank_dot(&mut ppu, 10);
+
+        assert!(ppu.read(PPUSTATUS) & Status::VERTICAL_BLANK.bits() != 0);
+        assert!(ppu.poll_nmi());
+    }
+
+    #[test]
+    fn read_right_before_vblank_clears_the_flag_and_the_nmi() {
+        let mut ppu = get_ppu();
+        clock_until_vblank_dot(&mut ppu, 1);
+
+        assert_eq!(ppu.read(PPUSTATUS) & Status::VERTICAL_BLANK.bits(), 0);
+        clock_through_vblank_scanline(&mut ppu);
+
+        assert_eq!(ppu.read(PPUSTATUS) & Status::VERTICAL_BLANK.bits(), 0);
+        assert!(!ppu.poll_nmi());
+    }
+
+    #[test]
+    fn read_as_vblank_starts_sees_the_flag_but_cancels_the_nmi() {
+        for dot in [2, 3] {
+            let mut ppu = get_ppu();
+            clock_until_vblank_dot(&mut ppu, dot);
+
+            assert!(ppu.read(PPUSTATUS) & Status::VERTICAL_BLANK.bits() != 0);
+            clock_through_vblank_scanline(&mut ppu);
+
+            assert!(!ppu.poll_nmi());
+        }
+    }
 }
@@ -0,0 +1,35 @@
+/*
+ * Scroll position decoded from a VRAM address and the fine X
+ * register, as used by the PPU to draw a scanline
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScrollState {
+    pub coarse_x: u8,
+    pub coarse_y: u8,
+    pub fine_x: u8,
+    pub fine_y: u8,
+    pub nametable: u8,
+}
+
+impl ScrollState {
+    /* Decodes the loopy layout of a VRAM address: yyy NN YYYYY XXXXX */
+    pub fn new(address: u16, fine_x: u8) -> Self {
+        Self {
+            coarse_x: (address & 0x001F) as u8,
+            coarse_y: ((address >> 5) & 0x001F) as u8,
+            fine_x: fine_x & 0x07,
+            fine_y: ((address >> 12) & 0x07) as u8,
+            nametable: ((address >> 10) & 0x03) as u8,
+        }
+    }
+
+    /* Horizontal position inside the 512 pixels wide nametables area */
+    pub fn x(&self) -> usize {
+        (self.nametable as usize & 0x01) * 256 + self.coarse_x as usize * 8 + self.fine_x as usize
+    }
+
+    /* Vertical position inside the 480 pixels high nametables area */
+    pub fn y(&self) -> usize {
+        (self.nametable as usize >> 1) * 240 + self.coarse_y as usize * 8 + self.fine_y as usize
+    }
+}
@@ -0,0 +1,36 @@
+/* Number of sprites held by the OAM */
+pub const OAM_SPRITES: usize = 64;
+
+/*
+ * A single sprite entry of the OAM, decoded from its 4 bytes:
+ * Y position, tile index, attributes and X position
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sprite {
+    pub index: u8,
+    pub x: u8,
+    pub y: u8,
+    pub tile: u8,
+    pub palette: u8,
+    pub behind_background: bool,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Sprite {
+    /* Decodes the 4 bytes of the sprite at the given OAM index */
+    pub fn new(index: u8, bytes: &[u8]) -> Self {
+        let attribute = bytes[2];
+
+        Self {
+            index,
+            y: bytes[0],
+            tile: bytes[1],
+            x: bytes[3],
+            palette: attribute & 0x03,
+            behind_background: attribute & 0x20 != 0,
+            flip_horizontal: attribute & 0x40 != 0,
+            flip_vertical: attribute & 0x80 != 0,
+        }
+    }
+}
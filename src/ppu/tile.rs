@@ -0,0 +1,37 @@
+/* Size in bytes of a single 8x8 tile inside the pattern tables */
+pub const TILE_SIZE: usize = 16;
+
+/*
+ * A tile is stored as two bitplanes of 8 bytes each, the first
+ * holding the low bit and the second one the high bit of every
+ * pixel. Each byte is a row of 8 pixels, leftmost pixel first.
+ */
+
+/* Reads a single pixel value (0-3) out of the two bitplanes of a tile row */
+pub fn get_row_pixel(lo: u8, hi: u8, column: u16) -> u8 {
+    let bit = 7 - column;
+
+    ((hi >> bit) & 0x01) << 1 | ((lo >> bit) & 0x01)
+}
+
+/* Decodes a tile row into its 8 pixel values */
+pub fn decode_row(lo: u8, hi: u8) -> [u8; 8] {
+    let mut row = [0; 8];
+
+    for (column, pixel) in row.iter_mut().enumerate() {
+        *pixel = get_row_pixel(lo, hi, column as u16);
+    }
+
+    row
+}
+
+/* Decodes the 16 bytes of a tile into its 8x8 pixel values */
+pub fn decode_tile(bytes: &[u8; TILE_SIZE]) -> [[u8; 8]; 8] {
+    let mut tile = [[0; 8]; 8];
+
+    for (y, row) in tile.iter_mut().enumerate() {
+        *row = decode_row(bytes[y], bytes[y + 8]);
+    }
+
+    tile
+}
@@ -0,0 +1,140 @@
+use crate::errors::{AppError, AppResult};
+
+/*
+ * Serializes the state of the emulated devices into a flat
+ * buffer of little-endian values. Devices write their fields
+ * in a fixed order and read them back in that same order.
+ */
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    /* Initializes a new empty StateWriter */
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    /* Writing a single byte */
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /* Writing a 16-bit value */
+    pub fn write_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /* Writing a 64-bit value */
+    pub fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /* Writing a flag as a single byte */
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    /* Writing a block of bytes, prefixed by its length */
+    pub fn write_bytes(&mut self, value: &[u8]) {
+        self.write_u64(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    /* The serialized state */
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/*
+ * Reads back the values written by a StateWriter. Running out
+ * of data or finding a block of an unexpected size means the
+ * state was not produced by the same devices.
+ */
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    /* Initializes a new StateReader over a serialized state */
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /* Reading a single byte */
+    pub fn read_u8(&mut self) -> AppResult<u8> {
+        Ok(self.get_next_bytes(1)?[0])
+    }
+
+    /* Reading a 16-bit value */
+    pub fn read_u16(&mut self) -> AppResult<u16> {
+        let bytes = self.get_next_bytes(2)?;
+
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /* Reading a 64-bit value */
+    pub fn read_u64(&mut self) -> AppResult<u64> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.get_next_bytes(8)?);
+
+        Ok(u64::from_le_bytes(value))
+    }
+
+    /* Reading a flag stored as a single byte */
+    pub fn read_bool(&mut self) -> AppResult<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    /* Reading a block of bytes, which must fill the destination exactly */
+    pub fn read_bytes(&mut self, destination: &mut [u8]) -> AppResult<()> {
+        if self.read_u64()? != destination.len() as u64 {
+            return Err(AppError::InvalidSaveState);
+        }
+
+        destination.copy_from_slice(self.get_next_bytes(destination.len())?);
+
+        Ok(())
+    }
+
+    /* Reading a block of bytes of a fixed length */
+    pub fn read_array<const N: usize>(&mut self) -> AppResult<[u8; N]> {
+        let mut array = [0; N];
+        self.read_bytes(&mut array)?;
+
+        Ok(array)
+    }
+
+    /* Reading a block of bytes of any length */
+    pub fn read_vec(&mut self) -> AppResult<Vec<u8>> {
+        let length = self.read_u64()? as usize;
+
+        Ok(self.get_next_bytes(length)?.to_vec())
+    }
+
+    /* Specifies whether the whole state was consumed */
+    pub fn is_finished(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn get_next_bytes(&mut self, length: usize) -> AppResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(AppError::InvalidSaveState)?;
+
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+}